@@ -1,6 +1,8 @@
-use clap::Parser;
-use clover::{Clover, Program};
-use clover_std::clover_std_inject_to;
+use clap::{Parser, Subcommand, ValueEnum};
+use clover::{Clover, CompileCache, Program};
+use clover::debug::{CompileErrorList, RuntimeError};
+use color_print::ceprintln;
+use clover_std::{clover_std_inject_to, clover_std_inject_modules};
 use serde::{Deserialize, Serialize};
 
 use std::error::Error;
@@ -8,6 +10,8 @@ use std::fmt;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::process::exit;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
 
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -92,8 +96,11 @@ fn setup() -> Result<(), Box<dyn Error>> {
 }
 
 #[derive(Parser, Debug)]
-#[clap(version)]
+#[clap(version, subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Compile input file
     #[clap(short, long, action)]
     compile: bool,
@@ -102,16 +109,173 @@ struct Args {
     #[clap(short, long = "output", value_parser)]
     output_filename: Option<String>,
 
+    /// Fail when the source has compile warnings
+    #[clap(long, action)]
+    deny_warnings: bool,
+
+    /// How compile errors and warnings are printed, json is one array of all diagnostics and nothing else is printed about them
+    #[clap(long, value_enum, default_value = "text")]
+    error_format: ErrorFormat,
+
+    /// Inject only these std modules (comma separated, such as Math,Array) instead of all of them
+    #[clap(long = "std", value_parser, value_delimiter = ',')]
+    std_modules: Option<Vec<String>>,
+
+    /// Run again every time the source or a file it includes changes
+    #[clap(short, long, action)]
+    watch: bool,
+
     /// Source filename to run/compile
-    #[clap(value_parser)]
-    pub filename: String,
+    #[clap(value_parser, required = true)]
+    pub filename: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ErrorFormat {
+    Text,
+    Json
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the bytecode of a compiled (.lucky) or source file
+    Disassemble {
+        #[clap(value_parser)]
+        filename: String
+    }
+}
+
+
+/// Prints the warnings of every file to stderr, returns the number of warnings.
+fn print_warnings(warnings: &[CompileErrorList], error_format: ErrorFormat) -> usize {
+    let count = warnings.iter().map(|list| list.errors.len()).sum();
+
+    match error_format {
+        ErrorFormat::Json if count > 0 => eprintln!("{}", CompileErrorList::lists_to_json(warnings)),
+        ErrorFormat::Json => {},
+        ErrorFormat::Text => {
+            for list in warnings {
+                for warning in list.errors.iter() {
+                    ceprintln!("<yellow>{}: {}</>", list.filename, warning);
+                };
+            };
+        }
+    };
+
+    count
+}
+
+// stderr is one json document in json mode, the exit status tells that warnings were denied
+fn print_denied_warnings(warning_count: usize, error_format: ErrorFormat) {
+    if error_format == ErrorFormat::Text {
+        ceprintln!("<red>error: {} warning(s) and --deny-warnings is set</>", warning_count);
+    };
+}
+
+fn print_compile_error(error: &CompileErrorList, error_format: ErrorFormat) {
+    match error_format {
+        ErrorFormat::Text => ceprintln!("<red>error: {}</>", error),
+        ErrorFormat::Json => eprintln!("{}", error.to_json())
+    };
+}
+
+fn run(program: Program, std_modules: Option<&[String]>) -> Result<(), RuntimeError> {
+    let mut env = program.into();
+
+    match std_modules {
+        Some(names) => {
+            let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+            clover_std_inject_modules(&mut env, &names).expect("std modules are checked before running");
+        },
+        None => clover_std_inject_to(&mut env)
+    };
+
+    // the stack of the error needs the env to be printed
+    env.execute().map(|_| ()).inspect_err(|error| {
+        if error.exit_code.is_none() {
+            ceprintln!("<red>error: {}</>", env.stack_trace(error));
+        };
+    })
+}
+
+// how often the files are checked, and how long they must stay unchanged before running again
+// (editors often write a file more than once when saving)
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Compiles and runs the file every time it or a file it includes changes, until the process is stopped.
+/// Errors are printed and watching goes on.
+fn watch(pie: &Clover, filename: &str, args: &Args) -> ! {
+    let mut cache = CompileCache::new();
+    let mut files = vec![ filename.to_string() ];
+
+    loop {
+        match pie.compile_file_with_warnings_and_cache(filename, &mut cache) {
+            Ok((program, warnings)) => {
+                // keep the files of the last successful compile, a broken include still has to be watched
+                if let Some(file_info) = program.file_info.as_ref() {
+                    files = file_info.filenames.clone();
+                };
+
+                let warning_count = print_warnings(&warnings, args.error_format);
+
+                if args.deny_warnings && warning_count > 0 {
+                    print_denied_warnings(warning_count, args.error_format);
+                } else if let Err(RuntimeError { exit_code: Some(exit_code), .. }) = run(program, args.std_modules.as_deref()) {
+                    ceprintln!("<yellow>exited with code {}</>", exit_code);
+                };
+            },
+            Err(error) => print_compile_error(&error, args.error_format)
+        };
+
+        if args.error_format == ErrorFormat::Text {
+            ceprintln!("<cyan>watching {} file(s) for changes</>", files.len());
+        };
+        wait_for_change(&files);
+    }
 }
 
+fn modified_times(files: &[String]) -> Vec<Option<SystemTime>> {
+    files.iter().map(|file| std::fs::metadata(file).and_then(|metadata| metadata.modified()).ok()).collect()
+}
+
+fn wait_for_change(files: &[String]) {
+    let mut times = modified_times(files);
+
+    while modified_times(files) == times {
+        sleep(WATCH_INTERVAL);
+    };
+
+    loop {
+        let current = modified_times(files);
+
+        if current == times {
+            return;
+        };
+
+        times = current;
+        sleep(WATCH_DEBOUNCE);
+    }
+}
 
 /// Entry point of the program
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
+    if let Some(Command::Disassemble { filename }) = &args.command {
+        let program = if filename.ends_with(".lucky") {
+            Program::deserialize(&mut File::open(filename)?)?
+        } else {
+            Clover::new().compile_file(filename)?
+        };
+
+        print!("{}", program.disassemble());
+        return Ok(());
+    }
+
+    // required unless there is a subcommand
+    let filename: String = args.filename.clone().unwrap();
+
     // Setup if not exist
     let config_file = std::path::Path::new("config.json");
     if !config_file.exists() {
@@ -127,16 +291,39 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     // Load config if specified
-    let _config = if let Some(config_file) = args.filename.strip_suffix(".json") {
+    let _config = if let Some(config_file) = filename.strip_suffix(".json") {
         Some(load_config(config_file)?)
     } else {
        // Load config if not specified
        Some(load_config("config.json")?)
     };
 
-    let pie = Clover::new();
+    if let Some(names) = &args.std_modules {
+        let modules: Vec<&str> = clover_std::MODULES.iter().map(|(module, _)| *module).collect();
 
-    let filename: String = args.filename.clone();
+        if let Some(name) = names.iter().find(|name| !modules.contains(&name.as_str())) {
+            println!("Unknown std module {}, the modules are {}.", name, modules.join(", "));
+            exit(-1);
+        }
+    }
+
+    let mut pie = Clover::new();
+
+    // warn about locals, functions and models hiding what is injected
+    pie.add_native_name("print");
+    match &args.std_modules {
+        Some(names) => names.iter().for_each(|name| pie.add_native_name(name)),
+        None => clover_std::MODULES.iter().for_each(|(name, _)| pie.add_native_name(name))
+    };
+
+    if args.watch {
+        if args.compile || filename.ends_with(".lucky") {
+            println!("Can only watch a source file when running it.");
+            exit(-1);
+        }
+
+        watch(&pie, &filename, &args);
+    }
 
     let program = if filename.ends_with(".lucky") {
         if args.compile {
@@ -145,14 +332,28 @@ fn main() -> Result<(), Box<dyn Error>> {
             exit(-1);
         }
 
-        let mut file = File::open(filename)?;
+        let mut file = File::open(&filename)?;
         Program::deserialize(&mut file)?
     } else {
-        pie.compile_file(filename.as_str())?
+        let (program, warnings) = match pie.compile_file_with_warnings(filename.as_str()) {
+            Ok(result) => result,
+            Err(error) => {
+                print_compile_error(&error, args.error_format);
+                exit(1);
+            }
+        };
+        let warning_count = print_warnings(&warnings, args.error_format);
+
+        if args.deny_warnings && warning_count > 0 {
+            print_denied_warnings(warning_count, args.error_format);
+            exit(1);
+        };
+
+        program
     };
 
     if args.compile {
-        let output_filename: String = args.output_filename.unwrap_or(if args.filename.ends_with("luck") { args.filename + "y" } else { args.filename + ".lucky" });
+        let output_filename: String = args.output_filename.unwrap_or(if filename.ends_with("luck") { filename + "y" } else { filename + ".lucky" });
 
         let mut file = File::create(output_filename)?;
         // time the function
@@ -163,11 +364,10 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("Compiled in {}ns", duration.as_nanos()); //.as_millis() #ms
 
     } else {
-        let mut env = program.into();
-
-        clover_std_inject_to(&mut env);
-
-        env.execute()?;
+        // IO.exit stops the script and a runtime error is printed by run, the process ends here
+        if let Err(error) = run(program, args.std_modules.as_deref()) {
+            exit(error.exit_code.unwrap_or(1));
+        };
     }
 
     Ok(())
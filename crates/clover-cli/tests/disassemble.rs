@@ -0,0 +1,36 @@
+use std::fs;
+use std::process::Command;
+
+const SOURCE: &str = "function main()\n    local greeting = \"hello\"\n    greeting + 1000\nend\n";
+
+#[test]
+fn disassemble_compiled_file() {
+    let directory = std::env::temp_dir().join(format!("clover_cli_disassemble_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("main.luck"), SOURCE).unwrap();
+
+    let compiled = Command::new(env!("CARGO_BIN_EXE_clover"))
+        .args([ "--compile", "main.luck" ])
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+    assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_clover"))
+        .args([ "disassemble", "main.lucky" ])
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+
+    fs::remove_dir_all(&directory).unwrap();
+
+    let listing = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // compiled files have no names, functions are listed by index
+    assert!(listing.contains("function Function(0)"), "{}", listing);
+    assert!(listing.contains("PushConstant"), "{}", listing);
+    assert!(listing.contains("\"hello\""), "{}", listing);
+    assert!(listing.contains("; +"), "{}", listing);
+    assert!(listing.contains("Return"), "{}", listing);
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::process::{Command, Output};
+
+// the cli writes config.json to the working directory, so every run gets its own one
+fn run(name: &str, files: &[ (&str, &str) ], arguments: &[ &str ]) -> Output {
+    let directory = std::env::temp_dir().join(format!("clover_cli_error_format_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for (filename, source) in files {
+        fs::write(directory.join(filename), source).unwrap();
+    };
+
+    let output = Command::new(env!("CARGO_BIN_EXE_clover"))
+        .args([ "--error-format", "json" ])
+        .args(arguments)
+        .arg("main.luck")
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+
+    fs::remove_dir_all(&directory).unwrap();
+    output
+}
+
+#[test]
+fn json_errors() {
+    let output = run("error", &[ ("main.luck", "function main()\n    local a = 1\n    a +\nend\n") ], &[]);
+    assert!(!output.status.success());
+
+    let diagnostics: serde_json::Value = serde_json::from_str(String::from_utf8_lossy(&output.stderr).trim()).unwrap();
+    let diagnostic = &diagnostics[0];
+
+    assert_eq!(diagnostic["filename"], "main.luck");
+    assert_eq!(diagnostic["line"], 4);
+    assert_eq!(diagnostic["column"], 1);
+    assert_eq!(diagnostic["severity"], "error");
+    assert_eq!(diagnostic["message"], "Unexpect token when parse expression");
+}
+
+#[test]
+fn json_warnings() {
+    let output = run("warning", &[ ("main.luck", "function main()\n    local unused = 1\nend\n") ], &[]);
+    assert!(output.status.success());
+
+    let diagnostics: serde_json::Value = serde_json::from_str(String::from_utf8_lossy(&output.stderr).trim()).unwrap();
+
+    assert_eq!(diagnostics[0]["severity"], "warning");
+    assert_eq!(diagnostics[0]["line"], 2);
+    assert_eq!(diagnostics[0]["message"], "local variable [unused] is never used");
+}
+
+#[test]
+fn one_document_for_all_files() {
+    let files = [
+        ("main.luck", "include helper from \"./helper.luck\"\n\nfunction main()\n    local unused = 1\n    helper()\nend\n"),
+        ("helper.luck", "public function helper()\n    local unused = 1\nend\n")
+    ];
+    let output = run("files", &files, &[ "--deny-warnings" ]);
+    assert!(!output.status.success());
+
+    // nothing but the diagnostics, not even the denied warnings message
+    let diagnostics: serde_json::Value = serde_json::from_str(String::from_utf8_lossy(&output.stderr).trim()).unwrap();
+    let filenames: Vec<&str> = diagnostics.as_array().unwrap().iter().map(|diagnostic| diagnostic["filename"].as_str().unwrap()).collect();
+
+    assert_eq!(filenames.len(), 2);
+    assert!(filenames.iter().any(|filename| filename.ends_with("helper.luck")), "{:?}", filenames);
+    assert!(filenames.contains(&"main.luck"), "{:?}", filenames);
+}
@@ -0,0 +1,31 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const SOURCE: &str = "function divide(a, b)\n    a / b\nend\n\nfunction main()\n    divide(1, 0)\nend\n";
+
+// the cli writes config.json to the working directory, so every run gets its own one
+fn run(name: &str) -> Output {
+    let directory: PathBuf = std::env::temp_dir().join(format!("clover_cli_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("main.luck"), SOURCE).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_clover"))
+        .arg("main.luck")
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+
+    fs::remove_dir_all(&directory).unwrap();
+    output
+}
+
+#[test]
+fn error_location() {
+    let output = run("runtime_error");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("main.luck:2:7: divide by zero"), "{}", stderr);
+    assert!(stderr.contains("    at main.luck:2:7 in divide\n    at main.luck:6:11 in main"), "{}", stderr);
+}
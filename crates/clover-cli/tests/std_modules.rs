@@ -0,0 +1,38 @@
+use std::fs;
+use std::process::{Command, Output};
+
+// the cli writes config.json to the working directory, so every run gets its own one
+fn run(name: &str, source: &str, arguments: &[ &str ]) -> Output {
+    let directory = std::env::temp_dir().join(format!("clover_cli_std_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("main.luck"), source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_clover"))
+        .args(arguments)
+        .arg("main.luck")
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+
+    fs::remove_dir_all(&directory).unwrap();
+    output
+}
+
+#[test]
+fn only_picked_modules() {
+    let output = run("math", "function main()\n    print(Math.pow(2, 3))\nend\n", &[ "--std", "Math,Array" ]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().last(), Some("8"));
+
+    let output = run("io", "function main()\n    print(Math.pow(2, 3))\n    IO.readline()\nend\n", &[ "--std", "Math" ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("[IO]"), "{}", stderr);
+}
+
+#[test]
+fn unknown_module() {
+    let output = run("unknown", "function main()\nend\n", &[ "--std", "Math,Network" ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Unknown std module Network"));
+}
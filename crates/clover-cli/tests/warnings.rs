@@ -0,0 +1,56 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const SOURCE: &str = "function main()\n    local unused = 1\n    true\nend\n";
+
+// the cli writes config.json to the working directory, so every run gets its own one
+fn run(name: &str, source: &str, arguments: &[ &str ]) -> Output {
+    let directory: PathBuf = std::env::temp_dir().join(format!("clover_cli_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("main.luck"), source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_clover"))
+        .args(arguments)
+        .arg("main.luck")
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+
+    fs::remove_dir_all(&directory).unwrap();
+    output
+}
+
+#[test]
+fn warnings_are_printed() {
+    let output = run("warn", SOURCE, &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("local variable [unused] is never used"), "{}", stderr);
+}
+
+#[test]
+fn deny_warnings() {
+    let output = run("deny", SOURCE, &[ "--deny-warnings" ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("local variable [unused] is never used"), "{}", stderr);
+    assert!(stderr.contains("--deny-warnings"), "{}", stderr);
+}
+
+#[test]
+fn shadowed_natives() {
+    let source = "function Math()\n    1\nend\n\nfunction main()\n    local print = Math()\n    print\nend\n";
+
+    let output = run("shadow", source, &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[Math] shadows the native model with the same name"), "{}", stderr);
+    assert!(stderr.contains("[print] shadows the native model with the same name"), "{}", stderr);
+
+    // only the picked modules are injected
+    let output = run("shadow_picked", source, &[ "--std", "Array" ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("[Math] shadows"), "{}", stderr);
+}
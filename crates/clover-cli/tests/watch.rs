@@ -0,0 +1,97 @@
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+fn source(run: usize) -> String {
+    format!("function main()\n    print(\"run {}\")\nend\n", run)
+}
+
+// starts watching main.luck in a directory of its own, the lines printed by the cli come through the receiver
+fn watch(name: &str) -> (Child, Receiver<String>, PathBuf) {
+    let directory = std::env::temp_dir().join(format!("clover_cli_watch_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("main.luck"), source(1)).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_clover"))
+        .args([ "--watch", "main.luck" ])
+        .current_dir(&directory)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let (sender, receiver) = channel();
+    let stdout = child.stdout.take().unwrap();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            };
+        };
+    });
+
+    (child, receiver, directory)
+}
+
+fn wait_for(receiver: &Receiver<String>, expected: &str) -> bool {
+    loop {
+        match receiver.recv_timeout(Duration::from_secs(10)) {
+            Ok(line) if line == expected => return true,
+            Ok(_) => continue,
+            Err(_) => return false
+        };
+    }
+}
+
+fn stop(mut child: Child, directory: PathBuf) {
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn runs_again_on_change() {
+    let (child, receiver, directory) = watch("change");
+
+    let first = wait_for(&receiver, "run 1");
+
+    // make sure the modified time is not the same as the first write
+    thread::sleep(Duration::from_millis(50));
+    fs::write(directory.join("main.luck"), source(2)).unwrap();
+
+    let second = first && wait_for(&receiver, "run 2");
+
+    stop(child, directory);
+
+    assert!(first, "the script did not run");
+    assert!(second, "the script did not run again after the change");
+}
+
+#[test]
+fn writes_close_together_run_once() {
+    let (child, receiver, directory) = watch("debounce");
+
+    let first = wait_for(&receiver, "run 1");
+
+    // an editor saving twice, the second write is well within WATCH_DEBOUNCE of the first one
+    thread::sleep(Duration::from_millis(50));
+    fs::write(directory.join("main.luck"), source(2)).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    fs::write(directory.join("main.luck"), source(3)).unwrap();
+
+    let mut runs = Vec::new();
+    while let Ok(line) = receiver.recv_timeout(Duration::from_secs(2)) {
+        if line.starts_with("run ") {
+            runs.push(line);
+        };
+    };
+
+    stop(child, directory);
+
+    assert!(first, "the script did not run");
+    assert_eq!(runs, vec![ "run 3".to_string() ]);
+}
@@ -1,5 +1,6 @@
-use clover::{Env, Object, NativeModel};
+use clover::{Env, Object, NativeModel, Reference};
 use clover::debug::RuntimeError;
+use clover::helper::MAX_ALLOCATION_LENGTH;
 
 #[derive(Debug)]
 pub struct Array;
@@ -9,6 +10,16 @@ impl NativeModel for Array {
         match key {
             "push" => Ok(Object::NativeFunction(push)),
             "pop" => Ok(Object::NativeFunction(pop)),
+            "contains" => Ok(Object::NativeFunction(contains)),
+            "index_of" => Ok(Object::NativeFunction(index_of)),
+            "insert" => Ok(Object::NativeFunction(insert)),
+            "remove" => Ok(Object::NativeFunction(remove)),
+            "set" => Ok(Object::NativeFunction(set)),
+            "clone" => Ok(Object::NativeFunction(clone)),
+            "reverse" => Ok(Object::NativeFunction(reverse)),
+            "concat" => Ok(Object::NativeFunction(concat)),
+            "fill" => Ok(Object::NativeFunction(fill)),
+            "range" => Ok(Object::NativeFunction(range)),
             _ => Ok(Object::Null)
         }
     }
@@ -26,6 +37,8 @@ pub fn push(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeErr
         _ => return Err(RuntimeError::new("First parameter must be an array", env.last_position()))
     };
 
+    env.ensure_array_mutable(&array)?;
+
     // Append the remaining parameters to the array
     for value in &parameters[1..] {
         array.borrow_mut().push(value.clone());
@@ -42,8 +55,386 @@ pub fn pop(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeErro
     let array = parameters[0].clone();
 
     if let Object::Array(array) = array {
+        env.ensure_array_mutable(&array)?;
         Ok(array.borrow_mut().pop().unwrap_or(Object::Null))
     } else {
         Err(RuntimeError::new("First parameter must be an array", env.last_position()))
     }
 }
+
+fn array_and_value(env: &Env, parameters: &[ Object ]) -> Result<(Reference<Vec<Object>>, Object), RuntimeError> {
+    if parameters.len() != 2 {
+        return Err(RuntimeError::new(&format!("except 2 parameters, got {}", parameters.len()), env.last_position()));
+    }
+
+    match &parameters[0] {
+        Object::Array(array) => Ok((array.clone(), parameters[1].clone())),
+        _ => Err(RuntimeError::new("First parameter must be an array", env.last_position()))
+    }
+}
+
+// items are compared as hosts compare objects, a number only equals a number of the same type
+pub fn contains(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let (array, value) = array_and_value(env, parameters)?;
+
+    let found = array.borrow().contains(&value);
+    Ok(Object::Boolean(found))
+}
+
+// index of the first equal item, -1 when not found
+pub fn index_of(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let (array, value) = array_and_value(env, parameters)?;
+
+    let index = array.borrow().iter().position(|item| *item == value);
+    Ok(Object::Integer(index.map_or(-1, |index| index as i64)))
+}
+
+// negative index counts from the end, -1 is the last item, None when out of 0..=max
+fn resolve_index(index: &Object, length: usize, max: usize) -> Option<usize> {
+    let index = match index {
+        Object::Integer(index) if *index < 0 => *index + length as i64,
+        Object::Integer(index) => *index,
+        _ => return None
+    };
+
+    if index >= 0 && index as usize <= max { Some(index as usize) } else { None }
+}
+
+// insert(array, index, value) - value is at the index after insert, an index equal to length appends,
+// an index out of range is an error
+pub fn insert(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() != 3 {
+        return Err(RuntimeError::new(&format!("except 3 parameters, got {}", parameters.len()), env.last_position()));
+    }
+
+    let array = match &parameters[0] {
+        Object::Array(array) => array.clone(),
+        _ => return Err(RuntimeError::new("First parameter must be an array", env.last_position()))
+    };
+
+    env.ensure_array_mutable(&array)?;
+
+    let length = array.borrow().len();
+    let index = resolve_index(&parameters[1], length, length).ok_or_else(|| RuntimeError::new("index out of range", env.last_position()))?;
+
+    array.borrow_mut().insert(index, parameters[2].clone());
+
+    Ok(Object::Array(array))
+}
+
+// remove(array, index) - returns the removed item, an index out of range is an error
+pub fn remove(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let (array, index) = array_and_value(env, parameters)?;
+    env.ensure_array_mutable(&array)?;
+
+    let length = array.borrow().len();
+    let index = match length {
+        0 => None,
+        _ => resolve_index(&index, length, length - 1)
+    }.ok_or_else(|| RuntimeError::new("index out of range", env.last_position()))?;
+
+    let removed = array.borrow_mut().remove(index);
+    Ok(removed)
+}
+
+// set(array, index, value, grow) - like array[index] = value, but a negative index counts from the end,
+// and an index past the end grows the array (the gap is null) when grow is true instead of being an error
+pub fn set(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() != 3 && parameters.len() != 4 {
+        return Err(RuntimeError::new(&format!("except 3 or 4 parameters, got {}", parameters.len()), env.last_position()));
+    }
+
+    let array = match &parameters[0] {
+        Object::Array(array) => array.clone(),
+        _ => return Err(RuntimeError::new("First parameter must be an array", env.last_position()))
+    };
+
+    let grow = match parameters.get(3) {
+        None | Some(Object::Boolean(false)) => false,
+        Some(Object::Boolean(true)) => true,
+        _ => return Err(RuntimeError::new("grow must be a boolean", env.last_position()))
+    };
+
+    if !matches!(parameters[1], Object::Integer(_)) {
+        return Err(RuntimeError::new("index must be an integer", env.last_position()));
+    }
+
+    env.ensure_array_mutable(&array)?;
+
+    let length = array.borrow().len();
+    let index = match resolve_index(&parameters[1], length, usize::MAX) {
+        Some(index) if index < length => index,
+        Some(index) if grow && index < MAX_ALLOCATION_LENGTH => index,
+        Some(_) if grow => return Err(RuntimeError::new(&format!("can not grow an array to more than {} items", MAX_ALLOCATION_LENGTH), env.last_position())),
+        _ => return Err(RuntimeError::new(&format!("index {} is out of range for an array of {} items", parameters[1].to_string(), length), env.last_position()))
+    };
+
+    let mut items = array.borrow_mut();
+    if index >= length {
+        items.resize(index + 1, Object::Null);
+    };
+    items[index] = parameters[2].clone();
+
+    Ok(parameters[2].clone())
+}
+
+// returns a new array with the same items, arrays are shared by reference so use this to change an array
+// without changing the one of the caller, the copy is not frozen
+pub fn clone(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() != 1 {
+        return Err(RuntimeError::new(&format!("except 1 parameter, got {}", parameters.len()), env.last_position()));
+    }
+
+    match &parameters[0] {
+        Object::Array(array) => Ok(Object::from(array.borrow().clone())),
+        _ => Err(RuntimeError::new("parameter must be an array", env.last_position()))
+    }
+}
+
+// returns a new array, the given one is not changed
+pub fn reverse(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() != 1 {
+        return Err(RuntimeError::new(&format!("except 1 parameter, got {}", parameters.len()), env.last_position()));
+    }
+
+    match &parameters[0] {
+        Object::Array(array) => Ok(Object::from(array.borrow().iter().rev().cloned().collect::<Vec<Object>>())),
+        _ => Err(RuntimeError::new("parameter must be an array", env.last_position()))
+    }
+}
+
+// concat(a, b, ...) - returns a new array with the items of all given arrays
+pub fn concat(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let mut result = Vec::new();
+
+    for parameter in parameters {
+        match parameter {
+            Object::Array(array) => result.extend(array.borrow().iter().cloned()),
+            _ => return Err(RuntimeError::new("all parameters must be arrays", env.last_position()))
+        }
+    }
+
+    Ok(Object::from(result))
+}
+
+// fill(value, count) - every item is the same value, arrays and instances are shared, not copied
+pub fn fill(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() != 2 {
+        return Err(RuntimeError::new(&format!("except 2 parameters, got {}", parameters.len()), env.last_position()));
+    }
+
+    match &parameters[1] {
+        Object::Integer(count) if *count >= 0 && *count as u64 <= MAX_ALLOCATION_LENGTH as u64 => Ok(Object::from(vec![ parameters[0].clone(); *count as usize ])),
+        Object::Integer(count) if *count >= 0 => Err(too_many_items(env)),
+        Object::Integer(_) => Err(RuntimeError::new("count can not be negative", env.last_position())),
+        _ => Err(RuntimeError::new("count must be an integer", env.last_position()))
+    }
+}
+
+// range(start, end, step) - end is exclusive, step is 1 when not given and can be negative,
+// integers when all parameters are integers, floats otherwise
+pub fn range(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() != 2 && parameters.len() != 3 {
+        return Err(RuntimeError::new(&format!("except 2 or 3 parameters, got {}", parameters.len()), env.last_position()));
+    }
+
+    let step = parameters.get(2).cloned().unwrap_or(Object::Integer(1));
+
+    match (&parameters[0], &parameters[1], &step) {
+        (Object::Integer(start), Object::Integer(end), Object::Integer(step)) => {
+            if *step == 0 {
+                return Err(RuntimeError::new("step can not be zero", env.last_position()));
+            }
+
+            let mut result = Vec::new();
+            let mut value = *start;
+            while (*step > 0 && value < *end) || (*step < 0 && value > *end) {
+                if result.len() == MAX_ALLOCATION_LENGTH {
+                    return Err(too_many_items(env));
+                }
+                result.push(Object::Integer(value));
+                value = match value.checked_add(*step) {
+                    Some(value) => value,
+                    None => break
+                };
+            }
+
+            Ok(Object::from(result))
+        },
+        (start, end, step) => {
+            let (start, end, step) = match (as_float(start), as_float(end), as_float(step)) {
+                (Some(start), Some(end), Some(step)) => (start, end, step),
+                _ => return Err(RuntimeError::new("parameters must be numbers", env.last_position()))
+            };
+
+            if !start.is_finite() || !end.is_finite() || !step.is_finite() {
+                return Err(RuntimeError::new("parameters must be finite numbers", env.last_position()));
+            }
+            if step == 0.0 {
+                return Err(RuntimeError::new("step can not be zero", env.last_position()));
+            }
+
+            // computed from the index, adding step again and again piles up rounding errors
+            let mut result = Vec::new();
+            let mut index = 0;
+            loop {
+                let value = start + step * index as f64;
+                if !((step > 0.0 && value < end) || (step < 0.0 && value > end)) {
+                    break;
+                }
+                if result.len() == MAX_ALLOCATION_LENGTH {
+                    return Err(too_many_items(env));
+                }
+                result.push(Object::Float(value));
+                index += 1;
+            }
+
+            Ok(Object::from(result))
+        }
+    }
+}
+
+fn too_many_items(env: &Env) -> RuntimeError {
+    RuntimeError::new(&format!("can not make an array of more than {} items", MAX_ALLOCATION_LENGTH), env.last_position())
+}
+
+fn as_float(object: &Object) -> Option<f64> {
+    match object {
+        Object::Integer(value) => Some(*value as f64),
+        Object::Float(value) => Some(*value),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clover::Object;
+    use crate::helper::empty_env;
+    use super::{push, pop, contains, index_of, insert, remove, set, clone, reverse, concat, fill, range};
+
+    #[test]
+    fn find_value() {
+        let mut env = empty_env();
+        let array = Object::from(vec![ Object::from(1), Object::from("two"), Object::from(vec![ Object::from(3), Object::from(4) ]) ]);
+
+        assert!(matches!(contains(&mut env, &[ array.clone(), Object::from(1) ]).unwrap(), Object::Boolean(true)));
+        assert!(matches!(contains(&mut env, &[ array.clone(), Object::from(1.0) ]).unwrap(), Object::Boolean(false)));
+        assert!(matches!(contains(&mut env, &[ array.clone(), Object::from(5) ]).unwrap(), Object::Boolean(false)));
+
+        // a different array with the same items
+        let nested = Object::from(vec![ Object::from(3), Object::from(4) ]);
+        assert!(matches!(contains(&mut env, &[ array.clone(), nested.clone() ]).unwrap(), Object::Boolean(true)));
+        assert!(matches!(index_of(&mut env, &[ array.clone(), nested ]).unwrap(), Object::Integer(2)));
+
+        assert!(matches!(index_of(&mut env, &[ array.clone(), Object::from("two") ]).unwrap(), Object::Integer(1)));
+        assert!(matches!(index_of(&mut env, &[ array.clone(), Object::from(vec![ Object::from(3) ]) ]).unwrap(), Object::Integer(-1)));
+
+        assert!(contains(&mut env, &[ Object::from(1), Object::from(1) ]).is_err());
+
+        // arrays holding themselves do not compare forever
+        let cycle = Object::from(vec![ Object::from(1) ]);
+        push(&mut env, &[ cycle.clone(), cycle.clone() ]).unwrap();
+        let other_cycle = Object::from(vec![ Object::from(1) ]);
+        push(&mut env, &[ other_cycle.clone(), other_cycle.clone() ]).unwrap();
+        assert!(matches!(contains(&mut env, &[ Object::from(vec![ cycle ]), other_cycle ]).unwrap(), Object::Boolean(true)));
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut env = empty_env();
+        let array = Object::from(vec![ Object::from(2), Object::from(3), Object::from(4) ]);
+
+        insert(&mut env, &[ array.clone(), Object::from(0), Object::from(1) ]).unwrap();
+        insert(&mut env, &[ array.clone(), Object::from(4), Object::from(6) ]).unwrap();
+        insert(&mut env, &[ array.clone(), Object::from(-1), Object::from(5) ]).unwrap();
+        assert_eq!(array.to_string(), "[ 1, 2, 3, 4, 5, 6 ]");
+
+        assert!(matches!(remove(&mut env, &[ array.clone(), Object::from(2) ]).unwrap(), Object::Integer(3)));
+        assert!(matches!(remove(&mut env, &[ array.clone(), Object::from(-1) ]).unwrap(), Object::Integer(6)));
+        assert_eq!(array.to_string(), "[ 1, 2, 4, 5 ]");
+
+        assert!(insert(&mut env, &[ array.clone(), Object::from(5), Object::from(0) ]).is_err());
+        assert!(remove(&mut env, &[ array.clone(), Object::from(4) ]).is_err());
+        assert!(remove(&mut env, &[ array.clone(), Object::from(-5) ]).is_err());
+        assert!(remove(&mut env, &[ Object::from(Vec::new()), Object::from(0) ]).is_err());
+    }
+
+    #[test]
+    fn set_and_grow() {
+        let mut env = empty_env();
+        let array = Object::from(vec![ Object::from(1), Object::from(2), Object::from(3) ]);
+
+        set(&mut env, &[ array.clone(), Object::from(0), Object::from(10) ]).unwrap();
+        set(&mut env, &[ array.clone(), Object::from(-1), Object::from(30) ]).unwrap();
+        assert_eq!(array.to_string(), "[ 10, 2, 30 ]");
+
+        let error = set(&mut env, &[ array.clone(), Object::from(5), Object::from(6) ]).unwrap_err();
+        assert_eq!(error.message, "index 5 is out of range for an array of 3 items");
+        assert_eq!(array.to_string(), "[ 10, 2, 30 ]");
+
+        set(&mut env, &[ array.clone(), Object::from(5), Object::from(6), Object::from(true) ]).unwrap();
+        assert_eq!(array.to_string(), "[ 10, 2, 30, null, null, 6 ]");
+
+        assert_eq!(set(&mut env, &[ array.clone(), Object::from(i64::MAX), Object::from(0), Object::from(true) ]).unwrap_err().message, "can not grow an array to more than 16777216 items");
+
+        // negative indices do not grow
+        assert!(set(&mut env, &[ array.clone(), Object::from(-7), Object::from(0), Object::from(true) ]).is_err());
+        assert!(set(&mut env, &[ array.clone(), Object::from("1"), Object::from(0) ]).is_err());
+    }
+
+    #[test]
+    fn frozen_array() {
+        let mut env = empty_env();
+        let array = Object::from(vec![ Object::from(1), Object::from(2) ]);
+
+        if let Object::Array(reference) = &array {
+            env.freeze_array(reference);
+        };
+
+        assert!(push(&mut env, &[ array.clone(), Object::from(3) ]).is_err());
+        assert!(pop(&mut env, std::slice::from_ref(&array)).is_err());
+        assert!(insert(&mut env, &[ array.clone(), Object::from(0), Object::from(0) ]).is_err());
+        assert!(remove(&mut env, &[ array.clone(), Object::from(0) ]).is_err());
+        assert_eq!(array.to_string(), "[ 1, 2 ]");
+
+        // the copy is not frozen and does not change the original
+        let copy = clone(&mut env, std::slice::from_ref(&array)).unwrap();
+        push(&mut env, &[ copy.clone(), Object::from(3) ]).unwrap();
+        assert_eq!(copy.to_string(), "[ 1, 2, 3 ]");
+        assert_eq!(array.to_string(), "[ 1, 2 ]");
+    }
+
+    #[test]
+    fn reverse_and_concat() {
+        let mut env = empty_env();
+        let array = Object::from(vec![ Object::from(1), Object::from(2), Object::from(3) ]);
+
+        let reversed = reverse(&mut env, std::slice::from_ref(&array)).unwrap();
+        assert_eq!(reversed.to_string(), "[ 3, 2, 1 ]");
+        assert_eq!(array.to_string(), "[ 1, 2, 3 ]");
+
+        let concatenated = concat(&mut env, &[ array.clone(), Object::from(Vec::new()), reversed ]).unwrap();
+        assert_eq!(concatenated.to_string(), "[ 1, 2, 3, 3, 2, 1 ]");
+
+        assert!(concat(&mut env, &[ array, Object::from(1) ]).is_err());
+    }
+
+    #[test]
+    fn fill_and_range() {
+        let mut env = empty_env();
+
+        assert_eq!(fill(&mut env, &[ Object::from(0), Object::from(5) ]).unwrap().to_string(), "[ 0, 0, 0, 0, 0 ]");
+        assert_eq!(fill(&mut env, &[ Object::from(0), Object::from(0) ]).unwrap().to_string(), "[  ]");
+        assert!(fill(&mut env, &[ Object::from(0), Object::from(-1) ]).is_err());
+
+        assert_eq!(range(&mut env, &[ Object::from(0), Object::from(10), Object::from(2) ]).unwrap().to_string(), "[ 0, 2, 4, 6, 8 ]");
+        assert_eq!(range(&mut env, &[ Object::from(3), Object::from(0), Object::from(-1) ]).unwrap().to_string(), "[ 3, 2, 1 ]");
+        assert_eq!(range(&mut env, &[ Object::from(0), Object::from(1), Object::from(0.25) ]).unwrap().to_string(), "[ 0.0, 0.25, 0.5, 0.75 ]");
+        assert!(range(&mut env, &[ Object::from(0), Object::from(10), Object::from(0) ]).is_err());
+
+        assert_eq!(fill(&mut env, &[ Object::from(0), Object::from(i64::MAX) ]).unwrap_err().message, "can not make an array of more than 16777216 items");
+        assert_eq!(range(&mut env, &[ Object::from(0), Object::from(i64::MAX) ]).unwrap_err().message, "can not make an array of more than 16777216 items");
+        assert_eq!(range(&mut env, &[ Object::from(0), Object::from(f64::INFINITY), Object::from(0.5) ]).unwrap_err().message, "parameters must be finite numbers");
+        assert!(range(&mut env, &[ Object::from(0), Object::from(1), Object::from(f64::NAN) ]).is_err());
+    }
+}
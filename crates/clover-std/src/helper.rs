@@ -14,4 +14,33 @@ pub fn expect_float(env: &Env, object: &Object) -> Result<f64, RuntimeError> {
         Object::Float(value) => Ok(*value),
         _ => Err(RuntimeError::new("can accept Float only", env.last_position()))
     }
-}
\ No newline at end of file
+}
+
+// an env without any function, for testing native functions
+#[cfg(test)]
+pub fn empty_env() -> Env {
+    use std::collections::HashMap;
+    use clover::Program;
+
+    Program {
+        models: Vec::new(),
+        functions: Vec::new(),
+        constants: Program::DEFAULT_CONSTANTS.to_vec(),
+        global_dependencies: Vec::new(),
+        local_variable_count: 0,
+        local_values: HashMap::new(),
+        entry_point: None,
+        file_info: None,
+        debug_info: None
+    }.into()
+}
+
+// value of the key in a map made by make_map, for testing native functions
+#[cfg(test)]
+pub fn map_get(map: &Object, key: &str) -> Object {
+    if let Object::NativeInstance(instance) = map {
+        instance.borrow().index_get(instance.clone(), &Object::from(key)).unwrap()
+    } else {
+        panic!("object is not a map");
+    }
+}
@@ -1,9 +1,12 @@
 use clover::{Env, Object, NativeModel};
 use clover::debug::RuntimeError;
 use clover::helper::make_reference;
+use crate::os::output_to_map;
 
 use std::io::Write;
 
+mod string;
+
 #[derive(Debug)]
 pub struct IO;
 
@@ -11,9 +14,18 @@ impl NativeModel for IO {
     fn model_get(&self, key: &str) -> Result<Object, RuntimeError> {
         match key {
             "print" => Ok(Object::NativeFunction(print)),
+            "eprint" => Ok(Object::NativeFunction(eprint)),
+            "print_no_newline" => Ok(Object::NativeFunction(print_no_newline)),
+            "print_separated" => Ok(Object::NativeFunction(print_separated)),
+            "format" => Ok(Object::NativeFunction(format)),
+            "printf" => Ok(Object::NativeFunction(printf)),
+            "flush" => Ok(Object::NativeFunction(flush)),
             "readline" => Ok(Object::NativeFunction(readline)),
             "readfile" => Ok(Object::NativeFunction(readfile)),
+            "readlines" => Ok(Object::NativeFunction(readlines)),
             "writefile" => Ok(Object::NativeFunction(writefile)),
+            "read_bytes" => Ok(Object::NativeFunction(read_bytes)),
+            "write_bytes" => Ok(Object::NativeFunction(write_bytes)),
             "appendfile" => Ok(Object::NativeFunction(appendfile)),
             "exit" => Ok(Object::NativeFunction(exit)),
             "clear" => Ok(Object::NativeFunction(clear)),
@@ -40,6 +52,11 @@ impl NativeModel for IO {
             //"length" => Ok(Object::NativeFunction(length)), // Get length of a string
             //"startswith" => Ok(Object::NativeFunction(startswith)), // Check if a string starts with another
             "endswith" => Ok(Object::NativeFunction(endswith)), // Check if a string ends with another
+            "pad_left" => Ok(Object::NativeFunction(string::pad_left)),
+            "pad_right" => Ok(Object::NativeFunction(string::pad_right)),
+            "repeat" => Ok(Object::NativeFunction(string::repeat)),
+            "index_of" => Ok(Object::NativeFunction(string::index_of)),
+            "substring" => Ok(Object::NativeFunction(string::substring)),
 
             //"push" => Ok(Object::NativeFunction(push)),
 
@@ -49,22 +66,149 @@ impl NativeModel for IO {
 }
 
 
-pub fn print(_env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
-    for object in parameters {
-        print!("{}", object.to_string());
+fn join_objects(env: &Env, parameters: &[ Object ], separator: &str, line_end: &str) -> String {
+    let mut output = String::new();
+
+    for (i, object) in parameters.iter().enumerate() {
+        if i > 0 {
+            output.push_str(separator);
+        };
+
+        output.push_str(&object.display(env.get_program()));
     };
 
-    println!();
+    output.push_str(line_end);
+
+    output
+}
+
+// write to the output of env, which is stdout unless host replaced it
+fn write_output(env: &mut Env, text: &str) -> Result<Object, RuntimeError> {
+    match env.output().write_all(text.as_bytes()) {
+        Ok(_) => Ok(Object::Null),
+        Err(error) => Err(RuntimeError::new(error.to_string().as_str(), env.last_position()))
+    }
+}
+
+pub fn print(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let text = join_objects(env, parameters, "", "\n");
+    write_output(env, &text)
+}
+
+// print to the error output of env, which is stderr unless host replaced it
+pub fn eprint(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let text = join_objects(env, parameters, "", "\n");
+
+    match env.error_output().write_all(text.as_bytes()) {
+        Ok(_) => Ok(Object::Null),
+        Err(error) => Err(RuntimeError::new(error.to_string().as_str(), env.last_position()))
+    }
+}
+
+// flushed, as text without a newline is mostly a prompt
+pub fn print_no_newline(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let text = join_objects(env, parameters, "", "");
+    write_output(env, &text)?;
+    flush(env, &[])
+}
+
+// first parameter is the separator put between values
+pub fn print_separated(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.is_empty() {
+        return Err(RuntimeError::new("No separator provided", env.last_position()));
+    }
+
+    let text = join_objects(env, &parameters[1..], &parameters[0].to_string(), "\n");
+    write_output(env, &text)
+}
+
+// replace `{}` with arguments in order and `{0}`, `{1}` with the argument at the index, `{{` and `}}` are escaped braces
+fn format_template(env: &Env, template: &str, arguments: &[ Object ]) -> Result<String, RuntimeError> {
+    let mut output = String::new();
+    let mut characters = template.chars().peekable();
+    let mut next_argument = 0;
+    let mut used_positional = false;
+
+    while let Some(character) = characters.next() {
+        match character {
+            '{' if characters.peek() == Some(&'{') => {
+                characters.next();
+                output.push('{');
+            },
+            '}' if characters.peek() == Some(&'}') => {
+                characters.next();
+                output.push('}');
+            },
+            '{' => {
+                let mut index_string = String::new();
+
+                loop {
+                    match characters.next() {
+                        Some('}') => break,
+                        Some(character) => index_string.push(character),
+                        None => { return Err(RuntimeError::new("unclosed placeholder in format template", env.last_position())); }
+                    }
+                };
+
+                let index = if index_string.is_empty() {
+                    used_positional = true;
+                    next_argument += 1;
+                    next_argument - 1
+                } else if let Ok(index) = index_string.trim().parse::<usize>() {
+                    index
+                } else {
+                    return Err(RuntimeError::new(&format!("invalid placeholder {{{}}} in format template", index_string), env.last_position()));
+                };
+
+                if let Some(argument) = arguments.get(index) {
+                    output.push_str(&argument.display(env.get_program()));
+                } else {
+                    return Err(RuntimeError::new(&format!("format template needs argument {} but only {} given", index, arguments.len()), env.last_position()));
+                }
+            },
+            '}' => { return Err(RuntimeError::new("unmatched '}' in format template", env.last_position())); },
+            _ => output.push(character)
+        }
+    };
+
+    if used_positional && next_argument != arguments.len() {
+        return Err(RuntimeError::new(&format!("format template has {} placeholders but {} arguments given", next_argument, arguments.len()), env.last_position()));
+    };
+
+    Ok(output)
+}
+
+pub fn format(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.is_empty() {
+        return Err(RuntimeError::new("No template provided", env.last_position()));
+    }
+
+    Ok(Object::from(format_template(env, &parameters[0].to_string(), &parameters[1..])?))
+}
+
+pub fn printf(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let formatted = format(env, parameters)?;
+
+    write_output(env, &formatted.to_string())
+}
 
-    Ok(Object::Null)
+// output is buffered, print_no_newline("name? ") then readline() should show the prompt before waiting
+pub fn flush(env: &mut Env, _parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    match env.output().flush() {
+        Ok(_) => Ok(Object::Null),
+        Err(error) => Err(RuntimeError::new(error.to_string().as_str(), env.last_position()))
+    }
 }
 
+// return null at the end of input, the output is flushed first so a prompt is shown before reading
 fn readline(env: &mut Env, _parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    flush(env, &[])?;
+
     let mut line = String::new();
-    if let Err(error) = std::io::stdin().read_line(&mut line) {
-        Err(RuntimeError::new(error.to_string().as_str(), env.last_position()))
-    } else {
-        Ok(Object::String(make_reference(line)))
+    match env.input().read_line(&mut line) {
+        Ok(0) => Ok(Object::Null),
+        Ok(_) => Ok(Object::String(make_reference(line))),
+        Err(error) => Err(RuntimeError::new(error.to_string().as_str(), env.last_position()))
     }
 }
 
@@ -83,6 +227,23 @@ pub fn readfile(env: &mut Env, parameters: &[ Object ]) -> Result<Object, Runtim
     }
 }
 
+// read file as an array of lines, "\n" and "\r\n" are stripped,
+// a newline at the end of file does not make an extra empty line, but empty lines before it are kept
+pub fn readlines(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.is_empty() {
+        return Err(RuntimeError::new("No file path provided", env.last_position()));
+    }
+
+    let file_path = parameters[0].to_string();
+    match std::fs::read_to_string(&file_path) {
+        Ok(contents) => Ok(Object::from(contents.lines().map(Object::from).collect::<Vec<Object>>())),
+        Err(error) => Err(RuntimeError::new(
+            format!("Failed to read file '{}': {}", file_path, error).as_str(),
+            env.last_position(),
+        )),
+    }
+}
+
 pub fn writefile(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
     if parameters.is_empty() {
         return Err(RuntimeError::new("No file path provided", env.last_position()));
@@ -99,6 +260,56 @@ pub fn writefile(env: &mut Env, parameters: &[ Object ]) -> Result<Object, Runti
     }
 }
 
+// read file as bytes
+pub fn read_bytes(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.is_empty() {
+        return Err(RuntimeError::new("No file path provided", env.last_position()));
+    }
+
+    let file_path = parameters[0].to_string();
+    match std::fs::read(&file_path) {
+        Ok(bytes) => Ok(Object::from_bytes(bytes)),
+        Err(error) => Err(RuntimeError::new(
+            format!("Failed to read file '{}': {}", file_path, error).as_str(),
+            env.last_position(),
+        )),
+    }
+}
+
+pub fn write_bytes(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() < 2 {
+        return Err(RuntimeError::new("Expected a file path and a byte array", env.last_position()));
+    }
+
+    let file_path = parameters[0].to_string();
+
+    // bytes, or an array of integers (0 - 255)
+    let bytes = if let Object::Bytes(bytes) = &parameters[1] {
+        bytes.borrow().clone()
+    } else if let Object::Array(array) = &parameters[1] {
+        let mut bytes = Vec::with_capacity(array.borrow().len());
+
+        for object in array.borrow().iter() {
+            match object {
+                Object::Integer(value) if (0..=255).contains(value) => bytes.push(*value as u8),
+                _ => { return Err(RuntimeError::new(&format!("Invalid byte value {}, expect integer 0 - 255", object.to_string()), env.last_position())); }
+            }
+        };
+
+        bytes
+    } else {
+        return Err(RuntimeError::new("Second parameter must be bytes or an array", env.last_position()));
+    };
+
+    match std::fs::write(&file_path, bytes) {
+        Ok(_) => Ok(Object::Null),
+        Err(error) => Err(RuntimeError::new(
+            format!("Failed to write to file '{}': {}", file_path, error).as_str(),
+            env.last_position(),
+        )),
+    }
+}
+
 pub fn appendfile(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
     if parameters.is_empty() {
         return Err(RuntimeError::new("No file path provided", env.last_position()));
@@ -132,16 +343,21 @@ pub fn exit(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeErr
     }
 
     match parameters[0].to_string().parse::<i32>() {
-        Ok(exit_code) => std::process::exit(exit_code),
+        // the host ends the process when it wants to, an embedding application keeps running
+        Ok(exit_code) => {
+            let _ = env.output().flush();
+            Err(RuntimeError::new_exit(exit_code, env.last_position()))
+        },
         Err(_) => Err(RuntimeError::new("Invalid exit code", env.last_position()))
     }
 }
 
-pub fn clear(_env: &mut Env, _parameters: &[ Object ]) -> Result<Object, RuntimeError> {
-    print!("\x1Bc");
-    Ok(Object::Null)
+pub fn clear(env: &mut Env, _parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    write_output(env, "\x1Bc")
 }
 
+// the command is split on whitespace, so an argument can not contain spaces or quotes,
+// use OS.run to pass the arguments as they are. returns a map with stdout, stderr and exit_code
 pub fn system(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
     if parameters.is_empty() {
         return Err(RuntimeError::new("No command provided", env.last_position()));
@@ -150,11 +366,16 @@ pub fn system(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeE
     let command = parameters[0].to_string();
     let args: Vec<String> = command.split_whitespace().map(String::from).collect();
 
+    if args.is_empty() {
+        return Err(RuntimeError::new("No command provided", env.last_position()));
+    }
+
     let output = std::process::Command::new(&args[0])
         .args(&args[1..])
         .output()
         .map_err(|error| RuntimeError::new(error.to_string().as_str(), env.last_position()))?;
-    Ok(Object::String(make_reference(String::from_utf8_lossy(&output.stdout).to_string())))
+
+    Ok(output_to_map(output))
 }
 
 // pub fn date(env: &mut env, _parameters: &[ Object ]) -> Result<Object, RuntimeError> {
@@ -192,4 +413,184 @@ pub fn to_upper(env: &mut Env, parameters: &[ Object ]) -> Result<Object, Runtim
 
     let strings: Vec<String> = parameters.iter().map(|value| value.to_string()).collect();
     Ok(Object::String(make_reference(strings[0].to_uppercase())))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use clover::Object;
+    use crate::helper::{empty_env, map_get};
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+    use crate::io::{eprint, flush, format, join_objects, print, print_no_newline, printf, read_bytes, readline, readlines, system, write_bytes};
+
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> { self.0.borrow_mut().write(buffer) }
+        fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn join() {
+        let env = empty_env();
+
+        assert_eq!(join_objects(&env, &[ Object::from("error: "), Object::from(42) ], "", "\n"), "error: 42\n");
+        assert_eq!(join_objects(&env, &[ Object::from(1), Object::from(2) ], ", ", ""), "1, 2");
+    }
+
+    #[test]
+    fn capture_output() {
+        let mut env = empty_env();
+        let buffer = Rc::new(RefCell::new(Vec::new()));
+        env.set_output(Box::new(SharedBuffer(buffer.clone())));
+
+        print(&mut env, &[ Object::from("hello "), Object::from(1) ]).unwrap();
+        print_no_newline(&mut env, &[ Object::from("a") ]).unwrap();
+        printf(&mut env, &[ Object::from("{}-{}"), Object::from(2), Object::from(3) ]).unwrap();
+
+        assert_eq!(String::from_utf8(buffer.borrow().clone()).unwrap(), "hello 1\na2-3");
+
+        // errors go to an output of their own
+        let error_buffer = Rc::new(RefCell::new(Vec::new()));
+        env.set_error_output(Box::new(SharedBuffer(error_buffer.clone())));
+
+        eprint(&mut env, &[ Object::from("failed "), Object::from(2) ]).unwrap();
+
+        assert_eq!(String::from_utf8(error_buffer.borrow().clone()).unwrap(), "failed 2\n");
+        assert_eq!(String::from_utf8(buffer.borrow().clone()).unwrap(), "hello 1\na2-3");
+    }
+
+    #[test]
+    fn exit_returns_to_host() {
+        let source = "function main()\n    print(\"before\")\n    IO.exit(3)\n    print(\"after\")\nrescue\n    print(\"rescued\")\nend\n";
+        let program = clover::Clover::new().compile_with_entry(source, "exit.luck", "main").unwrap();
+
+        let mut env: clover::Env = program.into();
+        crate::clover_std_inject_to(&mut env);
+
+        let buffer = Rc::new(RefCell::new(Vec::new()));
+        env.set_output(Box::new(SharedBuffer(buffer.clone())));
+
+        // the process is still running here, the script stopped and the rescue block did not run
+        let error = env.execute().unwrap_err();
+        assert_eq!(error.exit_code, Some(3));
+        assert_eq!(String::from_utf8(buffer.borrow().clone()).unwrap(), "before\n");
+    }
+
+    #[test]
+    fn format_placeholders() {
+        let mut env = empty_env();
+
+        let result = format(&mut env, &[ Object::from("x={} y={}"), Object::from(1), Object::from(2.5) ]).unwrap();
+        assert_eq!(result.to_string(), "x=1 y=2.5");
+
+        let result = format(&mut env, &[ Object::from("{1}-{0} {{}}"), Object::from("a"), Object::from("b") ]).unwrap();
+        assert_eq!(result.to_string(), "b-a {}");
+    }
+
+    #[test]
+    fn format_argument_count_mismatch() {
+        let mut env = empty_env();
+
+        assert!(format(&mut env, &[ Object::from("x={} y={}"), Object::from(1) ]).is_err());
+        assert!(format(&mut env, &[ Object::from("x={}"), Object::from(1), Object::from(2) ]).is_err());
+        assert!(format(&mut env, &[ Object::from("x={3}"), Object::from(1) ]).is_err());
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut env = empty_env();
+        let path = Object::from(std::env::temp_dir().join("clover_std_bytes_round_trip.bin").to_string_lossy().to_string());
+
+        let bytes = Object::from(vec![ Object::from(0), Object::from(128), Object::from(255) ]);
+        write_bytes(&mut env, &[ path.clone(), bytes ]).unwrap();
+
+        let result: Vec<u8> = read_bytes(&mut env, std::slice::from_ref(&path)).unwrap().try_into().unwrap();
+        assert_eq!(result, vec![ 0, 128, 255 ]);
+
+        write_bytes(&mut env, &[ path.clone(), Object::from_bytes(vec![ 1, 2 ]) ]).unwrap();
+        assert_eq!(read_bytes(&mut env, std::slice::from_ref(&path)).unwrap(), Object::from_bytes(vec![ 1, 2 ]));
+
+        let invalid = Object::from(vec![ Object::from(256) ]);
+        assert!(write_bytes(&mut env, &[ path, invalid ]).is_err());
+    }
+
+    #[test]
+    fn read_lines() {
+        let mut env = empty_env();
+        let path = std::env::temp_dir().join("clover_std_read_lines.txt");
+        std::fs::write(&path, "first\r\nsecond\n\nfourth\n").unwrap();
+
+        let result: Vec<Object> = readlines(&mut env, &[ Object::from(path.to_string_lossy().to_string()) ]).unwrap().try_into().unwrap();
+        let lines: Vec<String> = result.into_iter().map(|object| object.to_string()).collect();
+        assert_eq!(lines, vec![ "first", "second", "", "fourth" ]);
+    }
+
+    // writes are kept until flush, like stdout
+    struct BufferedOutput(Vec<u8>, Rc<RefCell<Vec<String>>>);
+
+    impl Write for BufferedOutput {
+        fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> { self.0.write(buffer) }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            if !self.0.is_empty() {
+                self.1.borrow_mut().push(String::from_utf8(std::mem::take(&mut self.0)).unwrap());
+            };
+            Ok(())
+        }
+    }
+
+    struct LoggedInput(std::io::Cursor<Vec<u8>>, Rc<RefCell<Vec<String>>>);
+
+    impl std::io::Read for LoggedInput {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            self.1.borrow_mut().push("<read>".to_string());
+            self.0.read(buffer)
+        }
+    }
+
+    #[test]
+    fn prompt_before_read() {
+        let mut env = empty_env();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        env.set_output(Box::new(BufferedOutput(Vec::new(), log.clone())));
+        env.set_input(Box::new(std::io::BufReader::new(LoggedInput(std::io::Cursor::new(b"clover\n".to_vec()), log.clone()))));
+
+        print(&mut env, &[ Object::from("welcome") ]).unwrap();
+        print_no_newline(&mut env, &[ Object::from("name? ") ]).unwrap();
+        assert_eq!(readline(&mut env, &[]).unwrap().to_string(), "clover\n");
+
+        print(&mut env, &[ Object::from("bye") ]).unwrap();
+        flush(&mut env, &[]).unwrap();
+
+        assert_eq!(log.borrow()[..3], [ "welcome\nname? ", "<read>", "bye\n" ]);
+    }
+
+    #[test]
+    fn read_line_until_end() {
+        let mut env = empty_env();
+        env.set_input(Box::new(std::io::Cursor::new("first\nsecond\n")));
+
+        assert_eq!(readline(&mut env, &[]).unwrap().to_string(), "first\n");
+        assert_eq!(readline(&mut env, &[]).unwrap().to_string(), "second\n");
+        assert!(readline(&mut env, &[]).unwrap().is_null());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn system_exit_code() {
+        let mut env = empty_env();
+
+        let result = system(&mut env, &[ Object::from("echo hello") ]).unwrap();
+        assert_eq!(map_get(&result, "stdout").to_string(), "hello\n");
+        assert!(matches!(map_get(&result, "exit_code"), Object::Integer(0)));
+
+        let result = system(&mut env, &[ Object::from("false") ]).unwrap();
+        assert!(matches!(map_get(&result, "exit_code"), Object::Integer(1)));
+
+        let result = system(&mut env, &[ Object::from("ls /no/such/directory") ]).unwrap();
+        assert!(!map_get(&result, "stderr").to_string().is_empty());
+        assert!(!matches!(map_get(&result, "exit_code"), Object::Integer(0)));
+    }
+}
@@ -0,0 +1,169 @@
+use clover::{Env, Object};
+use clover::debug::RuntimeError;
+use clover::helper::{make_reference, MAX_ALLOCATION_LENGTH};
+
+fn expect_string(env: &Env, object: &Object, name: &str) -> Result<String, RuntimeError> {
+    match object {
+        Object::String(value) => Ok(value.borrow().clone()),
+        _ => Err(RuntimeError::new(&format!("{} must be a String", name), env.last_position()))
+    }
+}
+
+fn expect_integer(env: &Env, object: &Object, name: &str) -> Result<i64, RuntimeError> {
+    match object {
+        Object::Integer(value) => Ok(*value),
+        _ => Err(RuntimeError::new(&format!("{} must be an Integer", name), env.last_position()))
+    }
+}
+
+// the string made by repeating value count times, in bytes up to MAX_ALLOCATION_LENGTH
+fn repeat_checked(env: &Env, value: &str, count: usize) -> Result<String, RuntimeError> {
+    match value.len().checked_mul(count) {
+        Some(length) if length <= MAX_ALLOCATION_LENGTH => Ok(value.repeat(count)),
+        _ => Err(RuntimeError::new(&format!("can not make a string longer than {} bytes", MAX_ALLOCATION_LENGTH), env.last_position()))
+    }
+}
+
+// (string, width, fill) with fill a space when not given, returns the padding to add
+fn padding(env: &Env, parameters: &[ Object ]) -> Result<(String, String), RuntimeError> {
+    if parameters.len() != 2 && parameters.len() != 3 {
+        return Err(RuntimeError::new(&format!("except 2 or 3 parameters, got {}", parameters.len()), env.last_position()));
+    };
+
+    let value = expect_string(env, &parameters[0], "string")?;
+    let width = expect_integer(env, &parameters[1], "width")?;
+
+    let fill = match parameters.get(2) {
+        Some(fill) => expect_string(env, fill, "fill")?,
+        None => " ".to_string()
+    };
+
+    if fill.chars().count() != 1 {
+        return Err(RuntimeError::new("fill must be one character", env.last_position()));
+    };
+
+    // width is counted in characters, a string longer than width is not cut
+    let count = (width.max(0) as usize).saturating_sub(value.chars().count());
+
+    Ok((value, repeat_checked(env, &fill, count)?))
+}
+
+pub fn pad_left(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let (value, padding) = padding(env, parameters)?;
+
+    Ok(Object::String(make_reference(padding + &value)))
+}
+
+pub fn pad_right(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let (value, padding) = padding(env, parameters)?;
+
+    Ok(Object::String(make_reference(value + &padding)))
+}
+
+// a count less than one gives an empty string
+pub fn repeat(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() != 2 {
+        return Err(RuntimeError::new(&format!("except 2 parameters, got {}", parameters.len()), env.last_position()));
+    };
+
+    let value = expect_string(env, &parameters[0], "string")?;
+    let count = expect_integer(env, &parameters[1], "count")?;
+
+    Ok(Object::String(make_reference(repeat_checked(env, &value, count.max(0) as usize)?)))
+}
+
+// index_of(haystack, needle) - character index of the first needle, -1 when not found
+pub fn index_of(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() != 2 {
+        return Err(RuntimeError::new(&format!("except 2 parameters, got {}", parameters.len()), env.last_position()));
+    };
+
+    let haystack = expect_string(env, &parameters[0], "haystack")?;
+    let needle = expect_string(env, &parameters[1], "needle")?;
+
+    Ok(Object::Integer(match haystack.find(&needle) {
+        Some(byte_index) => haystack[..byte_index].chars().count() as i64,
+        None => -1
+    }))
+}
+
+// negative index counts from the end, the result is clamped to 0..=length
+fn resolve_index(index: i64, length: usize) -> usize {
+    let index = if index < 0 { index + length as i64 } else { index };
+
+    index.clamp(0, length as i64) as usize
+}
+
+// substring(string, start, end) - characters from start to end (exclusive), end is the length when not given,
+// negative indices count from the end, indices out of range are clamped
+pub fn substring(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    if parameters.len() != 2 && parameters.len() != 3 {
+        return Err(RuntimeError::new(&format!("except 2 or 3 parameters, got {}", parameters.len()), env.last_position()));
+    };
+
+    let value = expect_string(env, &parameters[0], "string")?;
+    let length = value.chars().count();
+
+    let start = resolve_index(expect_integer(env, &parameters[1], "start")?, length);
+    let end = match parameters.get(2) {
+        Some(end) => resolve_index(expect_integer(env, end, "end")?, length),
+        None => length
+    };
+
+    let result: String = if start < end { value.chars().skip(start).take(end - start).collect() } else { String::new() };
+
+    Ok(Object::String(make_reference(result)))
+}
+
+#[cfg(test)]
+mod tests {
+    use clover::Object;
+    use crate::helper::empty_env;
+    use super::{pad_left, pad_right, repeat, index_of, substring};
+
+    #[test]
+    fn padding() {
+        let mut env = empty_env();
+
+        assert_eq!(pad_left(&mut env, &[ Object::from("7"), Object::from(3) ]).unwrap().to_string(), "  7");
+        assert_eq!(pad_left(&mut env, &[ Object::from("7"), Object::from(3), Object::from("0") ]).unwrap().to_string(), "007");
+        assert_eq!(pad_right(&mut env, &[ Object::from("7"), Object::from(3), Object::from(".") ]).unwrap().to_string(), "7..");
+        assert_eq!(pad_left(&mut env, &[ Object::from("1234"), Object::from(3) ]).unwrap().to_string(), "1234");
+        assert_eq!(pad_right(&mut env, &[ Object::from("7"), Object::from(-1) ]).unwrap().to_string(), "7");
+
+        assert!(pad_left(&mut env, &[ Object::from("7"), Object::from(3), Object::from("ab") ]).is_err());
+        assert!(pad_left(&mut env, &[ Object::from(7), Object::from(3) ]).is_err());
+        assert_eq!(pad_right(&mut env, &[ Object::from("7"), Object::from(i64::MAX) ]).unwrap_err().message, "can not make a string longer than 16777216 bytes");
+    }
+
+    #[test]
+    fn repeat_string() {
+        let mut env = empty_env();
+
+        assert_eq!(repeat(&mut env, &[ Object::from("ab"), Object::from(3) ]).unwrap().to_string(), "ababab");
+        assert_eq!(repeat(&mut env, &[ Object::from("ab"), Object::from(0) ]).unwrap().to_string(), "");
+        assert_eq!(repeat(&mut env, &[ Object::from("ab"), Object::from(-2) ]).unwrap().to_string(), "");
+        assert_eq!(repeat(&mut env, &[ Object::from("ab"), Object::from(i64::MAX) ]).unwrap_err().message, "can not make a string longer than 16777216 bytes");
+    }
+
+    #[test]
+    fn find_substring() {
+        let mut env = empty_env();
+
+        assert!(matches!(index_of(&mut env, &[ Object::from("hello world"), Object::from("world") ]).unwrap(), Object::Integer(6)));
+        assert!(matches!(index_of(&mut env, &[ Object::from("héllo"), Object::from("llo") ]).unwrap(), Object::Integer(2)));
+        assert!(matches!(index_of(&mut env, &[ Object::from("hello"), Object::from("xyz") ]).unwrap(), Object::Integer(-1)));
+    }
+
+    #[test]
+    fn extract_substring() {
+        let mut env = empty_env();
+        let value = Object::from("hello world");
+
+        assert_eq!(substring(&mut env, &[ value.clone(), Object::from(3), Object::from(8) ]).unwrap().to_string(), "lo wo");
+        assert_eq!(substring(&mut env, &[ value.clone(), Object::from(6) ]).unwrap().to_string(), "world");
+        assert_eq!(substring(&mut env, &[ value.clone(), Object::from(-5), Object::from(-1) ]).unwrap().to_string(), "worl");
+        assert_eq!(substring(&mut env, &[ value.clone(), Object::from(-100), Object::from(100) ]).unwrap().to_string(), "hello world");
+        assert_eq!(substring(&mut env, &[ value.clone(), Object::from(8), Object::from(3) ]).unwrap().to_string(), "");
+    }
+}
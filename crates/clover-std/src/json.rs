@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use clover::{Env, Object, NativeModel};
+use clover::debug::{Position, RuntimeError};
+use clover::helper::reference_address;
+use serde_json::{Map as JsonMap, Number, Value};
+use crate::helper::expect_parameter_count;
+use crate::map::make_map;
+
+#[derive(Debug)]
+pub struct Json;
+
+impl NativeModel for Json {
+    fn model_get(&self, key: &str) -> Result<Object, RuntimeError> {
+        match key {
+            "stringify" => Ok(Object::NativeFunction(stringify)),
+            "parse" => Ok(Object::NativeFunction(parse)),
+            _ => Ok(Object::Null)
+        }
+    }
+}
+
+// visiting holds the references being converted, json can not represent a cycle
+fn to_value(object: &Object, visiting: &mut HashSet<usize>) -> Result<Value, RuntimeError> {
+    let address = match object {
+        Object::Array(array) => Some(reference_address(array)),
+        Object::NativeInstance(instance) => Some(reference_address(instance)),
+        _ => None
+    };
+
+    if let Some(address) = address {
+        if !visiting.insert(address) {
+            return Err(RuntimeError::new("can not convert self-referential structure to json", Position::none()));
+        };
+    };
+
+    let value = match object {
+        Object::Null => Value::Null,
+        Object::Boolean(value) => Value::Bool(*value),
+        Object::Integer(value) => Value::Number(Number::from(*value)),
+        Object::Float(value) => {
+            if let Some(number) = Number::from_f64(*value) {
+                Value::Number(number)
+            } else {
+                return Err(RuntimeError::new(&format!("can not convert float [{}] to json", value), Position::none()));
+            }
+        },
+        Object::String(value) => Value::String(value.borrow().deref().clone()),
+        Object::Array(array) => Value::Array(array.borrow().iter().map(|child| to_value(child, visiting)).collect::<Result<Vec<Value>, RuntimeError>>()?),
+        Object::NativeInstance(instance) => {
+            if let Some(entries) = instance.borrow().raw_entries() {
+                let mut map = JsonMap::new();
+
+                for (key, value) in entries.iter() {
+                    map.insert(key.clone(), to_value(value, visiting)?);
+                };
+
+                Value::Object(map)
+            } else {
+                return Err(RuntimeError::new("can not convert native instance to json", Position::none()));
+            }
+        },
+        _ => {
+            return Err(RuntimeError::new("can convert null, boolean, integer, float, string, array and map to json only", Position::none()));
+        }
+    };
+
+    if let Some(address) = address {
+        visiting.remove(&address);
+    };
+
+    Ok(value)
+}
+
+fn from_value(value: &Value) -> Object {
+    match value {
+        Value::Null => Object::Null,
+        Value::Bool(value) => Object::Boolean(*value),
+        Value::Number(number) => {
+            if let Some(integer) = number.as_i64() {
+                Object::Integer(integer)
+            } else {
+                Object::Float(number.as_f64().unwrap_or(f64::NAN))
+            }
+        },
+        Value::String(value) => Object::from(value.clone()),
+        Value::Array(values) => Object::from(values.iter().map(from_value).collect::<Vec<Object>>()),
+        Value::Object(map) => {
+            let entries: HashMap<String, Object> = map.iter().map(|(key, value)| (key.clone(), from_value(value))).collect();
+            make_map(entries)
+        }
+    }
+}
+
+pub fn to_json(object: &Object) -> Result<String, RuntimeError> {
+    Ok(to_value(object, &mut HashSet::new())?.to_string())
+}
+
+pub fn from_json(source: &str) -> Result<Object, RuntimeError> {
+    match serde_json::from_str::<Value>(source) {
+        Ok(value) => Ok(from_value(&value)),
+        Err(error) => Err(RuntimeError::new(&format!("invalid json: {}", error), Position::none()))
+    }
+}
+
+pub fn stringify(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    expect_parameter_count(env, parameters, 1)?;
+
+    to_json(&parameters[0])
+        .map(Object::from)
+        .map_err(|error| RuntimeError::new(&error.message, env.last_position()))
+}
+
+pub fn parse(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    expect_parameter_count(env, parameters, 1)?;
+
+    match &parameters[0] {
+        Object::String(source) => from_json(source.borrow().as_str()).map_err(|error| RuntimeError::new(&error.message, env.last_position())),
+        _ => Err(RuntimeError::new("JSON.parse can parse String only", env.last_position()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clover::Object;
+    use crate::json::{from_json, parse, to_json};
+    use crate::helper::empty_env;
+
+    #[test]
+    fn round_trip() {
+        let source = r#"[1,2.5,"three",null,true,[{"inner":[4,5]}]]"#;
+
+        let object = from_json(source).unwrap();
+        assert!(matches!(object, Object::Array(_)));
+
+        assert_eq!(to_json(&object).unwrap(), source);
+    }
+
+    #[test]
+    fn reject_function() {
+        assert!(to_json(&Object::Function(0)).is_err());
+        assert!(from_json("{ broken").is_err());
+    }
+
+    #[test]
+    fn parse_string_only() {
+        let mut env = empty_env();
+
+        assert!(parse(&mut env, &[ Object::from("[1]") ]).is_ok());
+        assert_eq!(parse(&mut env, &[ Object::Integer(123) ]).unwrap_err().message, "JSON.parse can parse String only");
+    }
+
+    #[test]
+    fn reject_self_referential() {
+        let array = Object::from(vec![ Object::from(1) ]);
+        if let Object::Array(reference) = &array {
+            reference.borrow_mut().push(array.clone());
+        };
+
+        assert!(to_json(&array).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use clover::Env;
+use clover::{Env, NativeModel, Reference};
 use clover::helper::make_reference;
 
 mod io;
@@ -6,24 +6,111 @@ mod random;
 mod math;
 mod helper;
 mod map;
+mod json;
 mod array;
 mod os;
 mod net;
 mod lua;
 
+/// Makes a new instance of a std native model.
+pub type ModelConstructor = fn() -> Reference<dyn NativeModel>;
+
+/// The native models `clover_std_inject_modules` accepts, by name.
+pub const MODULES: &[ (&str, ModelConstructor) ] = &[
+    ("IO", || make_reference(io::IO {})),
+    ("Random", || make_reference(random::Random {})),
+    ("Math", || make_reference(math::Math {})),
+    ("Array", || make_reference(array::Array {})),
+    ("Net", || make_reference(net::Net {})),
+    ("OS", || make_reference(os::Os {})),
+    ("Map", || make_reference(map::Map {})),
+    ("JSON", || make_reference(json::Json {})),
+    ("Lua", || make_reference(lua::LuaLib {})),
+];
+
+// the ones clover_std_inject_safe adds, they can not reach outside of the env
+const SAFE_MODULES: [&str; 5] = [ "Random", "Math", "Array", "Map", "JSON" ];
+
 pub fn clover_std_inject_to(env: &mut Env) {
     env.add_native_function("print", io::print);
 
-    env.add_native_model("IO", make_reference(io::IO {}));
-    env.add_native_model("Random", make_reference(random::Random {}));
-    env.add_native_model("Math", make_reference(math::Math {}));
+    for (name, model) in MODULES {
+        env.add_native_model(name, model());
+    };
+}
+
+/// Injects only the modules which can not reach outside of the env, for running untrusted scripts.
+///
+/// `print`, `Random`, `Math`, `Array`, `Map` and `JSON` are injected. `IO` (files, stdin, processes), `OS`,
+/// `Net` and `Lua` are not, scripts using them fail with `global not found`. `print` writes to the output
+/// of the env, which the host can redirect with `Env::set_output`.
+///
+/// This only limits what a script can reach, a script can still loop forever or use a lot of memory,
+/// combine it with `Env::set_instruction_budget` for untrusted scripts.
+pub fn clover_std_inject_safe(env: &mut Env) {
+    env.add_native_function("print", io::print);
+
+    for (name, model) in MODULES.iter().filter(|(name, _)| SAFE_MODULES.contains(name)) {
+        env.add_native_model(name, model());
+    };
+}
+
+/// Injects `print` and the named modules (see `MODULES`) only, when the host picks what a script can reach.
+/// Nothing is injected when a name is not one of `MODULES`, the error tells which.
+pub fn clover_std_inject_modules(env: &mut Env, names: &[ &str ]) -> Result<(), String> {
+    let mut models = Vec::new();
+
+    for name in names {
+        match MODULES.iter().find(|(module, _)| module == name) {
+            Some((_, model)) => models.push((name, model)),
+            None => {
+                let modules: Vec<&str> = MODULES.iter().map(|(module, _)| *module).collect();
+                return Err(format!("unknown std module [{}], the modules are {}", name, modules.join(", ")));
+            }
+        };
+    };
+
+    env.add_native_function("print", io::print);
+
+    for (name, model) in models {
+        env.add_native_model(name, model());
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clover::{Object, Repl, ReplError};
+    use crate::{clover_std_inject_safe, clover_std_inject_modules};
+
+    #[test]
+    fn safe_injection() {
+        let mut repl = Repl::new();
+        clover_std_inject_safe(repl.env());
+
+        assert!(matches!(repl.eval("Math").unwrap(), Object::NativeModel(_)));
+        assert!(matches!(repl.eval("JSON.stringify([ 1, 2 ])").unwrap(), Object::String(_)));
 
-    env.add_native_model("Array", make_reference(array::Array {}));
-    env.add_native_model("Net", make_reference(net::Net {}));
+        for source in [ "IO.system(\"echo unsafe\")", "OS", "Net", "Lua" ] {
+            match repl.eval(source) {
+                Err(ReplError::Runtime(error)) => assert_eq!(error.message, "global not found"),
+                _ => panic!("[{}] should not be available", source)
+            };
+        };
+    }
 
-    env.add_native_model("OS", make_reference(os::Os {}));
+    #[test]
+    fn picked_modules() {
+        let mut repl = Repl::new();
+        assert!(clover_std_inject_modules(repl.env(), &[ "Math", "Network" ]).unwrap_err().contains("[Network]"));
 
-    env.add_native_model("Map", make_reference(map::Map {}));
+        clover_std_inject_modules(repl.env(), &[ "Math" ]).unwrap();
+        assert!(matches!(repl.eval("Math").unwrap(), Object::NativeModel(_)));
 
-    env.add_native_model("Lua", make_reference(lua::LuaLib {}));
+        match repl.eval("IO") {
+            Err(ReplError::Runtime(error)) => assert_eq!(error.message, "global not found"),
+            _ => panic!("IO should not be available")
+        };
+    }
 }
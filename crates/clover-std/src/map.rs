@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use clover::{NativeModel, NativeModelInstance, Object, Reference, Env};
 use clover::debug::{Position, RuntimeError};
 use clover::helper::make_reference;
+use crate::helper::expect_parameter_count;
 
 #[derive(Debug)]
 pub struct Map;
@@ -10,10 +11,23 @@ impl NativeModel for Map {
     fn call(&mut self, _state: &mut Env, _parameters: &[Object]) -> Result<Object, RuntimeError> {
         Ok(Object::NativeInstance(make_reference(MapInstance(HashMap::new()))))
     }
+
+    fn model_get(&self, key: &str) -> Result<Object, RuntimeError> {
+        match key {
+            "merge" => Ok(Object::NativeFunction(merge)),
+            "from_arrays" => Ok(Object::NativeFunction(from_arrays)),
+            "sorted_keys" => Ok(Object::NativeFunction(sorted_keys)),
+            _ => Ok(Object::Null)
+        }
+    }
 }
 
 pub struct MapInstance(HashMap<String, Object>);
 
+pub fn make_map(entries: HashMap<String, Object>) -> Object {
+    Object::NativeInstance(make_reference(MapInstance(entries)))
+}
+
 
 impl NativeModelInstance for MapInstance {
     fn index_get(&self, _this: Reference<dyn NativeModelInstance>, index: &Object) -> Result<Object, RuntimeError> {
@@ -63,4 +77,107 @@ impl NativeModelInstance for MapInstance {
             _ =>  Err(RuntimeError::new("index does not exists", env.last_position()))
         }
     }
-}
\ No newline at end of file
+
+    fn raw_entries(&self) -> Option<Vec<(String, Object)>> {
+        Some(self.0.iter().map(|(key, value)| (key.clone(), value.clone())).collect())
+    }
+
+    fn raw_copy(&self, entries: Vec<(String, Object)>) -> Option<Reference<dyn NativeModelInstance>> {
+        Some(make_reference(MapInstance(entries.into_iter().collect())))
+    }
+}
+
+fn map_entries(env: &Env, object: &Object) -> Result<Vec<(String, Object)>, RuntimeError> {
+    match object {
+        Object::NativeInstance(instance) => instance.borrow().raw_entries(),
+        _ => None
+    }.ok_or_else(|| RuntimeError::new("parameter must be a map", env.last_position()))
+}
+
+// merge(a, b) - returns a new map, entries of b override the entries of a with the same key
+pub fn merge(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    expect_parameter_count(env, parameters, 2)?;
+
+    let mut entries: HashMap<String, Object> = map_entries(env, &parameters[0])?.into_iter().collect();
+    entries.extend(map_entries(env, &parameters[1])?);
+
+    Ok(make_map(entries))
+}
+
+// from_arrays(keys, values) - keys are converted to strings like map[key] does, the arrays must have the same length
+pub fn from_arrays(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    expect_parameter_count(env, parameters, 2)?;
+
+    let (keys, values) = match (&parameters[0], &parameters[1]) {
+        (Object::Array(keys), Object::Array(values)) => (keys.borrow(), values.borrow()),
+        _ => return Err(RuntimeError::new("parameters must be arrays", env.last_position()))
+    };
+
+    if keys.len() != values.len() {
+        return Err(RuntimeError::new(&format!("got {} keys but {} values", keys.len(), values.len()), env.last_position()));
+    }
+
+    Ok(make_map(keys.iter().map(|key| key.to_string()).zip(values.iter().cloned()).collect()))
+}
+
+// sorted_keys(map) - keys in sorted order, the map itself has no order
+pub fn sorted_keys(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    expect_parameter_count(env, parameters, 1)?;
+
+    let mut keys: Vec<String> = map_entries(env, &parameters[0])?.into_iter().map(|(key, _)| key).collect();
+    keys.sort();
+
+    Ok(Object::from(keys.into_iter().map(Object::from).collect::<Vec<Object>>()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use clover::Object;
+    use crate::helper::{empty_env, map_get};
+    use super::{make_map, merge, from_arrays, sorted_keys};
+
+    #[test]
+    fn merge_maps() {
+        let mut env = empty_env();
+        let a = make_map(HashMap::from([ ("x".to_string(), Object::from(1)), ("y".to_string(), Object::from(2)) ]));
+        let b = make_map(HashMap::from([ ("y".to_string(), Object::from(3)), ("z".to_string(), Object::from(4)) ]));
+
+        let merged = merge(&mut env, &[ a.clone(), b ]).unwrap();
+        assert!(matches!(map_get(&merged, "x"), Object::Integer(1)));
+        assert!(matches!(map_get(&merged, "y"), Object::Integer(3)));
+        assert!(matches!(map_get(&merged, "z"), Object::Integer(4)));
+        // a is not changed
+        assert!(matches!(map_get(&a, "y"), Object::Integer(2)));
+
+        assert!(merge(&mut env, &[ a, Object::from(1) ]).is_err());
+    }
+
+    #[test]
+    fn zip_arrays() {
+        let mut env = empty_env();
+        let keys = Object::from(vec![ Object::from("a"), Object::from(1) ]);
+        let values = Object::from(vec![ Object::from(true), Object::from("one") ]);
+
+        let map = from_arrays(&mut env, &[ keys.clone(), values ]).unwrap();
+        assert!(matches!(map_get(&map, "a"), Object::Boolean(true)));
+        assert_eq!(map_get(&map, "1").to_string(), "one");
+
+        assert!(from_arrays(&mut env, &[ keys, Object::from(vec![ Object::from(1) ]) ]).is_err());
+    }
+
+    #[test]
+    fn stable_key_order() {
+        let mut env = empty_env();
+        let keys = [ "delta", "alpha", "charlie", "bravo", "echo" ];
+
+        let run = |env: &mut _| {
+            let map = make_map(keys.iter().map(|key| (key.to_string(), Object::Null)).collect());
+            sorted_keys(env, &[ map ]).unwrap().to_string()
+        };
+
+        let first = run(&mut env);
+        assert_eq!(first, "[ alpha, bravo, charlie, delta, echo ]");
+        assert_eq!(first, run(&mut env));
+    }
+}
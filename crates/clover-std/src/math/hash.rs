@@ -0,0 +1,106 @@
+use clover::{Env, Object};
+use clover::debug::RuntimeError;
+use crate::helper::expect_parameter_count;
+
+// 64 bit FNV-1a, no random seed so the hash is the same on every run and platform
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+const TAG_NULL: u8 = 0;
+const TAG_BOOLEAN: u8 = 1;
+const TAG_INTEGER: u8 = 2;
+const TAG_FLOAT: u8 = 3;
+const TAG_STRING: u8 = 4;
+const TAG_ARRAY: u8 = 5;
+
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        };
+    }
+
+    // values of different types never hash the same bytes, 1 and 1.0 are different keys
+    fn write_object(&mut self, object: &Object, arrays: &mut Vec<*const Vec<Object>>) -> Result<(), String> {
+        match object {
+            Object::Null => self.write(&[ TAG_NULL ]),
+            Object::Boolean(value) => self.write(&[ TAG_BOOLEAN, *value as u8 ]),
+            Object::Integer(value) => {
+                self.write(&[ TAG_INTEGER ]);
+                self.write(&value.to_le_bytes());
+            },
+            Object::Float(value) => {
+                // 0.0 and -0.0 are equal, so they hash the same
+                let value = if *value == 0.0 { 0.0 } else { *value };
+                self.write(&[ TAG_FLOAT ]);
+                self.write(&value.to_bits().to_le_bytes());
+            },
+            Object::String(value) => {
+                let value = value.borrow();
+                self.write(&[ TAG_STRING ]);
+                self.write(&(value.len() as u64).to_le_bytes());
+                self.write(value.as_bytes());
+            },
+            Object::Array(array) => {
+                let pointer = array.as_ptr() as *const Vec<Object>;
+                if arrays.contains(&pointer) {
+                    return Err("can not hash an array which contains itself".to_string());
+                };
+
+                arrays.push(pointer);
+
+                let array = array.borrow();
+                self.write(&[ TAG_ARRAY ]);
+                self.write(&(array.len() as u64).to_le_bytes());
+
+                for item in array.iter() {
+                    self.write_object(item, arrays)?;
+                };
+
+                arrays.pop();
+            },
+            _ => return Err(format!("Math.hash can not hash {}", object.to_string()))
+        };
+
+        Ok(())
+    }
+}
+
+pub fn hash_object(object: &Object) -> Result<i64, String> {
+    let mut hasher = Fnv(FNV_OFFSET_BASIS);
+    hasher.write_object(object, &mut Vec::new())?;
+
+    Ok(hasher.0 as i64)
+}
+
+pub fn hash(state: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    expect_parameter_count(state, parameters, 1)?;
+
+    hash_object(&parameters[0])
+        .map(Object::Integer)
+        .map_err(|message| RuntimeError::new(&message, state.last_position()))
+}
+
+#[cfg(test)]
+mod tests {
+    use clover::Object;
+    use super::hash_object;
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(hash_object(&Object::from("abc")).unwrap(), 3937386813790840848);
+        assert_eq!(hash_object(&Object::from("abc")).unwrap(), hash_object(&Object::from("abc".to_string())).unwrap());
+
+        assert_ne!(hash_object(&Object::from(1)).unwrap(), hash_object(&Object::from(1.0)).unwrap());
+        assert_eq!(hash_object(&Object::from(0.0)).unwrap(), hash_object(&Object::from(-0.0)).unwrap());
+
+        let array = Object::from(vec![ Object::from(1), Object::from("abc"), Object::from(vec![ Object::from(2.5) ]) ]);
+        assert_eq!(hash_object(&array).unwrap(), hash_object(&array.clone()).unwrap());
+        assert_ne!(hash_object(&array).unwrap(), hash_object(&Object::from(vec![ Object::from(1), Object::from("abc") ])).unwrap());
+
+        assert!(hash_object(&Object::Function(0)).is_err());
+    }
+}
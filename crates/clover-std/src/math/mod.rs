@@ -4,6 +4,8 @@ use std::f64::consts::PI;
 
 mod pow;
 mod trigonometric;
+mod hash;
+mod number;
 
 #[derive(Debug)]
 pub struct Math;
@@ -17,6 +19,14 @@ impl NativeModel for Math {
             "sin" => Ok(Object::NativeFunction(trigonometric::sin)),
             "cos" => Ok(Object::NativeFunction(trigonometric::cos)),
 
+            "hash" => Ok(Object::NativeFunction(hash::hash)),
+
+            "clamp" => Ok(Object::NativeFunction(number::clamp)),
+            "sign" => Ok(Object::NativeFunction(number::sign)),
+            "parse_int" => Ok(Object::NativeFunction(number::parse_int)),
+            "gcd" => Ok(Object::NativeFunction(number::gcd)),
+            "lcm" => Ok(Object::NativeFunction(number::lcm)),
+
             "PI" => Ok(Object::Float(PI)),
             _ => Ok(Object::Null)
         }
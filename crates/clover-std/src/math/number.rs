@@ -0,0 +1,178 @@
+use clover::{Env, Integer, Object};
+use clover::debug::RuntimeError;
+use crate::helper::expect_parameter_count;
+
+fn to_float(object: &Object) -> Option<f64> {
+    match object {
+        Object::Integer(value) => Some(*value as f64),
+        Object::Float(value) => Some(*value),
+        _ => None
+    }
+}
+
+// integer when every parameter is integer, float otherwise
+pub fn clamp(state: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    expect_parameter_count(state, parameters, 3)?;
+
+    if let [ Object::Integer(value), Object::Integer(low), Object::Integer(high) ] = parameters {
+        if low > high {
+            return Err(RuntimeError::new("Math.clamp lower bound is greater than upper bound", state.last_position()));
+        };
+
+        return Ok(Object::Integer(*value.max(low).min(high)));
+    };
+
+    let values: Option<Vec<f64>> = parameters.iter().map(to_float).collect();
+
+    match values.as_deref() {
+        Some(&[ value, low, high ]) => {
+            if low > high {
+                return Err(RuntimeError::new("Math.clamp lower bound is greater than upper bound", state.last_position()));
+            };
+
+            Ok(Object::Float(value.max(low).min(high)))
+        },
+        _ => Err(RuntimeError::new("Math.clamp can accept Integer or Float only", state.last_position()))
+    }
+}
+
+// -1, 0 or 1, float for float
+pub fn sign(state: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    expect_parameter_count(state, parameters, 1)?;
+
+    match &parameters[0] {
+        Object::Integer(value) => Ok(Object::Integer(value.signum())),
+        // signum of zero is 1, NaN stays NaN
+        Object::Float(value) if *value == 0.0 => Ok(Object::Float(0.0)),
+        Object::Float(value) => Ok(Object::Float(value.signum())),
+        _ => Err(RuntimeError::new("Math.sign can accept Integer or Float only", state.last_position()))
+    }
+}
+
+// parse_int(string, radix) - radix from 2 to 36, null when the string is not a number in that radix
+pub fn parse_int(state: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    expect_parameter_count(state, parameters, 2)?;
+
+    let radix = match &parameters[1] {
+        Object::Integer(radix) if (2..=36).contains(radix) => *radix as u32,
+        _ => return Err(RuntimeError::new("Math.parse_int radix must be an Integer from 2 to 36", state.last_position()))
+    };
+
+    match &parameters[0] {
+        Object::String(value) => Ok(i64::from_str_radix(value.borrow().trim(), radix).map_or(Object::Null, Object::Integer)),
+        _ => Err(RuntimeError::new("Math.parse_int can parse String only", state.last_position()))
+    }
+}
+
+fn two_integers(state: &Env, parameters: &[ Object ], name: &str) -> Result<(Integer, Integer), RuntimeError> {
+    expect_parameter_count(state, parameters, 2)?;
+
+    match parameters {
+        [ Object::Integer(a), Object::Integer(b) ] => Ok((*a, *b)),
+        _ => Err(RuntimeError::new(&format!("Math.{} can accept Integer only", name), state.last_position()))
+    }
+}
+
+// on the absolute values, gcd of i64::MIN and 0 does not fit in i64 so it is done in u64
+fn unsigned_gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    };
+
+    a
+}
+
+// gcd(a, b) - never negative, gcd(0, 0) is 0
+pub fn gcd(state: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let (a, b) = two_integers(state, parameters, "gcd")?;
+
+    Integer::try_from(unsigned_gcd(a.unsigned_abs(), b.unsigned_abs()))
+        .map(Object::Integer)
+        .map_err(|_| RuntimeError::new("Math.gcd result is too large for an Integer", state.last_position()))
+}
+
+// lcm(a, b) - never negative, 0 when any is 0, an error when the result does not fit in an Integer
+pub fn lcm(state: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let (a, b) = two_integers(state, parameters, "lcm")?;
+
+    if a == 0 || b == 0 {
+        return Ok(Object::Integer(0));
+    };
+
+    let (a, b) = (a.unsigned_abs(), b.unsigned_abs());
+
+    (a / unsigned_gcd(a, b)).checked_mul(b)
+        .and_then(|lcm| Integer::try_from(lcm).ok())
+        .map(Object::Integer)
+        .ok_or_else(|| RuntimeError::new("Math.lcm result is too large for an Integer", state.last_position()))
+}
+
+#[cfg(test)]
+mod tests {
+    use clover::Object;
+    use crate::helper::empty_env;
+    use super::{clamp, sign, parse_int, gcd, lcm};
+
+    #[test]
+    fn clamp_range() {
+        let mut env = empty_env();
+
+        assert!(matches!(clamp(&mut env, &[ Object::from(-5), Object::from(0), Object::from(10) ]).unwrap(), Object::Integer(0)));
+        assert!(matches!(clamp(&mut env, &[ Object::from(5), Object::from(0), Object::from(10) ]).unwrap(), Object::Integer(5)));
+        assert!(matches!(clamp(&mut env, &[ Object::from(15), Object::from(0), Object::from(10) ]).unwrap(), Object::Integer(10)));
+
+        assert!(matches!(clamp(&mut env, &[ Object::from(1.5), Object::from(0), Object::from(1) ]).unwrap(), Object::Float(value) if value == 1.0));
+        assert!(matches!(clamp(&mut env, &[ Object::from(-1), Object::from(-0.5), Object::from(0.5) ]).unwrap(), Object::Float(value) if value == -0.5));
+
+        assert!(clamp(&mut env, &[ Object::from(1), Object::from(10), Object::from(0) ]).is_err());
+        assert!(clamp(&mut env, &[ Object::from("1"), Object::from(0), Object::from(10) ]).is_err());
+    }
+
+    #[test]
+    fn sign_of_number() {
+        let mut env = empty_env();
+
+        assert!(matches!(sign(&mut env, &[ Object::from(-7) ]).unwrap(), Object::Integer(-1)));
+        assert!(matches!(sign(&mut env, &[ Object::from(0) ]).unwrap(), Object::Integer(0)));
+        assert!(matches!(sign(&mut env, &[ Object::from(7) ]).unwrap(), Object::Integer(1)));
+
+        assert!(matches!(sign(&mut env, &[ Object::from(-2.5) ]).unwrap(), Object::Float(value) if value == -1.0));
+        assert!(matches!(sign(&mut env, &[ Object::from(0.0) ]).unwrap(), Object::Float(value) if value == 0.0));
+        assert!(matches!(sign(&mut env, &[ Object::from(2.5) ]).unwrap(), Object::Float(value) if value == 1.0));
+    }
+
+    #[test]
+    fn parse_with_radix() {
+        let mut env = empty_env();
+
+        assert!(matches!(parse_int(&mut env, &[ Object::from("FF"), Object::from(16) ]).unwrap(), Object::Integer(255)));
+        assert!(matches!(parse_int(&mut env, &[ Object::from("ff"), Object::from(16) ]).unwrap(), Object::Integer(255)));
+        assert!(matches!(parse_int(&mut env, &[ Object::from("101"), Object::from(2) ]).unwrap(), Object::Integer(5)));
+        assert!(matches!(parse_int(&mut env, &[ Object::from("-z"), Object::from(36) ]).unwrap(), Object::Integer(-35)));
+
+        assert!(parse_int(&mut env, &[ Object::from("102"), Object::from(2) ]).unwrap().is_null());
+        assert!(parse_int(&mut env, &[ Object::from(""), Object::from(10) ]).unwrap().is_null());
+
+        assert!(parse_int(&mut env, &[ Object::from("1"), Object::from(1) ]).is_err());
+        assert!(parse_int(&mut env, &[ Object::from("1"), Object::from(37) ]).is_err());
+    }
+
+    #[test]
+    fn gcd_and_lcm() {
+        let mut env = empty_env();
+
+        assert!(matches!(gcd(&mut env, &[ Object::from(12), Object::from(18) ]).unwrap(), Object::Integer(6)));
+        assert!(matches!(gcd(&mut env, &[ Object::from(-12), Object::from(18) ]).unwrap(), Object::Integer(6)));
+        assert!(matches!(gcd(&mut env, &[ Object::from(0), Object::from(0) ]).unwrap(), Object::Integer(0)));
+        assert!(matches!(gcd(&mut env, &[ Object::from(0), Object::from(-5) ]).unwrap(), Object::Integer(5)));
+        assert!(gcd(&mut env, &[ Object::from(i64::MIN), Object::from(0) ]).is_err());
+
+        assert!(matches!(lcm(&mut env, &[ Object::from(4), Object::from(6) ]).unwrap(), Object::Integer(12)));
+        assert!(matches!(lcm(&mut env, &[ Object::from(-4), Object::from(6) ]).unwrap(), Object::Integer(12)));
+        assert!(matches!(lcm(&mut env, &[ Object::from(0), Object::from(6) ]).unwrap(), Object::Integer(0)));
+        assert!(lcm(&mut env, &[ Object::from(i64::MAX), Object::from(2) ]).is_err());
+
+        assert!(gcd(&mut env, &[ Object::from(1.5), Object::from(3) ]).is_err());
+        assert!(lcm(&mut env, &[ Object::from(4) ]).is_err());
+    }
+}
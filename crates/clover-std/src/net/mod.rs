@@ -14,6 +14,7 @@ impl NativeModel for Net {
     fn model_get(&self, key: &str) -> Result<Object, RuntimeError> {
         match key {
             "get" => Ok(Object::NativeFunction(sync_get)),
+            "get_json" => Ok(Object::NativeFunction(get_json)),
             "has_wifi" => Ok(Object::NativeFunction(has_wifi)),
             _ => Ok(Object::Null)
         }
@@ -27,6 +28,13 @@ fn sync_get(env: &mut Env, parameters: &[Object]) -> Result<Object, RuntimeError
     rt.block_on(async_get(env, parameters))
 }
 
+// same as get, but parse the response body as json
+fn get_json(env: &mut Env, parameters: &[Object]) -> Result<Object, RuntimeError> {
+    let body = sync_get(env, parameters)?;
+
+    crate::json::from_json(&body.to_string()).map_err(|error| RuntimeError::new(&error.message, env.last_position()))
+}
+
 fn has_wifi(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
     let test_url = "http://www.google.com";
     let parameters = vec![Object::String(Rc::new(RefCell::new(test_url.to_string())))];
@@ -1,4 +1,8 @@
 use clover::{Env, Object, NativeModel};
+use clover::helper::make_reference;
+use crate::map::make_map;
+use std::collections::HashMap;
+use std::process::{Command, Output};
 use clover::debug::RuntimeError;
 use sysinfo::System;
 use std::env;
@@ -24,6 +28,7 @@ impl NativeModel for Os {
             "does_file_exist" => Ok(Object::NativeFunction(does_file_exist)),
             "does_dir_exist" => Ok(Object::NativeFunction(does_dir_exist)),
             "get_extension" => Ok(Object::NativeFunction(get_extension)),
+            "run" => Ok(Object::NativeFunction(run)),
             _ => Ok(Object::Null)
         }
     }
@@ -124,4 +129,64 @@ pub fn get_extension(env: &mut Env, parameters: &[Object]) -> Result<Object, Run
         Some(extension) => Ok(Object::String(Rc::new(RefCell::new(extension.to_string_lossy().to_string())))),
         None => Ok(Object::Null),
     }
-}
\ No newline at end of file
+}
+
+// OS.run(program, [ arguments ]) - arguments are passed as they are, nothing is split or interpreted by a shell,
+// returns the map of output_to_map
+pub fn run(env: &mut Env, parameters: &[Object]) -> Result<Object, RuntimeError> {
+    if parameters.is_empty() || parameters.len() > 2 {
+        return Err(RuntimeError::new(&format!("except 1 or 2 parameters, got {}", parameters.len()), env.last_position()));
+    }
+
+    let program = match &parameters[0] {
+        Object::String(program) => program.borrow().clone(),
+        _ => return Err(RuntimeError::new("program must be a string", env.last_position()))
+    };
+
+    let arguments: Vec<String> = match parameters.get(1) {
+        Some(Object::Array(arguments)) => arguments.borrow().iter().map(|argument| argument.to_string()).collect(),
+        None => Vec::new(),
+        _ => return Err(RuntimeError::new("arguments must be an array", env.last_position()))
+    };
+
+    let output = Command::new(&program)
+        .args(&arguments)
+        .output()
+        .map_err(|error| RuntimeError::new(&format!("can not run [{}]: {}", program, error), env.last_position()))?;
+
+    Ok(output_to_map(output))
+}
+
+// map with stdout, stderr and exit_code (null when the process was killed by a signal) of a finished process
+pub fn output_to_map(output: Output) -> Object {
+    let mut result = HashMap::new();
+    result.insert("stdout".to_string(), Object::String(make_reference(String::from_utf8_lossy(&output.stdout).to_string())));
+    result.insert("stderr".to_string(), Object::String(make_reference(String::from_utf8_lossy(&output.stderr).to_string())));
+    result.insert("exit_code".to_string(), output.status.code().map_or(Object::Null, |code| Object::Integer(code as i64)));
+
+    make_map(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use clover::Object;
+    use crate::helper::{empty_env, map_get as get};
+    use super::run;
+
+    #[test]
+    #[cfg(unix)]
+    fn run_with_space_in_argument() {
+        let mut env = empty_env();
+
+        let result = run(&mut env, &[ Object::from("printf"), Object::from(vec![ Object::from("[%s]"), Object::from("two words") ]) ]).unwrap();
+
+        assert_eq!(get(&result, "stdout").to_string(), "[two words]");
+        assert_eq!(get(&result, "stderr").to_string(), "");
+        assert!(matches!(get(&result, "exit_code"), Object::Integer(0)));
+
+        let result = run(&mut env, &[ Object::from("sh"), Object::from(vec![ Object::from("-c"), Object::from("exit 3") ]) ]).unwrap();
+        assert!(matches!(get(&result, "exit_code"), Object::Integer(3)));
+
+        assert!(run(&mut env, &[ Object::from("printf"), Object::from("not an array") ]).is_err());
+    }
+}
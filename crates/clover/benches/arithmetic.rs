@@ -0,0 +1,9 @@
+// integer arithmetic in a loop, most of the integers are small ones
+//
+// cargo bench -p clover --bench arithmetic
+
+mod harness;
+
+fn main() {
+    harness::bench_file("arithmetic", "benches/arithmetic.luck", &[ "small_integers", "large_integers" ]);
+}
@@ -0,0 +1,9 @@
+// steady state execution of the for_loop fixture
+//
+// cargo bench -p clover --bench for_loop
+
+mod harness;
+
+fn main() {
+    harness::bench_file("for_loop", "tests/for_loop.luck", &[ "simple", "nests", "break_loop", "array", "for_model" ]);
+}
@@ -0,0 +1,56 @@
+// measures a function of a luck file like criterion does, the env is reset between runs instead of created again
+
+use clover::{Clover, Env, Object};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const WARM_UP: Duration = Duration::from_millis(500);
+const MEASURE: Duration = Duration::from_secs(3);
+const SAMPLE_COUNT: u128 = 50;
+
+fn find_function(env: &Env, name: &str) -> usize {
+    env.get_program().find_function(name).unwrap()
+}
+
+fn bench(name: &str, env: &mut Env, function_index: usize) {
+    let mut run = |iterations: u64| {
+        for _ in 0..iterations {
+            env.reset();
+            let result = env.execute_by_function_index(function_index, &[]).unwrap();
+            assert!(matches!(black_box(result), Object::Boolean(true)));
+        };
+    };
+
+    // warm up, and find out how many iterations make a sample
+    let mut warm_up_iterations: u64 = 0;
+    let start = Instant::now();
+    while start.elapsed() < WARM_UP {
+        run(1);
+        warm_up_iterations += 1;
+    };
+
+    let iterations = (warm_up_iterations as u128 * MEASURE.as_nanos() / WARM_UP.as_nanos() / SAMPLE_COUNT).max(1) as u64;
+
+    let mut samples = Vec::new();
+    let start = Instant::now();
+    while start.elapsed() < MEASURE {
+        let sample_start = Instant::now();
+        run(iterations);
+        samples.push(sample_start.elapsed().as_nanos() as f64 / iterations as f64);
+    };
+
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    println!("{:<24} time: [{:.1} ns {:.1} ns {:.1} ns] ({} samples)", name, samples[0], samples[samples.len() / 2], samples[samples.len() - 1], samples.len());
+}
+
+// each function has to return true
+pub fn bench_file(group: &str, filename: &str, function_names: &[ &str ]) {
+    let program = Clover::new().compile_file(filename).unwrap();
+    let mut env: Env = program.into();
+
+    for name in function_names {
+        let function_index = find_function(&env, name);
+        bench(&format!("{}/{}", group, name), &mut env, function_index);
+    };
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use crate::intermediate::ast::Document;
+
+/// Keeps parsed documents between compilations, a file is parsed again only when its content changed.
+#[derive(Debug, Default)]
+pub struct CompileCache {
+    // key is filename, value is the source and its parsed document
+    documents: HashMap<String, (String, Document)>,
+    parsed_count: usize
+}
+
+impl CompileCache {
+    pub fn new() -> CompileCache {
+        CompileCache::default()
+    }
+
+    /// how many times a source was actually parsed, cache hits are not counted
+    pub fn parsed_count(&self) -> usize {
+        self.parsed_count
+    }
+
+    pub fn clear(&mut self) {
+        self.documents.clear();
+    }
+
+    /// the cached document of the file, if it was parsed from the same source
+    pub fn get(&self, filename: &str, source: &str) -> Option<Document> {
+        match self.documents.get(filename) {
+            Some((cached_source, document)) if cached_source == source => Some(document.clone()),
+            _ => None
+        }
+    }
+
+    pub fn insert(&mut self, filename: &str, source: &str, document: Document) {
+        self.parsed_count += 1;
+        self.documents.insert(filename.to_string(), (source.to_string(), document));
+    }
+}
@@ -1,13 +1,18 @@
 use std::collections::{HashMap, HashSet};
-use std::fs::{read_to_string, File};
+use std::env;
+use std::fs::{read_dir, read_to_string, File};
+use std::path::{Path, PathBuf};
+use std::thread;
 
+use crate::backend::compile_cache::CompileCache;
 use crate::backend::dependency_solver::DependencySolver;
+use crate::frontend::lexer::lex;
+use crate::frontend::parser::{parse, parse_statements};
 use crate::backend::function_state::{Scope, FunctionState};
-use crate::frontend::parser::parse;
 use crate::intermediate::{CompileErrorList, Position, Token, TokenValue};
-use crate::intermediate::ast::{Definition, Document, IncludeDefinition, ModelDefinition, FunctionDefinition, ImplementDefinition, ApplyDefinition, Statement, Expression, IntegerExpression, FloatExpression, StringExpression, BooleanExpression, IdentifierExpression, InfixExpression, CallExpression, InstanceGetExpression, ThisExpression, PrefixExpression, IfExpression, ArrayExpression, IndexGetExpression, ForStatement, LocalDefinition};
-use crate::runtime::object::{Object, make_reference};
-use crate::runtime::opcode::{OpCode, Instruction};
+use crate::intermediate::ast::{Definition, Document, IncludeDefinition, ModelDefinition, FunctionDefinition, ImplementDefinition, ApplyDefinition, Statement, Expression, IntegerExpression, FloatExpression, StringExpression, BooleanExpression, IdentifierExpression, InfixExpression, CallExpression, InstanceGetExpression, ThisExpression, PrefixExpression, IfExpression, ArrayExpression, IndexGetExpression, ForStatement, LocalDefinition, LocalStatement, NullExpression};
+use crate::runtime::object::{Object, Integer, Float, make_reference};
+use crate::runtime::opcode::{OpCode, Instruction, INSTANCE_GET_PROPERTY, INSTANCE_GET_METHOD};
 use crate::runtime::program::{Program, Model, Function};
 use crate::backend::assembly_state::AssemblyState;
 use crate::runtime::runtime_info::{FileInfo, DebugInfo};
@@ -22,21 +27,24 @@ use std::io::{Read, Write, BufReader, BufWriter};
 /// - `functions`: A vector of `Function` instances representing the functions defined in the program.
 /// - `constants`: A vector of `Object` instances representing the constants used in the program.
 /// - `int_const_indices`: A hash map mapping integer constants to their indices in the `constants` vector.
+/// - `float_const_indices`: A hash map mapping the bits of float constants to their indices in the `constants` vector.
 /// - `str_const_indices`: A hash map mapping string constants to their indices in the `constants` vector.
 /// - `global_dependencies`: A set of indices of global dependencies used in the program.
 /// - `local_variable_count`: The number of local variables used in the program.
 /// - `assembly_states`: A hash map of `AssemblyState` instances representing the assembly state for each module in the program.
 /// - `local_values`: A hash map mapping local variable indices to their corresponding indices in the `assemblies` hash map.
-/// - `entry_point`: The index of the entry point function in the `functions` vector.
+/// - `entry_point`: The index of the entry point function in the `functions` vector, None when there is no `main`.
 /// - `file_info`: A `FileInfo` instance containing information about the source file.
 /// - `debug_info`: A `DebugInfo` instance containing debugging information for the compiled program.
+/// - `warnings`: Warnings of the compiled files, such as unused locals and includes.
 #[derive(Debug)]
 pub struct CompilerContext {
     model_definitions: Vec<Model>,
     function_definitions: Vec<Function>,
     constants: Vec<Object>,
 
-    int_const_indices: HashMap<i64, usize>,
+    int_const_indices: HashMap<Integer, usize>,
+    float_const_indices: HashMap<u64, usize>,
     str_const_indices: HashMap<String, usize>,
 
     global_dependencies: HashSet<usize>,
@@ -45,10 +53,15 @@ pub struct CompilerContext {
     assembly_states: HashMap<String, AssemblyState>,
     local_values: HashMap<usize, usize>,
 
-    entry_point: usize,
+    entry_point: Option<usize>,
 
     file_info: FileInfo,
-    debug_info: DebugInfo
+    debug_info: DebugInfo,
+
+    warnings: Vec<CompileErrorList>,
+
+    // names the env will have as native models, a local with one of them gets a warning
+    native_names: HashSet<String>
 }
 
 impl CompilerContext {
@@ -59,6 +72,7 @@ impl CompilerContext {
             constants: Program::DEFAULT_CONSTANTS.to_vec(),
 
             int_const_indices: HashMap::new(),
+            float_const_indices: HashMap::new(),
             str_const_indices: HashMap::new(),
 
             global_dependencies: HashSet::new(),
@@ -67,10 +81,14 @@ impl CompilerContext {
             assembly_states: HashMap::new(),
             local_values: HashMap::new(),
 
-            entry_point: 0,
+            entry_point: None,
 
             file_info: FileInfo::new(),
-            debug_info: DebugInfo::new()
+            debug_info: DebugInfo::new(),
+
+            warnings: Vec::new(),
+
+            native_names: HashSet::new()
         }
     }
 
@@ -91,6 +109,16 @@ impl CompilerContext {
                     index
                 }
             },
+            // by bits, so 0.0 and -0.0 stay apart
+            Object::Float(value) => {
+                if let Some(index) = self.float_const_indices.get(&value.to_bits()) {
+                    *index
+                } else {
+                    let index = self.add_constant_no_check(object.clone());
+                    self.float_const_indices.insert(value.to_bits(), index);
+                    index
+                }
+            },
             Object::String(value) => {
                 if let Some(index) = self.str_const_indices.get(value.borrow().deref()) {
                     *index
@@ -170,12 +198,54 @@ impl CompilerContext {
         loaded_assemblies
     }
 
+    /// warnings of each compiled file, files without warnings are not listed
+    pub fn warnings(&self) -> &[CompileErrorList] {
+        &self.warnings
+    }
+
+    // function with the name defined in the file, instance functions are not counted
+    fn find_function(&self, filename: &str, name: &str) -> Option<usize> {
+        let assembly_index = self.assembly_states.get(filename)?.index;
+
+        (0..self.function_definitions.len()).find(|&index| {
+            !self.function_definitions[index].is_instance
+                && self.file_info.function_files[index] == assembly_index
+                && self.file_info.function_names[index] == name
+        })
+    }
+
+    pub fn add_native_name(&mut self, name: &str) {
+        self.native_names.insert(name.to_string());
+    }
+
     pub fn to_program(&self) -> Program {
+        let mut global_dependencies: Vec<usize> = self.global_dependencies.iter().cloned().collect();
+        global_dependencies.sort();
+
+        // globals are accessed by their slot (index in global dependencies), no name lookup at runtime
+        let slots: HashMap<usize, usize> = global_dependencies.iter().enumerate().map(|(slot, &constant_index)| (constant_index, slot)).collect();
+
+        let functions = self.function_definitions.iter().map(|function| {
+            let mut function = function.clone();
+
+            for instruction in function.instructions.iter_mut() {
+                let opcode = match instruction.opcode() {
+                    OpCode::GlobalGet => OpCode::GlobalGetSlot,
+                    OpCode::GlobalSet => OpCode::GlobalSetSlot,
+                    _ => continue
+                };
+
+                *instruction = opcode.to_instruction(slots[&(instruction.operand() as usize)] as u64);
+            };
+
+            function
+        }).collect();
+
         Program {
             models: self.model_definitions.clone(),
-            functions: self.function_definitions.clone(),
+            functions,
             constants: self.constants.clone(),
-            global_dependencies: self.global_dependencies.iter().cloned().collect(),
+            global_dependencies,
 
             local_variable_count: self.local_variable_count,
             local_values: self.local_values.clone(),
@@ -192,7 +262,15 @@ impl CompilerContext {
 pub struct CompilerEnv {
     pub assembly_state: AssemblyState,
     pub locals: Scope,
-    pub errors: CompileErrorList
+    pub errors: CompileErrorList,
+    pub warnings: CompileErrorList,
+
+    // included names and the file locals which are read, for unused warnings
+    pub include_aliases: Vec<(usize, Token)>,
+    pub used_locals: HashSet<usize>,
+
+    // functions a model got from apply, by model index
+    pub applied_functions: HashMap<usize, HashSet<String>>
 }
 
 pub trait Storage {
@@ -244,6 +322,27 @@ impl DefaultStorage {
 }
 
 impl CompilerEnv {
+    pub fn new(filename: &str) -> CompilerEnv {
+        CompilerEnv {
+            assembly_state: AssemblyState::new(filename),
+            locals: Scope::new(),
+            errors: CompileErrorList::new(filename),
+            warnings: CompileErrorList::new(filename),
+            include_aliases: Vec::new(),
+            used_locals: HashSet::new(),
+            applied_functions: HashMap::new()
+        }
+    }
+
+    // the native model can not be reached by this name in the scope of the local
+    fn warn_if_shadows_native(&mut self, context: &CompilerContext, token: &Token) {
+        let name = token.value.to_string();
+
+        if context.native_names.contains(&name) {
+            self.warnings.push_warning(token, &format!("[{}] shadows the native model with the same name", name));
+        };
+    }
+
     fn define_local(&mut self, context: &mut CompilerContext, name: &str) -> Option<usize> {
         if self.locals.contains_key(name) {
             None
@@ -257,6 +356,7 @@ impl CompilerEnv {
 
     fn define_local_by_identifier(&mut self, context: &mut CompilerContext, token: &Token) -> Option<usize> {
         if let TokenValue::Identifier(identifier) = &token.value {
+            self.warn_if_shadows_native(context, token);
             self.define_local(context, identifier)
         } else {
             self.errors.push_error(token, "variable already exists");
@@ -266,15 +366,13 @@ impl CompilerEnv {
 
     fn compile_integer_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, integer_expression: &IntegerExpression) {
         if let TokenValue::Integer(value) = integer_expression.token.value {
-            let index = context.add_constant(Object::Integer(value));
-            func_state.emit(OpCode::PushConstant.to_instruction(index as u64), integer_expression.token.position);
+            emit_integer(context, func_state, value, integer_expression.token.position);
         }
     }
 
     fn compile_float_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, float_expression: &FloatExpression) {
         if let TokenValue::Float(value) = float_expression.token.value {
-            let index = context.add_constant(Object::Float(value));
-            func_state.emit(OpCode::PushConstant.to_instruction(index as u64), float_expression.token.position);
+            emit_float(context, func_state, value, float_expression.token.position);
         }
     }
 
@@ -297,8 +395,10 @@ impl CompilerEnv {
         let identifier = identifier_expression.token.value.to_string();
 
         if let Some(index) = func_state.find_local(&identifier) {
+            func_state.used_locals.insert(index);
             func_state.emit(OpCode::LocalGet.to_instruction(index as u64), identifier_expression.token.position);
         } else if let Some(&index) = self.locals.get(&identifier) {
+            self.used_locals.insert(index);
             func_state.emit(OpCode::ContextGet.to_instruction(index as u64), identifier_expression.token.position);
         } else {
             let index = context.add_constant(Object::String(make_reference(identifier)));
@@ -308,6 +408,13 @@ impl CompilerEnv {
     }
 
     fn compile_this_expression(&mut self, _context: &mut CompilerContext, func_state: &mut FunctionState, this_expression: &ThisExpression) {
+        if !func_state.is_instance {
+            // null keeps the stack as if it compiled, so the rest of the function can still be checked
+            self.errors.push_error(&this_expression.token, "this can only be used in an instance function");
+            func_state.emit(OpCode::PushConstant.to_instruction(Program::NULL_CONSTANT_INDEX as u64), this_expression.token.position);
+            return;
+        };
+
         func_state.emit(OpCode::LocalGet.to_instruction(0 as u64), this_expression.token.position);
     }
 
@@ -334,6 +441,9 @@ impl CompilerEnv {
                     func_state.emit(OpCode::GlobalSet.to_instruction(index as u64), infix_expression.infix.position);
                 }
             },
+            Expression::InstanceGet(instance_get_expression) if instance_get_expression.token.value == TokenValue::QuestionDot => {
+                self.errors.push_error(&instance_get_expression.token, "can not assign to null safe access");
+            },
             Expression::InstanceGet(instance_get_expression) => {
                 self.compile_indexed_set(
                     context,
@@ -368,6 +478,19 @@ impl CompilerEnv {
             return self.compile_assign_expression(context, func_state, infix_expression);
         };
 
+        if infix_expression.infix.value == TokenValue::QuestionQuestion {
+            return self.compile_null_coalescing_expression(context, func_state, infix_expression);
+        };
+
+        // string literals added together are one constant
+        if infix_expression.infix.value == TokenValue::Plus {
+            if let (Some(left), Some(right)) = (literal_string(&infix_expression.left), literal_string(&infix_expression.right)) {
+                let index = context.add_constant(Object::String(make_reference(left + &right)));
+                func_state.emit(OpCode::PushConstant.to_instruction(index as u64), infix_expression.infix.position);
+                return;
+            };
+        };
+
         if let Some(instruction) = get_operation_instruction_by_token(&infix_expression.infix) {
             self.compile_expression(context, func_state, infix_expression.left.deref());
             self.compile_expression(context, func_state, infix_expression.right.deref());
@@ -389,7 +512,41 @@ impl CompilerEnv {
         };
     }
 
+    // a ?? b - b is evaluated only when a is null
+    fn compile_null_coalescing_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, infix_expression: &InfixExpression) {
+        self.compile_expression(context, func_state, infix_expression.left.deref());
+
+        let jump_if_null_index = func_state.emit_opcode(OpCode::JumpIfNull, infix_expression.infix.position);
+        let jump_to_end_index = func_state.emit_opcode(OpCode::Jump, infix_expression.infix.position);
+
+        let right_position = func_state.get_next_instruction_index() as u64;
+        func_state.replace_instruction(jump_if_null_index, OpCode::JumpIfNull.to_instruction(right_position));
+
+        func_state.emit_opcode(OpCode::Pop, infix_expression.infix.position);
+        self.compile_expression(context, func_state, infix_expression.right.deref());
+
+        let end_position = func_state.get_next_instruction_index() as u64;
+        func_state.replace_instruction(jump_to_end_index, OpCode::Jump.to_instruction(end_position));
+    }
+
     fn compile_prefix_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, prefix_expression: &PrefixExpression) {
+        // a minus right before a number literal is folded into the literal, -5 is one constant
+        if prefix_expression.prefix.value == TokenValue::Minus {
+            let position = prefix_expression.prefix.position;
+
+            match prefix_expression.right.deref() {
+                Expression::Integer(IntegerExpression { token: Token { value: TokenValue::Integer(value), .. } }) => {
+                    emit_integer(context, func_state, -value, position);
+                    return;
+                },
+                Expression::Float(FloatExpression { token: Token { value: TokenValue::Float(value), .. } }) => {
+                    emit_float(context, func_state, -value, position);
+                    return;
+                },
+                _ => {}
+            }
+        };
+
         self.compile_expression(context, func_state, prefix_expression.right.deref());
 
         match prefix_expression.prefix.value {
@@ -399,9 +556,12 @@ impl CompilerEnv {
         }
     }
 
-    fn compile_call_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, call_expression: &CallExpression) {
+    fn compile_call_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, call_expression: &CallExpression, null_jumps: &mut Vec<usize>) {
         // compile the function, after this the function object will on the top of stack
-        self.compile_expression(context, func_state, call_expression.function.deref());
+        match call_expression.function.deref() {
+            Expression::InstanceGet(instance_get_expression) => self.compile_instance_get_expression(context, func_state, instance_get_expression, INSTANCE_GET_METHOD, null_jumps),
+            function => self.compile_chain_part(context, func_state, function, null_jumps)
+        };
         // compile parameters
         for parameter_expression in call_expression.parameters.iter() {
             self.compile_expression(context, func_state, parameter_expression);
@@ -418,21 +578,32 @@ impl CompilerEnv {
         func_state.emit(OpCode::Array.to_instruction(array_expression.values.len() as u64), array_expression.token.position);
     }
 
-    fn compile_instance_get_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, instance_get_expression: &InstanceGetExpression) {
-        self.compile_expression(context, func_state, instance_get_expression.instance.deref());
+    fn compile_instance_get_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, instance_get_expression: &InstanceGetExpression, operand: u64, null_jumps: &mut Vec<usize>) {
+        self.compile_chain_part(context, func_state, instance_get_expression.instance.deref(), null_jumps);
+
+        // a?.b - when a is null, skip the get and leave the null as result
+        if instance_get_expression.token.value == TokenValue::QuestionDot {
+            null_jumps.push(func_state.emit_opcode(OpCode::JumpIfNull, instance_get_expression.token.position));
+        };
+
         self.compile_expression(context, func_state, instance_get_expression.index.deref());
 
-        func_state.emit_opcode(OpCode::InstanceGet, instance_get_expression.token.position);
+        func_state.emit(OpCode::InstanceGet.to_instruction(operand), instance_get_expression.token.position);
     }
 
-    fn compile_index_get_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, index_get_expression: &IndexGetExpression) {
-        self.compile_expression(context, func_state, index_get_expression.instance.deref());
+    fn compile_index_get_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, index_get_expression: &IndexGetExpression, null_jumps: &mut Vec<usize>) {
+        self.compile_chain_part(context, func_state, index_get_expression.instance.deref(), null_jumps);
         self.compile_expression(context, func_state, index_get_expression.index.deref());
 
         func_state.emit_opcode(OpCode::IndexGet, index_get_expression.token.position);
     }
 
     fn compile_if_expression(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, if_expression: &IfExpression) {
+        // if x = 5 is almost always a typo of ==
+        if let Expression::Infix(InfixExpression { infix: infix @ Token { value: TokenValue::Assign, .. }, .. }) = if_expression.condition.deref() {
+            self.warnings.push_warning(infix, "assignment used as condition, did you mean [==]?");
+        };
+
         self.compile_expression(context, func_state, if_expression.condition.deref());
 
         let true_part_instruction_index = func_state.emit_opcode_without_position(OpCode::JumpIf);
@@ -476,17 +647,38 @@ impl CompilerEnv {
             Expression::Identifier(identifier_expression) => self.compile_identifier_expression(context, func_state, identifier_expression),
             Expression::Prefix(prefix_expression) => self.compile_prefix_expression(context, func_state, prefix_expression),
             Expression::Infix(infix_expression) => self.compile_infix_expression(context, func_state, infix_expression),
-            Expression::Call(call_expression) => self.compile_call_expression(context, func_state, call_expression),
-            Expression::InstanceGet(instance_get_expression) => self.compile_instance_get_expression(context, func_state, instance_get_expression),
-            Expression::IndexGet(index_get_expression) => self.compile_index_get_expression(context, func_state, index_get_expression),
+            Expression::Call(_) | Expression::InstanceGet(_) | Expression::IndexGet(_) => self.compile_member_chain(context, func_state, expression),
             Expression::This(this_expression) => self.compile_this_expression(context, func_state, this_expression),
             Expression::If(if_expression) => self.compile_if_expression(context, func_state, if_expression)
         }
     }
 
+    // a?.b.c, a?.b() and a?.b[0] - when a is null, the rest of the whole chain is skipped and the null is the result
+    fn compile_member_chain(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, expression: &Expression) {
+        let mut null_jumps = Vec::new();
+        self.compile_chain_part(context, func_state, expression, &mut null_jumps);
+
+        let end_position = func_state.get_next_instruction_index() as u64;
+        for index in null_jumps {
+            func_state.replace_instruction(index, OpCode::JumpIfNull.to_instruction(end_position));
+        };
+    }
+
+    // the jumps of ?. in a chain are collected in null_jumps, they go to the end of the chain
+    fn compile_chain_part(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, expression: &Expression, null_jumps: &mut Vec<usize>) {
+        match expression {
+            Expression::Call(call_expression) => self.compile_call_expression(context, func_state, call_expression, null_jumps),
+            Expression::InstanceGet(instance_get_expression) => self.compile_instance_get_expression(context, func_state, instance_get_expression, INSTANCE_GET_PROPERTY, null_jumps),
+            Expression::IndexGet(index_get_expression) => self.compile_index_get_expression(context, func_state, index_get_expression, null_jumps),
+            _ => self.compile_expression(context, func_state, expression)
+        }
+    }
+
     fn compile_for_statement(&mut self, context: &mut CompilerContext, func_state: &mut FunctionState, for_statement: &ForStatement) {
         let enumerable_local_index = func_state.define_anonymous_local();
         let iterator_local_index = func_state.define_anonymous_local();
+        // length of an array when the loop starts, set by ForNext, so items added in the loop do not make it endless
+        let length_local_index = func_state.define_anonymous_local();
 
         func_state.enter_scope();
         func_state.enter_break_scope();
@@ -501,16 +693,35 @@ impl CompilerEnv {
         func_state.emit(OpCode::LocalSet.to_instruction(iterator_local_index as u64), func_state.get_last_position());
         func_state.emit_opcode_without_position(OpCode::Pop);
 
+        // an outer loop runs this one again, the length of the last run must be cleared
+        func_state.emit(OpCode::PushConstant.to_instruction(Program::NULL_CONSTANT_INDEX as u64), func_state.get_last_position());
+        func_state.emit(OpCode::LocalSet.to_instruction(length_local_index as u64), func_state.get_last_position());
+        func_state.emit_opcode_without_position(OpCode::Pop);
+
         // because we just enter a new scope, so we never have a duplicate name here, so can unwrap directly
         let local_variable_index = func_state.define_local(&for_statement.identifier.value.to_string()).unwrap();
 
+        let value_local_index = for_statement.value_identifier.as_ref().map(|value_identifier| {
+            func_state.define_local(&value_identifier.value.to_string()).unwrap_or_else(|| {
+                self.errors.push_error(value_identifier, "variable already exists");
+                local_variable_index
+            })
+        });
+
         let start_loop_position = func_state.get_next_instruction_index();
 
-        func_state.emit(OpCode::ForNext.to_instruction(enumerable_local_index as u64), func_state.get_last_position());
+        let for_next = if value_local_index.is_some() { OpCode::ForNextPair } else { OpCode::ForNext };
+        func_state.emit(for_next.to_instruction(enumerable_local_index as u64), func_state.get_last_position());
 
         let jump_to_end_if_true_instruction_index = func_state.get_next_instruction_index();
         func_state.emit_opcode_without_position(OpCode::JumpIf);
 
+        // the value is on top of the key
+        if let (Some(value_local_index), Some(value_identifier)) = (value_local_index, &for_statement.value_identifier) {
+            func_state.emit(OpCode::LocalSet.to_instruction(value_local_index as u64), value_identifier.position);
+            func_state.emit_opcode_without_position(OpCode::Pop);
+        };
+
         // set the iterator to local
         func_state.emit(OpCode::LocalSet.to_instruction(local_variable_index as u64), for_statement.identifier.position);
         func_state.emit_opcode_without_position(OpCode::Pop);
@@ -541,7 +752,9 @@ impl CompilerEnv {
             },
             Statement::Local(local_statement) => {
                 for (i, token) in local_statement.variables.iter().enumerate() {
-                    if let Some(index) = func_state.define_local(&token.value.to_string()) {
+                    self.warn_if_shadows_native(context, token);
+
+                    if let Some(index) = func_state.declare_local(token) {
                         if let Some(expression) = local_statement.values.get(i).unwrap() {
                             self.compile_expression(context, func_state, expression);
                             func_state.emit(OpCode::LocalInit.to_instruction(index as u64), token.position);
@@ -551,13 +764,31 @@ impl CompilerEnv {
                     };
                 }
             },
-            Statement::Break(break_statement) => func_state.emit_break(break_statement.token.position),
+            Statement::Break(break_statement) => {
+                if func_state.break_scopes.is_empty() {
+                    self.errors.push_error(&break_statement.token, "break can only be used inside a loop");
+                } else {
+                    func_state.emit_break(break_statement.token.position);
+                };
+            },
             Statement::Rescue(rescue_statement) => {
                 if func_state.current_depth > 1 {
                     self.errors.push_error(&rescue_statement.token, "rescue can only in the layer of function");
                 } else {
                     func_state.emit_return(rescue_statement.token.position);
                     func_state.rescue_position = func_state.get_next_instruction_index();
+
+                    // runtime push the error object before jump to rescue position
+                    if let Some(identifier) = &rescue_statement.identifier {
+                        if let Some(index) = func_state.declare_local(identifier) {
+                            func_state.emit(OpCode::LocalInit.to_instruction(index as u64), identifier.position);
+                        } else {
+                            self.errors.push_error(identifier, "variable already exists");
+                            func_state.emit_opcode(OpCode::Pop, identifier.position);
+                        }
+                    } else {
+                        func_state.emit_opcode(OpCode::Pop, rescue_statement.token.position);
+                    }
                 }
             },
             Statement::For(for_statement) => self.compile_for_statement(context, func_state, for_statement)
@@ -565,16 +796,63 @@ impl CompilerEnv {
         func_state.current_depth -= 1;
     }
 
-    fn compile_include_definition(&mut self, context: &mut CompilerContext, include_definition: &IncludeDefinition) {
+    // returns the name, local index and constant index of each included name
+    fn compile_include_definition(&mut self, context: &mut CompilerContext, include_definition: &IncludeDefinition) -> Vec<(String, usize, usize)> {
+        if let Some(wildcard) = &include_definition.wildcard {
+            return self.compile_wildcard_include(context, wildcard, &include_definition.filename.value.to_string());
+        };
+
+        let mut included = Vec::new();
+
         for (i, alias) in include_definition.aliases.iter().enumerate() {
             if let Some(index) = self.define_local_by_identifier(context, alias) {
+                self.include_aliases.push((index, alias.clone()));
+
                 let public_name = include_definition.public_names.get(i).unwrap();
 
                 if let Some(constant_index) = context.find_constant_index_by_include(&include_definition.filename.value.to_string(), &public_name.value.to_string()) {
                     context.local_values.insert(index, constant_index);
+                    included.push((alias.value.to_string(), index, constant_index));
                 }
             }
         }
+
+        included
+    }
+
+    // every public name of the file under its own name, not warned when unused as a file seldom needs all of them
+    fn compile_wildcard_include(&mut self, context: &mut CompilerContext, wildcard: &Token, filename: &str) -> Vec<(String, usize, usize)> {
+        let mut public_names: Vec<(String, usize)> = match context.assembly_states.get(filename) {
+            Some(assembly_state) => assembly_state.public_indices.iter().map(|(name, &index)| (name.clone(), index)).collect(),
+            None => return Vec::new()
+        };
+        // sorted so locals get the same indices every compilation
+        public_names.sort();
+
+        let mut included = Vec::new();
+
+        for (name, constant_index) in public_names {
+            let token = Token::new(TokenValue::Identifier(name.clone()), wildcard.position);
+
+            match self.define_local_by_identifier(context, &token) {
+                Some(index) => {
+                    context.local_values.insert(index, constant_index);
+                    included.push((name, index, constant_index));
+                },
+                None => self.errors.push_error(wildcard, &format!("included [{}] already exists", name))
+            };
+        };
+
+        included
+    }
+
+    // included names become public names of this file too, so files including this one can reach them
+    fn compile_public_include_definition(&mut self, context: &mut CompilerContext, include_definition: &IncludeDefinition) {
+        for (name, local_index, constant_index) in self.compile_include_definition(context, include_definition) {
+            // exported, not unused
+            self.used_locals.insert(local_index);
+            self.assembly_state.public_indices.insert(name, constant_index);
+        };
     }
 
     fn compile_local_definition(&mut self, context: &mut CompilerContext, local_definition: &LocalDefinition) {
@@ -645,6 +923,8 @@ impl CompilerEnv {
                 func_state.is_instance = true;
             };
 
+            self.warn_if_shadows_native(context, parameter);
+
             if func_state.define_local(&parameter.value.to_string()).is_none() {
                 self.errors.push_error(parameter, "parameter already exists");
             };
@@ -658,6 +938,10 @@ impl CompilerEnv {
 
         func_state.emit_return(func_state.get_last_position());
 
+        for token in func_state.unused_locals() {
+            self.warnings.push_warning(token, &format!("local variable [{}] is never used", token.value.to_string()));
+        };
+
         func_state
     }
 
@@ -681,7 +965,7 @@ impl CompilerEnv {
             };
 
             if &function_definition.name.value.to_string() == "main" {
-                context.entry_point = function_index;
+                context.entry_point = Some(function_index);
             };
 
             constant_index
@@ -696,6 +980,8 @@ impl CompilerEnv {
 
     fn find_model_index_by_local_name(&mut self, context: &mut CompilerContext, token: &Token) -> Option<usize> {
         if let Some(&model_local_index) = self.locals.get(&token.value.to_string()) {
+            self.used_locals.insert(model_local_index);
+
             if let Some(Object::Model(model_index)) = context.get_local_value(model_local_index) {
                 return Some(model_index);
             } else {
@@ -721,12 +1007,26 @@ impl CompilerEnv {
         if let Some(model_index) = self.find_model_index_by_local_name(context, &implement_definition.model_name) {
             let model = context.model_definitions.get_mut(model_index).unwrap();
 
+            // added after the properties of the model, so they come after them in the constructor too
+            for token in implement_definition.properties.iter() {
+                if !model.add_property(&token.value.to_string()) {
+                    self.errors.push_error(token, "property already exists");
+                };
+            };
+
             for (name, index) in functions {
+                // implemented in the model itself now, so an apply later will keep it
+                if let Some(applied_functions) = self.applied_functions.get_mut(&model_index) {
+                    applied_functions.remove(&name);
+                };
+
                 model.functions.insert(name, index);
             };
         }
     }
 
+    // functions of the target model win over the applied ones, no matter which comes first,
+    // an apply after an apply replaces the functions of the former
     fn compile_apply_definition(&mut self, context: &mut CompilerContext, apply_definition: &ApplyDefinition) {
         let mut functions = HashMap::new();
 
@@ -740,9 +1040,19 @@ impl CompilerEnv {
 
         if let Some(model_index) = self.find_model_index_by_local_name(context, &apply_definition.target_model) {
             let model = context.model_definitions.get_mut(model_index).unwrap();
+            let applied_functions = self.applied_functions.entry(model_index).or_default();
 
-            for (name, index) in functions{
-                model.functions.insert(name, index);
+            let mut names: Vec<String> = functions.keys().cloned().collect();
+            names.sort();
+
+            for name in names {
+                if model.functions.contains_key(&name) && !applied_functions.contains(&name) {
+                    self.warnings.push_warning(&apply_definition.source_model, &format!("function [{}] is already implemented in [{}], the one from [{}] is not applied", name, apply_definition.target_model.value.to_string(), apply_definition.source_model.value.to_string()));
+                    continue;
+                };
+
+                model.functions.insert(name.clone(), functions[&name]);
+                applied_functions.insert(name);
             };
         };
     }
@@ -750,7 +1060,8 @@ impl CompilerEnv {
     fn compile_definition(&mut self, context: &mut CompilerContext, definition: &Definition) {
         match definition {
             Definition::Local(local_definition) => self.compile_local_definition(context, local_definition),
-            Definition::Include(include_definition) => self.compile_include_definition(context, include_definition),
+            Definition::Include(include_definition) => { self.compile_include_definition(context, include_definition); },
+            Definition::PublicInclude(include_definition) => self.compile_public_include_definition(context, include_definition),
             Definition::Model(model_definition) => { self.compile_model_definition(context, model_definition); },
             Definition::PublicModel(model_definition) => self.compile_public_model_definition(context, model_definition),
             Definition::Function(function_definition) => { self.compile_function_definition(context, function_definition); },
@@ -764,15 +1075,17 @@ impl CompilerEnv {
         for definition in document.definitions.iter() {
             self.compile_definition(context, definition);
         }
+
+        for (index, alias) in self.include_aliases.iter() {
+            if !self.used_locals.contains(index) {
+                self.warnings.push_warning(alias, &format!("included [{}] is never used", alias.value.to_string()));
+            };
+        };
     }
 }
 
 pub fn compile_document(document: &Document, context: &mut CompilerContext) -> Result<(), CompileErrorList> {
-    let mut env = CompilerEnv {
-        assembly_state: AssemblyState::new(&document.filename),
-        locals: Scope::new(),
-        errors: CompileErrorList::new(&document.filename)
-    };
+    let mut env = CompilerEnv::new(&document.filename);
 
     env.assembly_state.index = context.assembly_states.len();
 
@@ -781,6 +1094,10 @@ pub fn compile_document(document: &Document, context: &mut CompilerContext) -> R
     context.add_assembly(env.assembly_state);
     context.file_info.filenames.push(document.filename.clone());
 
+    if !env.warnings.is_empty() {
+        context.warnings.push(env.warnings);
+    };
+
     if env.errors.is_empty() {
         Ok(())
     } else {
@@ -789,11 +1106,65 @@ pub fn compile_document(document: &Document, context: &mut CompilerContext) -> R
 }
 
 pub fn compile_to(context: &mut CompilerContext, source: &str, filename: &str, file_loader: &dyn Storage) -> Result<(), CompileErrorList> {
+    compile_to_with_cache(context, source, filename, file_loader, &mut CompileCache::new())
+}
+
+pub fn compile_to_with_cache(context: &mut CompilerContext, source: &str, filename: &str, file_loader: &dyn Storage, cache: &mut CompileCache) -> Result<(), CompileErrorList> {
+    compile_to_with_options(context, source, filename, file_loader, cache, true)
+}
+
+// parse the sources, on separate threads when parallel is set, results keep the order of sources
+fn parse_sources(sources: Vec<(String, String)>, cache: &mut CompileCache, parallel: bool) -> Result<Vec<Document>, CompileErrorList> {
+    let mut results: Vec<Option<Result<Document, CompileErrorList>>> = sources.iter()
+        .map(|(filename, source)| cache.get(filename, source).map(Ok))
+        .collect();
+
+    let missed: Vec<usize> = (0..sources.len()).filter(|&i| results[i].is_none()).collect();
+
+    if parallel && missed.len() > 1 {
+        let parsed: Vec<Result<Document, CompileErrorList>> = thread::scope(|scope| {
+            let handles: Vec<_> = missed.iter()
+                .map(|&i| {
+                    let (filename, source) = &sources[i];
+                    scope.spawn(move || parse(source, filename))
+                })
+                .collect();
+
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        for (&i, result) in missed.iter().zip(parsed) {
+            results[i] = Some(result);
+        };
+    } else {
+        for &i in missed.iter() {
+            let (filename, source) = &sources[i];
+            results[i] = Some(parse(source, filename));
+        };
+    };
+
+    let mut documents = Vec::new();
+
+    for (i, result) in results.into_iter().enumerate() {
+        let document = result.unwrap()?;
+
+        if missed.contains(&i) {
+            let (filename, source) = &sources[i];
+            cache.insert(filename, source, document.clone());
+        };
+
+        documents.push(document);
+    };
+
+    Ok(documents)
+}
+
+pub(crate) fn compile_to_with_options(context: &mut CompilerContext, source: &str, filename: &str, file_loader: &dyn Storage, cache: &mut CompileCache, parallel: bool) -> Result<(), CompileErrorList> {
     let mut documents: HashMap<String, Document> = HashMap::new();
 
     let mut dependency_solver = DependencySolver::new();
 
-    let document = parse(&source, filename)?;
+    let document = parse_sources(vec![ (filename.to_string(), source.to_string()) ], cache, parallel)?.remove(0);
 
     let loaded_assemblies = context.get_loaded_assemblies();
 
@@ -801,14 +1172,26 @@ pub fn compile_to(context: &mut CompilerContext, source: &str, filename: &str, f
 
     documents.insert(document.filename.clone(), document);
 
-    while let Some(dependency_filename) = dependency_solver.get_unsolved_filename() {
-        let dependency_source = file_loader.load_file(&dependency_filename)?;
-        let dependency_document = parse(&dependency_source, &dependency_filename)?;
+    // every unsolved file in a round is independent from the others, so they are parsed together
+    loop {
+        let unsolved_filenames = dependency_solver.get_unsolved_filenames();
+        if unsolved_filenames.is_empty() {
+            break;
+        };
 
-        dependency_solver.solve(&dependency_document, &loaded_assemblies);
-        documents.insert(dependency_filename, dependency_document);
-    }
+        let mut sources = Vec::new();
+        for dependency_filename in unsolved_filenames {
+            let dependency_source = file_loader.load_file(&dependency_filename)?;
+            sources.push((dependency_filename, dependency_source));
+        };
 
+        for dependency_document in parse_sources(sources, cache, parallel)? {
+            dependency_solver.solve(&dependency_document, &loaded_assemblies);
+            documents.insert(dependency_document.filename.clone(), dependency_document);
+        };
+    };
+
+    // compiling stays on this thread, the context holds objects which can not be shared between threads
     while let Some(filename_to_compile) = dependency_solver.get_next_no_dependency_filename() {
         let document_to_compile = documents.get(&filename_to_compile).unwrap();
 
@@ -828,12 +1211,219 @@ pub fn compile_to(context: &mut CompilerContext, source: &str, filename: &str, f
     Ok(())
 }
 
+fn emit_integer(context: &mut CompilerContext, func_state: &mut FunctionState, value: Integer, position: Position) {
+    if (Program::SMALL_INTEGER_MIN..=Program::SMALL_INTEGER_MAX).contains(&value) {
+        func_state.emit(OpCode::PushInteger.to_instruction((value - Program::SMALL_INTEGER_MIN) as u64), position);
+        return;
+    };
+
+    let index = context.add_constant(Object::Integer(value));
+    func_state.emit(OpCode::PushConstant.to_instruction(index as u64), position);
+}
+
+fn emit_float(context: &mut CompilerContext, func_state: &mut FunctionState, value: Float, position: Position) {
+    let index = context.add_constant(Object::Float(value));
+    func_state.emit(OpCode::PushConstant.to_instruction(index as u64), position);
+}
+
+// value of a string literal, or of string literals added together such as "a" + "b" + "c"
+fn literal_string(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::String(StringExpression { token: Token { value: TokenValue::String(value), .. } }) => Some(value.clone()),
+        Expression::Infix(infix_expression) if infix_expression.infix.value == TokenValue::Plus => {
+            Some(literal_string(&infix_expression.left)? + &literal_string(&infix_expression.right)?)
+        },
+        _ => None
+    }
+}
+
+// name of the function which runs the statements of a repl input
+pub const REPL_FUNCTION_NAME: &str = "<repl>";
+
+// compiler env for the inputs of a repl, the inputs share one assembly
+pub fn create_repl_compiler_env(context: &mut CompilerContext, filename: &str) -> CompilerEnv {
+    let mut env = CompilerEnv::new(filename);
+
+    env.assembly_state.index = context.assembly_states.len();
+    context.add_assembly(env.assembly_state.clone());
+    context.file_info.filenames.push(filename.to_string());
+
+    env
+}
+
+// a local statement of a repl input declares a file local, so later inputs can still see it
+fn hoist_local_statement(env: &mut CompilerEnv, context: &mut CompilerContext, local_statement: &LocalStatement, statements: &mut Vec<Statement>) {
+    for (i, token) in local_statement.variables.iter().enumerate() {
+        if !env.locals.contains_key(&token.value.to_string()) {
+            env.define_local_by_identifier(context, token);
+        };
+
+        let value = match local_statement.values.get(i) {
+            Some(Some(expression)) => expression.clone(),
+            _ => Expression::Null(NullExpression { token: Token::new(TokenValue::Null, token.position) })
+        };
+
+        statements.push(Statement::Expression(Expression::Infix(InfixExpression {
+            left: Box::new(Expression::Identifier(IdentifierExpression { token: token.clone() })),
+            infix: Token::new(TokenValue::Assign, token.position),
+            right: Box::new(value)
+        })));
+    };
+}
+
+/// Compiles one input of a repl into the context. The compiler env is kept between inputs, so the
+/// file locals defined by former inputs are visible. Definitions are compiled as in a file, other inputs
+/// are statements, they are compiled into a function whose index is returned.
+pub fn compile_repl_input(context: &mut CompilerContext, env: &mut CompilerEnv, source: &str, file_loader: &dyn Storage) -> Result<Option<usize>, CompileErrorList> {
+    let filename = env.assembly_state.filename.clone();
+
+    let is_definition = matches!(
+        lex(source)?.first().map(|token| &token.value),
+        Some(TokenValue::Function | TokenValue::Model | TokenValue::Implement | TokenValue::Apply | TokenValue::Public | TokenValue::Include)
+    );
+
+    let function_index = if is_definition {
+        let document = parse(source, &filename)?;
+
+        for dependency_filename in document.get_dependencies() {
+            if !context.assembly_states.contains_key(&dependency_filename) {
+                compile_to(context, &file_loader.load_file(&dependency_filename)?, &dependency_filename, file_loader)?;
+            };
+        };
+
+        for definition in document.definitions.iter() {
+            env.compile_definition(context, definition);
+        };
+
+        None
+    } else {
+        let mut body = Vec::new();
+
+        for statement in parse_statements(source, &filename)? {
+            match statement {
+                Statement::Local(local_statement) => hoist_local_statement(env, context, &local_statement, &mut body),
+                _ => body.push(statement)
+            };
+        };
+
+        let function_definition = FunctionDefinition {
+            name: Token::new(TokenValue::Identifier(REPL_FUNCTION_NAME.to_string()), Position::none()),
+            parameters: Vec::new(),
+            body
+        };
+
+        let func_state = env.compile_function_definition_base(context, &function_definition);
+
+        Some(context.add_function(func_state, REPL_FUNCTION_NAME, env.assembly_state.index))
+    };
+
+    // an input is too short for the unused warnings to make sense
+    env.warnings = CompileErrorList::new(&filename);
+
+    if env.errors.is_empty() {
+        Ok(function_index)
+    } else {
+        Err(std::mem::replace(&mut env.errors, CompileErrorList::new(&filename)))
+    }
+}
+
+// the file whose main function becomes the entry point when compiling a directory
+pub const DIRECTORY_ENTRY_FILENAME: &str = "main.luck";
+
+// same form as the include paths after normalize_include_paths, so includes between files in the directory resolve to one assembly
+fn normalize_filename(path: &Path) -> String {
+    let current_path = env::current_dir().ok().and_then(|path| path.canonicalize().ok());
+
+    let filename = match (path.canonicalize(), current_path) {
+        (Ok(full_path), Some(current_path)) => full_path.strip_prefix(&current_path).map(|stripped| stripped.to_path_buf()).unwrap_or(full_path),
+        _ => path.to_path_buf()
+    };
+
+    filename.to_string_lossy().replace("\\", "/")
+}
+
+fn find_source_files(directory: &Path, filenames: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in read_dir(directory)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            find_source_files(&path, filenames)?;
+        } else if path.extension().is_some_and(|extension| extension == "luck") {
+            filenames.push(path);
+        };
+    };
+
+    Ok(())
+}
+
+pub fn compile_directory(directory: &str, file_loader: &dyn Storage) -> Result<Program, CompileErrorList> {
+    let mut paths = Vec::new();
+
+    if find_source_files(Path::new(directory), &mut paths).is_err() {
+        let mut errors = CompileErrorList::new(directory);
+        errors.push_error(&Token::new(TokenValue::None, Position::none()), "Failed to read the specified source directory.");
+        return Err(errors);
+    };
+
+    paths.sort();
+
+    let entry_path = Path::new(directory).join(DIRECTORY_ENTRY_FILENAME);
+    if !paths.contains(&entry_path) {
+        let mut errors = CompileErrorList::new(directory);
+        errors.push_error(&Token::new(TokenValue::None, Position::none()), &format!("Can not find entry file [{}] in the specified directory.", DIRECTORY_ENTRY_FILENAME));
+        return Err(errors);
+    };
+
+    let mut context = CompilerContext::new();
+
+    // compile the entry file first and keep its main, other files may define a main too
+    let entry_filename = normalize_filename(&entry_path);
+    compile_to(&mut context, &file_loader.load_file(&entry_filename)?, &entry_filename, file_loader)?;
+    let entry_point = context.entry_point;
+
+    for path in paths.iter() {
+        let filename = normalize_filename(path);
+
+        if context.assembly_states.contains_key(&filename) {
+            continue;
+        };
+
+        compile_to(&mut context, &file_loader.load_file(&filename)?, &filename, file_loader)?;
+    };
+
+    context.entry_point = entry_point;
+
+    Ok(context.to_program())
+}
+
 pub fn compile_file(filename: &str, file_loader: &dyn Storage) -> Result<Program, CompileErrorList> {
     let source = file_loader.load_file(filename)?;
 
     compile(&source, filename, file_loader)
 }
 
+pub fn compile_file_with_cache(filename: &str, file_loader: &dyn Storage, cache: &mut CompileCache) -> Result<Program, CompileErrorList> {
+    let source = file_loader.load_file(filename)?;
+    let mut context = CompilerContext::new();
+
+    compile_to_with_cache(&mut context, &source, filename, file_loader, cache)?;
+
+    Ok(context.to_program())
+}
+
+pub fn compile_file_with_warnings(filename: &str, file_loader: &dyn Storage, native_names: &[String], cache: &mut CompileCache) -> Result<(Program, Vec<CompileErrorList>), CompileErrorList> {
+    let source = file_loader.load_file(filename)?;
+    let mut context = CompilerContext::new();
+
+    for name in native_names {
+        context.add_native_name(name);
+    };
+
+    compile_to_with_cache(&mut context, &source, filename, file_loader, cache)?;
+
+    Ok((context.to_program(), context.warnings().to_vec()))
+}
+
 pub fn compile(source: &str, filename: &str, file_loader: &dyn Storage) -> Result<Program, CompileErrorList> {
     let mut context = CompilerContext::new();
 
@@ -842,6 +1432,22 @@ pub fn compile(source: &str, filename: &str, file_loader: &dyn Storage) -> Resul
     Ok(context.to_program())
 }
 
+// same as compile, but the entry point is the function with the name in the file instead of main
+pub fn compile_with_entry(source: &str, filename: &str, entry_name: &str, file_loader: &dyn Storage) -> Result<Program, CompileErrorList> {
+    let mut context = CompilerContext::new();
+
+    compile_to(&mut context, source, filename, file_loader)?;
+
+    if let Some(function_index) = context.find_function(filename, entry_name) {
+        context.entry_point = Some(function_index);
+        Ok(context.to_program())
+    } else {
+        let mut errors = CompileErrorList::new(filename);
+        errors.push_error(&Token::new(TokenValue::None, Position::none()), &format!("can not find entry function [{}]", entry_name));
+        Err(errors)
+    }
+}
+
 // helpers
 fn get_operation_instruction_by_token(token: &Token) -> Option<Instruction> {
     let operand: usize = match token.value {
@@ -26,21 +26,23 @@ impl DependencySolver {
             list.push(filename.clone());
         };
 
+        list.sort();
         list
     }
 
-    pub fn get_unsolved_filename(&self) -> Option<String> {
-        if let Some(filename) = self.unsolved.iter().next() {
-            Some(filename.clone())
-        } else {
-            None
-        }
+    // sorted, so the files are always handled in the same order
+    pub fn get_unsolved_filenames(&self) -> Vec<String> {
+        let mut filenames: Vec<String> = self.unsolved.iter().cloned().collect();
+        filenames.sort();
+        filenames
     }
-    
+
     pub fn get_next_no_dependency_filename(&self) -> Option<String> {
         self.dependencies.iter()
-            .find(|&(_, &dependency)| dependency == 0)
-            .map(|(filename, _)| filename.clone())
+            .filter(|&(_, &dependency)| dependency == 0)
+            .map(|(filename, _)| filename)
+            .min()
+            .cloned()
     }
 
     pub fn set_loaded(&mut self, filename: &str) {
@@ -1,7 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::runtime::opcode::{Instruction, OpCode};
-use crate::intermediate::{Positions, Position};
+use crate::intermediate::{Positions, Position, Token};
 use crate::runtime::program::Program;
 
 pub type Scope = HashMap<String, usize>;
@@ -18,7 +18,11 @@ pub struct FunctionState {
     pub scopes: Vec<Scope>,
     pub break_scopes: Vec<BreakScope>,
     pub instructions: Vec<Instruction>,
-    pub positions: Positions
+    pub positions: Positions,
+
+    // locals declared by statements, and the locals which are read, for unused warnings
+    pub declared_locals: Vec<(usize, Token)>,
+    pub used_locals: HashSet<usize>
 }
 
 impl FunctionState {
@@ -32,7 +36,9 @@ impl FunctionState {
             scopes: Vec::new(),
             break_scopes: Vec::new(),
             instructions: Vec::new(),
-            positions: Positions::new()
+            positions: Positions::new(),
+            declared_locals: Vec::new(),
+            used_locals: HashSet::new()
         };
 
         func_state.enter_scope();
@@ -162,5 +168,17 @@ impl FunctionState {
         index
     }
 
+    pub fn declare_local(&mut self, token: &Token) -> Option<usize> {
+        let index = self.define_local(&token.value.to_string())?;
+        self.declared_locals.push((index, token.clone()));
+        Some(index)
+    }
+
+    pub fn unused_locals(&self) -> Vec<&Token> {
+        self.declared_locals.iter()
+            .filter(|(index, token)| !self.used_locals.contains(index) && !token.value.to_string().starts_with('_'))
+            .map(|(_, token)| token)
+            .collect()
+    }
 
 }
@@ -1,4 +1,5 @@
 pub mod compiler;
+pub mod compile_cache;
 mod dependency_solver;
 mod function_state;
 mod assembly_state;
\ No newline at end of file
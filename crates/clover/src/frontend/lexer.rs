@@ -1,18 +1,23 @@
 use crate::intermediate::{ Token, TokenValue, Position, TokenList, CompileErrorList };
+use crate::runtime::object::{Integer, Float};
 
 use std::iter::Peekable;
 use std::str::Chars;
 
-/// A macro to match a token value with a value from a list of options.
+/// A macro to define a function which matches a token value with a value from a list of options.
 ///
-/// This macro takes a token value and a list of options in the form of
-/// `key => value`. It will return `Some(value)` if the token matches `key`
-/// and `None` otherwise.
-macro_rules! match_token {
-    ($token: ident, $($key: expr => $value: expr), *) => {
-        match $token {
-            $( $key => return Some($value), )*
-            _ => None
+/// This macro takes a list of options in the form of `key => value`. The function returns
+/// `Some(value)` if the token matches `key` and `None` otherwise. The keys are also kept in
+/// a constant, so tools get the same list the lexer uses.
+macro_rules! token_table {
+    ($function: ident, $keys: ident, $($key: literal => $value: expr), *) => {
+        const $keys: &[ &str ] = &[ $( $key ), * ];
+
+        fn $function(token: &str) -> Option<TokenValue> {
+            match token {
+                $( $key => Some($value), )*
+                _ => None
+            }
         }
     }
 }
@@ -101,9 +106,9 @@ impl<'a> LexerState<'a> {
             self.next_character();
         }
 
-        let value = match number_string.parse::<f64>() {
+        let value = match number_string.parse::<Float>() {
             Ok(num) if is_float => TokenValue::Float(num),
-            Ok(num) => TokenValue::Integer(num as i64),
+            Ok(num) => TokenValue::Integer(num as Integer),
             Err(_) => TokenValue::Invalid(format!("Invalid number '{}'", number_string)),
         };
 
@@ -149,7 +154,9 @@ impl<'a> LexerState<'a> {
             }
         }
 
-        Token::new(get_symbol(symbol_string.as_str()).unwrap(), position)
+        // '?' is only a part of multi character symbols
+        let symbol = get_symbol(symbol_string.as_str()).unwrap_or_else(|| TokenValue::Invalid(format!("Unknown symbol [{}]", symbol_string)));
+        Token::new(symbol, position)
     }
 
     fn peek(&mut self) -> char {
@@ -208,81 +215,90 @@ fn is_comment_prefix(character: char) -> bool {
 fn is_symbol(character: char) -> bool {
     let string = String::from(character);
 
-    get_symbol(string.as_str()).is_some()
+    character == '?' || get_symbol(string.as_str()).is_some()
 }
 
 // token helper functions
 
-fn get_keyword(keyword: &str) -> Option<TokenValue> {
-    match_token! {
-        keyword,
-        "true"          => TokenValue::True,
-        "false"         => TokenValue::False,
-        "null"          => TokenValue::Null,
-
-        "and"           => TokenValue::And,
-        "or"            => TokenValue::Or,
-        "not"           => TokenValue::Not,
-
-        "include"       => TokenValue::Include,
-        "from"          => TokenValue::From,
-        "model"         => TokenValue::Model,
-        "function"      => TokenValue::Function,
-        "end"           => TokenValue::End,
-        "implement"     => TokenValue::Implement,
-        "local"         => TokenValue::Local,
-        "apply"         => TokenValue::Apply,
-        "to"            => TokenValue::To,
-        "return"        => TokenValue::Return,
-        "public"        => TokenValue::Public,
-        "as"            => TokenValue::As,
-        "this"          => TokenValue::This,
-        "if"            => TokenValue::If,
-        "else"          => TokenValue::Else,
-        "elseif"        => TokenValue::ElseIf,
-        "while"         => TokenValue::While,
-        "for"           => TokenValue::For,
-        "in"            => TokenValue::In,
-        "break"         => TokenValue::Break,
-
-        "rescue"        => TokenValue::Rescue
-    }
+token_table! {
+    get_keyword, KEYWORDS,
+    "true"          => TokenValue::True,
+    "false"         => TokenValue::False,
+    "null"          => TokenValue::Null,
+
+    "and"           => TokenValue::And,
+    "or"            => TokenValue::Or,
+    "not"           => TokenValue::Not,
+
+    "include"       => TokenValue::Include,
+    "from"          => TokenValue::From,
+    "model"         => TokenValue::Model,
+    "function"      => TokenValue::Function,
+    "end"           => TokenValue::End,
+    "implement"     => TokenValue::Implement,
+    "local"         => TokenValue::Local,
+    "apply"         => TokenValue::Apply,
+    "to"            => TokenValue::To,
+    "return"        => TokenValue::Return,
+    "public"        => TokenValue::Public,
+    "as"            => TokenValue::As,
+    "this"          => TokenValue::This,
+    "if"            => TokenValue::If,
+    "else"          => TokenValue::Else,
+    "elseif"        => TokenValue::ElseIf,
+    "while"         => TokenValue::While,
+    "for"           => TokenValue::For,
+    "in"            => TokenValue::In,
+    "break"         => TokenValue::Break,
+
+    "rescue"        => TokenValue::Rescue
 }
 
-fn get_symbol(symbol: &str) -> Option<TokenValue> {
-    match_token! {
-        symbol,
-        "="     =>  TokenValue::Assign,
-        "+"     =>  TokenValue::Plus,
-        "-"     =>  TokenValue::Minus,
-        "*"     =>  TokenValue::Star,
-        "/"     =>  TokenValue::Slash,
-        "%"     =>  TokenValue::Percent,
-        "!"     =>  TokenValue::Not,
-        "("     =>  TokenValue::LeftParentheses,
-        ")"     =>  TokenValue::RightParentheses,
-        "["     =>  TokenValue::LeftBracket,
-        "]"     =>  TokenValue::RightBracket,
-        ","     =>  TokenValue::Comma,
-        ":"     =>  TokenValue::Colon,
-        "&"     =>  TokenValue::BitAnd,
-        "|"     =>  TokenValue::BitOr,
-        "."     =>  TokenValue::Dot,
-        ">"     =>  TokenValue::Greater,
-        "<"     =>  TokenValue::Less,
-
-        "=="    =>  TokenValue::Equal,
-        "!="    =>  TokenValue::NotEqual,
-        "&&"    =>  TokenValue::And,
-        "||"    =>  TokenValue::Or,
-        ">="    =>  TokenValue::GreaterEqual,
-        "<="    =>  TokenValue::LessEqual,
-        "+="    =>  TokenValue::PlusAssign,
-        "-="    =>  TokenValue::MinusAssign,
-        "*="    =>  TokenValue::StarAssign,
-        "/="    =>  TokenValue::SlashAssign,
-        "%="    =>  TokenValue::PercentAssign
-    }
+token_table! {
+    get_symbol, SYMBOLS,
+    "="     =>  TokenValue::Assign,
+    "+"     =>  TokenValue::Plus,
+    "-"     =>  TokenValue::Minus,
+    "*"     =>  TokenValue::Star,
+    "/"     =>  TokenValue::Slash,
+    "%"     =>  TokenValue::Percent,
+    "!"     =>  TokenValue::Not,
+    "("     =>  TokenValue::LeftParentheses,
+    ")"     =>  TokenValue::RightParentheses,
+    "["     =>  TokenValue::LeftBracket,
+    "]"     =>  TokenValue::RightBracket,
+    ","     =>  TokenValue::Comma,
+    ":"     =>  TokenValue::Colon,
+    ";"     =>  TokenValue::Semicolon,
+    "&"     =>  TokenValue::BitAnd,
+    "|"     =>  TokenValue::BitOr,
+    "."     =>  TokenValue::Dot,
+    ">"     =>  TokenValue::Greater,
+    "<"     =>  TokenValue::Less,
+
+    "=="    =>  TokenValue::Equal,
+    "!="    =>  TokenValue::NotEqual,
+    "&&"    =>  TokenValue::And,
+    "||"    =>  TokenValue::Or,
+    ">="    =>  TokenValue::GreaterEqual,
+    "<="    =>  TokenValue::LessEqual,
+    "+="    =>  TokenValue::PlusAssign,
+    "-="    =>  TokenValue::MinusAssign,
+    "*="    =>  TokenValue::StarAssign,
+    "/="    =>  TokenValue::SlashAssign,
+    "%="    =>  TokenValue::PercentAssign,
+    "?."    =>  TokenValue::QuestionDot,
+    "??"    =>  TokenValue::QuestionQuestion
+}
+
+/// Every keyword of the language, such as `function` and `rescue`.
+pub fn keywords() -> &'static [ &'static str ] {
+    KEYWORDS
+}
+
+/// Every symbol of the language, such as `+=` and `?.`.
+pub fn symbols() -> &'static [ &'static str ] {
+    SYMBOLS
 }
 
 
@@ -364,6 +380,7 @@ mod tests {
         assert_eq!(get_symbol("]"), Some(TokenValue::RightBracket));
         assert_eq!(get_symbol(","), Some(TokenValue::Comma));
         assert_eq!(get_symbol(":"), Some(TokenValue::Colon));
+        assert_eq!(get_symbol(";"), Some(TokenValue::Semicolon));
         assert_eq!(get_symbol("&"), Some(TokenValue::BitAnd));
         assert_eq!(get_symbol("|"), Some(TokenValue::BitOr));
         assert_eq!(get_symbol("."), Some(TokenValue::Dot));
@@ -381,5 +398,36 @@ mod tests {
         assert_eq!(get_symbol("*="), Some(TokenValue::StarAssign));
         assert_eq!(get_symbol("/="), Some(TokenValue::SlashAssign));
         assert_eq!(get_symbol("%="), Some(TokenValue::PercentAssign));
+        assert_eq!(get_symbol("?."), Some(TokenValue::QuestionDot));
+        assert_eq!(get_symbol("??"), Some(TokenValue::QuestionQuestion));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn vocabulary() {
+        let keywords = crate::tooling::keywords();
+        assert!(keywords.contains(&"function") && keywords.contains(&"model") && keywords.contains(&"rescue"));
+        assert!(keywords.iter().all(|keyword| get_keyword(keyword).is_some()));
+
+        let symbols = crate::tooling::symbols();
+        assert!(symbols.contains(&"?.") && symbols.contains(&"+="));
+        assert!(symbols.iter().all(|symbol| get_symbol(symbol).is_some()));
+    }
+
+    #[test]
+    fn tokenize() {
+        let tokens = crate::tooling::tokenize("local a = 10\nprint(\"hi\")").unwrap();
+
+        let found: Vec<(TokenValue, u16, u16)> = tokens.iter().map(|token| (token.value.clone(), token.position.line, token.position.column)).collect();
+        assert_eq!(found, vec![
+            (TokenValue::Local, 1, 1),
+            (TokenValue::Identifier("a".to_string()), 1, 7),
+            (TokenValue::Assign, 1, 9),
+            (TokenValue::Integer(10), 1, 11),
+            (TokenValue::Identifier("print".to_string()), 2, 1),
+            (TokenValue::LeftParentheses, 2, 6),
+            (TokenValue::String("hi".to_string()), 2, 7),
+            (TokenValue::RightParentheses, 2, 11),
+            (TokenValue::Eof, 2, 11)
+        ]);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::intermediate::{Token, CompileErrorList, TokenValue, CompileError};
+use crate::intermediate::{Token, CompileErrorList, TokenValue, CompileError, Severity};
 use crate::intermediate::ast::{Document, Definition, ModelDefinition, FunctionDefinition, Statement, ImplementDefinition, ApplyDefinition, LocalDefinition, IncludeDefinition, ReturnStatement, Expression, IdentifierExpression, IntegerExpression, FloatExpression, BooleanExpression, ThisExpression, NullExpression, PrefixExpression, IfExpression, InfixExpression, CallExpression, StringExpression, InstanceGetExpression, LocalStatement, ArrayExpression, IndexGetExpression, ForStatement, BreakStatement, RescueStatement};
 use crate::intermediate::TokenValue::Identifier;
 use crate::frontend::lexer::lex;
@@ -12,14 +12,15 @@ use std::slice::Iter;
 enum SymbolPriority {
     Lowest      = 0,
     Assign      = 1,
-    Boolean     = 2,
-    Equals      = 3,
-    LessGreater = 4,
-    Sum         = 5,
-    Product     = 6,
-    Prefix      = 7,
-    Call        = 8,
-    InstanceGet = 9
+    Coalesce    = 2,
+    Boolean     = 3,
+    Equals      = 4,
+    LessGreater = 5,
+    Sum         = 6,
+    Product     = 7,
+    Prefix      = 8,
+    Call        = 9,
+    InstanceGet = 10
 }
 
 struct ParserState<'a> {
@@ -31,6 +32,9 @@ struct ParserState<'a> {
 }
 
 impl<'a> ParserState<'a> {
+    // tokens which can not be in a body, a body meets them is not closed
+    const BODY_BREAKERS: [TokenValue; 8] = [ TokenValue::Function, TokenValue::Model, TokenValue::Implement, TokenValue::Apply, TokenValue::Public, TokenValue::Include, TokenValue::Eof, TokenValue::None ];
+
     fn next_token(&mut self) {
         self.previous_token = mem::replace(&mut self.current_token, 
             mem::replace(&mut self.peek_token, Token::none()));
@@ -43,7 +47,8 @@ impl<'a> ParserState<'a> {
     fn push_error(&mut self, token: &Token, message: String) {
         let error = CompileError {
             token: token.clone(),
-            message
+            message,
+            severity: Severity::Error
         };
 
         self.errors.push(error);
@@ -52,12 +57,13 @@ impl<'a> ParserState<'a> {
     fn get_current_priority(&self) -> SymbolPriority {
         match self.current_token.value {
             TokenValue::Assign | TokenValue::PlusAssign | TokenValue::MinusAssign | TokenValue::StarAssign | TokenValue::SlashAssign | TokenValue::PercentAssign => SymbolPriority::Assign,
+            TokenValue::QuestionQuestion => SymbolPriority::Coalesce,
             TokenValue::And | TokenValue::Or => SymbolPriority::Boolean,
             TokenValue::Equal | TokenValue::NotEqual => SymbolPriority::Equals,
             TokenValue::Less | TokenValue::Greater | TokenValue::LessEqual | TokenValue::GreaterEqual => SymbolPriority::LessGreater,
             TokenValue::Plus | TokenValue::Minus => SymbolPriority::Sum,
             TokenValue::Star | TokenValue::Slash | TokenValue::Percent | TokenValue::BitAnd | TokenValue::BitOr => SymbolPriority::Product,
-            TokenValue::Dot | TokenValue::LeftBracket => SymbolPriority::InstanceGet,
+            TokenValue::Dot | TokenValue::QuestionDot | TokenValue::LeftBracket => SymbolPriority::InstanceGet,
             TokenValue::LeftParentheses => SymbolPriority::Call,
             _ => SymbolPriority::Lowest
         }
@@ -100,6 +106,19 @@ impl<'a> ParserState<'a> {
         }
     }
 
+    // the block opened by the opener token must be closed here, otherwise the error points at the opener
+    fn expect_end_of(&mut self, opener: &Token) -> bool {
+        if self.current_token.value == TokenValue::End {
+            self.next_token();
+            return true;
+        };
+
+        let keyword = format!("{:?}", opener.value).to_lowercase();
+        self.push_error(opener, format!("expected `end` to close `{}` opened at line {}", keyword, opener.position.line));
+
+        false
+    }
+
     fn parse_identifier_expression(&mut self) -> Option<Expression> {
         if !self.expect_token(TokenValue::Identifier("".to_string())) {
             return None;
@@ -195,23 +214,34 @@ impl<'a> ParserState<'a> {
             None
         }
     }
+    // an if on the next line is a nested if in the else body, which needs its own end
+    fn current_token_is_else_if(&self) -> bool {
+        self.current_token.value == TokenValue::Else && self.peek_token.value == TokenValue::If && self.peek_token.position.line == self.current_token.position.line
+    }
+
     fn parse_if_expression(&mut self) -> Option<Expression> {
         if !self.current_token_is_any_of(&[ TokenValue::If, TokenValue::ElseIf ]) {
             return None;
         }
+        let opener = self.current_token.clone();
         self.next_token();
 
         let mut expect_end_token = true;
 
         if let Some(condition) = self.parse_expression(SymbolPriority::Lowest) {
-            let true_part = self.parse_body(&[ TokenValue::End, TokenValue::Else, TokenValue::ElseIf, TokenValue::Eof ]);
+            let true_part = self.parse_body(&[ TokenValue::End, TokenValue::Else, TokenValue::ElseIf ]);
 
             let mut false_part = None;
 
+            if self.current_token_is_else_if() {
+                // `else if` on one line is the same as `elseif`, the if is the start of the chain
+                self.next_token();
+            }
+
             if self.current_token.value == TokenValue::Else {
                 self.next_token();
-                false_part = Some(self.parse_body(&[ TokenValue::End, TokenValue::Eof ]))
-            } else if self.current_token.value == TokenValue::ElseIf {
+                false_part = Some(self.parse_body(&[ TokenValue::End ]))
+            } else if self.current_token_is_any_of(&[ TokenValue::If, TokenValue::ElseIf ]) {
                 expect_end_token = false;
 
                 if let Some(expression) = self.parse_if_expression() {
@@ -222,7 +252,7 @@ impl<'a> ParserState<'a> {
                 };
             }
 
-            if expect_end_token && !self.expect_and_pop_token(TokenValue::End) {
+            if expect_end_token && !self.expect_end_of(&opener) {
                 return None;
             };
 
@@ -236,19 +266,15 @@ impl<'a> ParserState<'a> {
         }
     }
     
+    // a trailing comma before the end token is allowed
     fn parse_comma_expressions(&mut self, end_tokens: &[ TokenValue ]) -> Option<Vec<Expression>> {
         let mut values = Vec::new();
 
-        let mut last_comma = None;
-
         while !self.current_token_is_any_of(end_tokens) {
-            last_comma = None;
-
             if let Some(parameter) = self.parse_expression(SymbolPriority::Lowest) {
                 values.push(parameter);
 
                 if self.current_token.value == TokenValue::Comma {
-                    last_comma = Some(self.current_token.clone());
                     self.next_token();
                 }
 
@@ -257,10 +283,6 @@ impl<'a> ParserState<'a> {
             }
         }
 
-        if let Some(token) = last_comma {
-            self.push_error(&token, "Unexpect token".to_string());
-        };
-
         Some(values)
     }
 
@@ -325,7 +347,8 @@ impl<'a> ParserState<'a> {
         self.next_token();
 
         match token.value.clone() {
-            TokenValue::Dot => {
+            // the token tells the compiler whether it is null safe
+            TokenValue::Dot | TokenValue::QuestionDot => {
                 let identifier_token = self.current_token.clone();
                 if let TokenValue::Identifier(identifier) = identifier_token.value.clone() {
                     self.next_token();
@@ -377,7 +400,7 @@ impl<'a> ParserState<'a> {
 
         match self.current_token.value {
             TokenValue::Assign | TokenValue::PlusAssign | TokenValue::MinusAssign | TokenValue::StarAssign | TokenValue::SlashAssign | TokenValue::PercentAssign |
-            TokenValue::And | TokenValue::Or | TokenValue::Equal | TokenValue::NotEqual | TokenValue::Less | TokenValue::Greater | TokenValue::LessEqual | TokenValue::GreaterEqual |
+            TokenValue::QuestionQuestion | TokenValue::And | TokenValue::Or | TokenValue::Equal | TokenValue::NotEqual | TokenValue::Less | TokenValue::Greater | TokenValue::LessEqual | TokenValue::GreaterEqual |
             TokenValue::BitAnd | TokenValue::BitOr | TokenValue::Plus | TokenValue::Minus | TokenValue::Star | TokenValue::Slash | TokenValue::Percent
             => {
                 let token = self.current_token.clone();
@@ -396,7 +419,7 @@ impl<'a> ParserState<'a> {
                 }
             }
             TokenValue::LeftParentheses => self.parse_call_expression(expression),
-            TokenValue::Dot | TokenValue::LeftBracket => self.parse_instance_get_expression(expression),
+            TokenValue::Dot | TokenValue::QuestionDot | TokenValue::LeftBracket => self.parse_instance_get_expression(expression),
             _ => None
         }
     }
@@ -436,7 +459,20 @@ impl<'a> ParserState<'a> {
     }
 
     fn parse_rescue_statement(&mut self) -> Option<Statement> {
-        self.parse_simple_statement(|token| Statement::Rescue(RescueStatement { token }))
+        let token = self.current_token.clone();
+        self.next_token();
+
+        // the identifier must at the same line, otherwise it is the first statement of rescue block
+        let identifier = match self.current_token.value {
+            TokenValue::Identifier(_) if self.current_token.position.line == token.position.line => {
+                let identifier = self.current_token.clone();
+                self.next_token();
+                Some(identifier)
+            },
+            _ => None
+        };
+
+        Some(Statement::Rescue(RescueStatement { token, identifier }))
     }
 
     fn parse_expression_statement(&mut self) -> Option<Statement> {
@@ -487,6 +523,20 @@ impl<'a> ParserState<'a> {
         let identifier = self.current_token.clone();
         self.next_token();
 
+        let value_identifier = if self.current_token.value == TokenValue::Comma {
+            self.next_token();
+
+            if !self.expect_token(TokenValue::Identifier("".to_string())) {
+                return None;
+            };
+
+            let value_identifier = self.current_token.clone();
+            self.next_token();
+            Some(value_identifier)
+        } else {
+            None
+        };
+
         if !self.expect_and_pop_token(TokenValue::In) {
             return None;
         };
@@ -497,15 +547,16 @@ impl<'a> ParserState<'a> {
             return None;
         };
 
-        let statements = self.parse_body(&[ TokenValue::Eof, TokenValue::End ]);
+        let statements = self.parse_body(&[ TokenValue::End ]);
 
-        if !self.expect_and_pop_token(TokenValue::End) {
+        if !self.expect_end_of(&token) {
             return None;
         };
 
         Some(Statement::For(ForStatement{
             token,
             identifier,
+            value_identifier,
             enumerable: expression.unwrap(),
             statements
         }))
@@ -524,6 +575,7 @@ impl<'a> ParserState<'a> {
 
     fn parse_model_definition(&mut self) -> Option<Definition> {
         // skip model token
+        let opener = self.current_token.clone();
         self.next_token();
 
         // model name
@@ -542,7 +594,7 @@ impl<'a> ParserState<'a> {
             self.next_token();
         };
 
-        if !self.expect_and_pop_token(TokenValue::End) {
+        if !self.expect_end_of(&opener) {
             return None;
         }
 
@@ -556,11 +608,23 @@ impl<'a> ParserState<'a> {
         // Initialize an empty vector to store statements
         let mut statements = Vec::new();
 
-        // Continue parsing statements until a terminator token is encountered
-        while !self.current_token_is_any_of(terminators) {
+        // Continue parsing statements until a terminator token is encountered,
+        // a definition keyword or the end of file also ends the body, the block is left open then
+        while !self.current_token_is_any_of(terminators) && !self.current_token_is_any_of(&ParserState::BODY_BREAKERS) {
+            // semicolon is an optional separator, for more than one statement in a line
+            if self.current_token.value == TokenValue::Semicolon {
+                self.next_token();
+                continue;
+            };
+
+            let remaining_tokens = self.tokens.len();
+
             // Parse and add each statement to the vector, if available
             if let Some(statement) = self.parse_statement() {
                 statements.push(statement);
+            } else if remaining_tokens == self.tokens.len() {
+                // nothing consumed, skip the token so we will not stuck here
+                self.next_token();
             };
         };
 
@@ -570,6 +634,7 @@ impl<'a> ParserState<'a> {
 
     fn parse_function_definition(&mut self) -> Option<Definition> {
         // skip function token
+        let opener = self.current_token.clone();
         self.next_token();
 
         // function name
@@ -583,13 +648,10 @@ impl<'a> ParserState<'a> {
             return None;
         };
 
-        // parse parameters
+        // parse parameters, a trailing comma is allowed
         let mut parameters = Vec::new();
-        let mut last_comma = None;
 
         while self.current_token.value != TokenValue::RightParentheses {
-            last_comma = None;
-
             if parameters.len() == 0 {
                 if !self.current_token_is_any_of(&[ TokenValue::Identifier("".to_string()), TokenValue::This ]) {
                     self.errors.push_error(&self.current_token.clone(), "Unexpect token");
@@ -603,23 +665,18 @@ impl<'a> ParserState<'a> {
             self.next_token();
 
             if self.current_token.value == TokenValue::Comma {
-                last_comma = Some(self.current_token.clone());
                 self.next_token();
             }
         }
 
-        if let Some(comma) = last_comma {
-            self.push_error(&comma.clone(), format!("Unexpect token [{:?}]", comma.value));
-        };
-
         if !self.expect_and_pop_token(TokenValue::RightParentheses) {
             return None;
         };
 
         // parse function body
-        let body = self.parse_body(&[ TokenValue::End, TokenValue::Eof ]);
+        let body = self.parse_body(&[ TokenValue::End ]);
 
-        if !self.expect_and_pop_token(TokenValue::End) {
+        if !self.expect_end_of(&opener) {
             return None;
         };
 
@@ -631,6 +688,7 @@ impl<'a> ParserState<'a> {
     }
 
     fn parse_implement_definition(&mut self) -> Option<Definition> {
+        let opener = self.current_token.clone();
         self.next_token();
 
         if !self.expect_token(TokenValue::Identifier("".to_string())) {
@@ -639,22 +697,32 @@ impl<'a> ParserState<'a> {
         let model_name = self.current_token.clone();
         self.next_token();
 
+        let mut properties = Vec::new();
         let mut functions = Vec::new();
 
-        while self.current_token.value == TokenValue::Function {
-            if let Some(definition) = self.parse_function_definition() {
-                if let Definition::Function(function_definition) = definition {
-                    functions.push(function_definition);
-                };
+        // properties are names between the functions, same as in a model
+        loop {
+            match self.current_token.value {
+                TokenValue::Function => {
+                    if let Some(Definition::Function(function_definition)) = self.parse_function_definition() {
+                        functions.push(function_definition);
+                    };
+                },
+                TokenValue::Identifier(_) => {
+                    properties.push(self.current_token.clone());
+                    self.next_token();
+                },
+                _ => break
             };
         };
 
-        if !self.expect_and_pop_token(TokenValue::End) {
+        if !self.expect_end_of(&opener) {
             return None;
         };
 
         Some(Definition::Implement(ImplementDefinition {
             model_name,
+            properties,
             functions
         }))
     }
@@ -734,7 +802,15 @@ impl<'a> ParserState<'a> {
 
         let mut last_comma = None;
 
-        while models.len() == 0 || self.current_token_is_any_of(&[ Identifier("".to_string()) ]) {
+        let wildcard = if self.current_token.value == TokenValue::Star {
+            let star = self.current_token.clone();
+            self.next_token();
+            Some(star)
+        } else {
+            None
+        };
+
+        while wildcard.is_none() && (models.len() == 0 || self.current_token_is_any_of(&[ Identifier("".to_string()) ])) {
             last_comma = None;
 
             if !self.expect_token(TokenValue::Identifier("".to_string())) {
@@ -777,6 +853,7 @@ impl<'a> ParserState<'a> {
         Some(Definition::Include(IncludeDefinition {
             public_names: models,
             aliases,
+            wildcard,
             filename
         }))
     }
@@ -799,6 +876,13 @@ impl<'a> ParserState<'a> {
                     None
                 }
             },
+            TokenValue::Include => {
+                if let Some(Definition::Include(include_definition)) = self.parse_include_definition() {
+                    Some(Definition::PublicInclude(include_definition))
+                } else {
+                    None
+                }
+            },
             _ => {
                 self.push_error(&self.current_token.clone(), "Unexpect token".to_string());
                 None
@@ -817,7 +901,7 @@ impl<'a> ParserState<'a> {
             TokenValue::Public => self.parse_public_definition(),
             _ => {
                 self.push_error(&self.current_token.clone(), format!("Unexcpet token [{:?}]", self.current_token.clone()));
-                self.skip_until(&[ TokenValue::Include, TokenValue::Public, TokenValue::Model, TokenValue::Implement, TokenValue::Apply, TokenValue::Local, TokenValue::Function ]);
+                self.skip_until(&[ TokenValue::Include, TokenValue::Public, TokenValue::Model, TokenValue::Implement, TokenValue::Apply, TokenValue::Local, TokenValue::Function, TokenValue::Eof, TokenValue::None ]);
                 None
             }
         }
@@ -833,7 +917,7 @@ impl<'a> ParserState<'a> {
 
             if let Some(definition) = self.parse_definition() {
 
-                if let Definition::Include(_) = definition {
+                if let Definition::Include(_) | Definition::PublicInclude(_) = definition {
                     if include_definitions_ended {
                         self.push_error(&current_token, "include definition must at the top of files".to_string());
                     };
@@ -881,4 +965,32 @@ pub fn parse(source: &str, filename: &str) -> Result<Document, CompileErrorList>
     } else {
         Err(env.errors)
     }
-}
\ No newline at end of file
+}
+
+// statements outside of any function, used by the repl
+pub fn parse_statements(source: &str, filename: &str) -> Result<Vec<Statement>, CompileErrorList> {
+    let token_list = lex(source)?;
+
+    let mut env = ParserState {
+        tokens: token_list.iter(),
+        previous_token: Token::none(),
+        current_token: Token::none(),
+        peek_token: Token::none(),
+        errors: CompileErrorList::new(filename)
+    };
+
+    env.next_token();
+    env.next_token();
+
+    let statements = env.parse_body(&[ TokenValue::Eof ]);
+
+    if !env.current_token_is_any_of(&[ TokenValue::Eof, TokenValue::None ]) {
+        env.push_error(&env.current_token.clone(), format!("Unexpect token [{:?}], definitions and statements can not be mixed", env.current_token.value));
+    };
+
+    if env.errors.is_empty() {
+        Ok(statements)
+    } else {
+        Err(env.errors)
+    }
+}
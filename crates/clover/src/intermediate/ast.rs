@@ -111,7 +111,8 @@ pub enum Definition {
     Local(LocalDefinition),
     Include(IncludeDefinition),
     PublicModel(ModelDefinition),
-    PublicFunction(FunctionDefinition)
+    PublicFunction(FunctionDefinition),
+    PublicInclude(IncludeDefinition)
 }
 
 #[derive(Clone, Debug)]
@@ -131,6 +132,7 @@ pub struct FunctionDefinition {
 #[derive(Clone, Debug)]
 pub struct ImplementDefinition {
     pub model_name: Token,
+    pub properties: Vec<Token>,
     pub functions: Vec<FunctionDefinition>
 }
 
@@ -150,6 +152,8 @@ pub struct LocalDefinition {
 pub struct IncludeDefinition {
     pub public_names: Vec<Token>,
     pub aliases: Vec<Token>,
+    // the * of `include * from`, every public name of the file is included and the lists above are empty
+    pub wildcard: Option<Token>,
     pub filename: Token
 }
 
@@ -181,13 +185,17 @@ pub struct BreakStatement {
 
 #[derive(Clone, Debug)]
 pub struct RescueStatement {
-    pub token: Token
+    pub token: Token,
+    // local variable to receive the error object
+    pub identifier: Option<Token>
 }
 
 #[derive(Clone, Debug)]
 pub struct ForStatement {
     pub token: Token,
     pub identifier: Token,
+    // second name of `for key, value in`, the identifier is the key then
+    pub value_identifier: Option<Token>,
     pub enumerable: Expression,
     pub statements: Vec<Statement>
 }
@@ -203,7 +211,7 @@ impl Document {
         let path = env::current_dir().unwrap().canonicalize().unwrap();
 
         let mut definition_iterator = self.definitions.iter_mut();
-        while let Some(Definition::Include(definition)) = definition_iterator.next() {
+        while let Some(Definition::Include(definition) | Definition::PublicInclude(definition)) = definition_iterator.next() {
             let mut current_path = PathBuf::from(&self.filename);
             current_path.pop();
 
@@ -220,7 +228,7 @@ impl Document {
         let mut filenames = Vec::new();
 
         let mut definition_iterator = self.definitions.iter();
-        while let Some(Definition::Include(definition)) = definition_iterator.next() {
+        while let Some(Definition::Include(definition) | Definition::PublicInclude(definition)) = definition_iterator.next() {
             if let TokenValue::String(filename) = &definition.filename.value {
                     filenames.push(filename.clone());
             };
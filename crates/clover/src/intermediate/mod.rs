@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use crate::runtime::object::{Integer, Float};
 
 pub mod ast;
 
@@ -11,8 +12,8 @@ pub enum TokenValue {
     Identifier(String),
 
     String(String),
-    Integer(i64),
-    Float(f64),
+    Integer(Integer),
+    Float(Float),
 
     True,
     False,
@@ -52,7 +53,10 @@ pub enum TokenValue {
 
     Comma,
     Colon,
+    Semicolon,
     Dot,
+    QuestionDot,
+    QuestionQuestion,
 
     Include,
     From,
@@ -133,14 +137,25 @@ impl Token {
 
 pub type TokenList = Vec<Token>;
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
 #[derive(Clone, Debug)]
 pub struct CompileError {
     pub token: Token,
-    pub message: String
+    pub message: String,
+    pub severity: Severity
 }
 
 impl Display for CompileError {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.severity == Severity::Warning {
+            formatter.write_str("warning ")?;
+        };
+
         formatter.write_fmt(format_args!("at ({}, {}) near {} - {}", self.token.position.line, self.token.position.column, self.token.value.to_string(), self.message))
     }
 }
@@ -168,13 +183,66 @@ impl CompileErrorList {
     pub fn push_error(&mut self, token: &Token, message: &str) {
         self.push(CompileError {
             token: token.clone(),
-            message: message.to_string()
+            message: message.to_string(),
+            severity: Severity::Error
+        });
+    }
+
+    pub fn push_warning(&mut self, token: &Token, message: &str) {
+        self.push(CompileError {
+            token: token.clone(),
+            message: message.to_string(),
+            severity: Severity::Warning
         });
     }
 
     pub fn is_empty(&self) -> bool {
         self.errors.is_empty()
     }
+
+    /// The entries as a JSON array of objects with `filename`, `line`, `column`, `severity` ("error" or
+    /// "warning") and `message`, for editors.
+    pub fn to_json(&self) -> String {
+        format!("[{}]", self.json_entries().join(","))
+    }
+
+    /// Same as `to_json`, the entries of every list in one array, such as the warnings of all files.
+    pub fn lists_to_json(lists: &[CompileErrorList]) -> String {
+        let entries: Vec<String> = lists.iter().flat_map(|list| list.json_entries()).collect();
+
+        format!("[{}]", entries.join(","))
+    }
+
+    fn json_entries(&self) -> Vec<String> {
+        self.errors.iter().map(|error| {
+            let severity = match error.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning"
+            };
+
+            format!("{{\"filename\":{},\"line\":{},\"column\":{},\"severity\":\"{}\",\"message\":{}}}",
+                json_string(&self.filename), error.token.position.line, error.token.position.column, severity, json_string(&error.message))
+        }).collect()
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut result = String::from("\"");
+
+    for character in value.chars() {
+        match character {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            character if (character as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", character as u32)),
+            character => result.push(character)
+        };
+    };
+
+    result.push('"');
+    result
 }
 
 impl Display for CompileErrorList {
@@ -4,23 +4,39 @@ mod frontend;
 mod intermediate;
 mod backend;
 mod runtime;
+mod repl;
 pub mod version;
 
 pub use runtime::program::Program;
+pub use runtime::program_builder::{ProgramBuilder, BuildError};
 pub use runtime::env::Env;
 pub use runtime::object::Object;
+pub use runtime::object::Integer;
 pub use runtime::object::NativeModel;
 pub use runtime::object::NativeModelInstance;
 pub use runtime::object::Reference;
+pub use runtime::object::WeakObject;
+pub use backend::compile_cache::CompileCache;
+pub use repl::{Repl, ReplError};
+
+/// Former name of `Env`, kept so code written against it still builds.
+#[deprecated(note = "use `Env` instead")]
+pub type State = Env;
 
 use backend::compiler::DefaultStorage;
 use backend::compiler::compile_file;
+use backend::compiler::compile_with_entry;
+use backend::compiler::compile_directory;
+use backend::compiler::compile_file_with_cache;
+use backend::compiler::compile_file_with_warnings;
 use std::ops::{Deref, DerefMut};
 
 //use clover_std::clover_std_inject_to;
 
 pub mod helper {
     pub use crate::runtime::object::make_reference;
+    pub use crate::runtime::object::MAX_ALLOCATION_LENGTH;
+    pub use crate::runtime::object_display::reference_address;
     pub use crate::backend::compiler::Storage;
 
     pub use crate::runtime::object::ensure_parameters_length;
@@ -28,24 +44,47 @@ pub mod helper {
 
 pub mod debug {
     pub use crate::intermediate::CompileErrorList;
+    pub use crate::intermediate::Severity;
     pub use crate::runtime::program::RuntimeError;
     pub use crate::intermediate::Position;
+    pub use crate::runtime::runtime_info::FunctionCoverage;
+}
+
+/// Instructions of a `Program`, for building one with `ProgramBuilder`.
+pub mod bytecode {
+    pub use crate::runtime::opcode::{Instruction, OpCode};
+    pub use crate::runtime::opcode::{OPERATION_ADD, OPERATION_SUB, OPERATION_MULTIPLY, OPERATION_DIVIDE, OPERATION_MOD, OPERATION_EQUAL, OPERATION_GREATER, OPERATION_LESS, OPERATION_GREATER_EQUAL, OPERATION_LESS_EQUAL, OPERATION_AND, OPERATION_OR};
+}
+
+/// Access to the front end for editor tooling such as syntax highlighting.
+pub mod tooling {
+    pub use crate::intermediate::{Token, TokenValue, Position};
+
+    /// Splits the source into tokens, each with the position it starts at. The last token is `TokenValue::Eof`.
+    pub fn tokenize(source: &str) -> Result<Vec<Token>, crate::debug::CompileErrorList> {
+        crate::frontend::lexer::lex(source)
+    }
+
+    pub use crate::frontend::lexer::{keywords, symbols};
 }
 
 pub struct Clover {
-    storage: Box<dyn helper::Storage>
+    storage: Box<dyn helper::Storage>,
+    native_names: Vec<String>
 }
 
 impl Clover {
     pub fn new_with_file_loader(storage: Box<dyn helper::Storage>) -> Clover {
         Clover {
-            storage
+            storage,
+            native_names: Vec::new()
         }
     }
 
     pub fn new() -> Clover {
         Clover {
-            storage: Box::new(DefaultStorage::new())
+            storage: Box::new(DefaultStorage::new()),
+            native_names: Vec::new()
         }
     }
 
@@ -53,6 +92,40 @@ impl Clover {
         compile_file(filename, self.storage.deref())
     }
 
+    /// Compiles the source with the function `entry_name` of it as entry point instead of `main`,
+    /// it is a compile error when the source does not define that function.
+    pub fn compile_with_entry(&self, source: &str, filename: &str, entry_name: &str) -> Result<Program, debug::CompileErrorList> {
+        compile_with_entry(source, filename, entry_name, self.storage.deref())
+    }
+
+    /// Same as `compile_file`, also returns the warnings (such as unused locals) of each file.
+    pub fn compile_file_with_warnings(&self, filename: &str) -> Result<(Program, Vec<debug::CompileErrorList>), debug::CompileErrorList> {
+        compile_file_with_warnings(filename, self.storage.deref(), &self.native_names, &mut CompileCache::new())
+    }
+
+    /// Same as `compile_file_with_warnings`, but unchanged files are not parsed again (see `compile_file_with_cache`).
+    pub fn compile_file_with_warnings_and_cache(&self, filename: &str, cache: &mut CompileCache) -> Result<(Program, Vec<debug::CompileErrorList>), debug::CompileErrorList> {
+        compile_file_with_warnings(filename, self.storage.deref(), &self.native_names, cache)
+    }
+
+    /// Tells the compiler a native model (such as `Math`) will be added to the env by this name,
+    /// `compile_file_with_warnings` warns about locals, parameters, functions and models which shadow it.
+    pub fn add_native_name(&mut self, name: &str) {
+        self.native_names.push(name.to_string());
+    }
+
+    /// Same as `compile_file`, but files whose content is unchanged since the last compilation with
+    /// the same cache are not parsed again.
+    pub fn compile_file_with_cache(&self, filename: &str, cache: &mut CompileCache) -> Result<Program, debug::CompileErrorList> {
+        compile_file_with_cache(filename, self.storage.deref(), cache)
+    }
+
+    /// Compiles every `.luck` file under the directory into one program, includes between them
+    /// are resolved as usual. The `main` function of `main.luck` in the directory is the entry point.
+    pub fn compile_directory(&self, directory: &str) -> Result<Program, debug::CompileErrorList> {
+        compile_directory(directory, self.storage.deref())
+    }
+
     pub fn save_program(&self, filename: &str, program: &Program) -> Result<(), debug::CompileErrorList> {
 
         let mut writer = self.storage.get_writer(filename)?;
@@ -114,6 +187,15 @@ mod tests {
         };
     }
 
+    fn error_messages(list: &crate::debug::CompileErrorList) -> Vec<(String, u16)> {
+        list.errors.iter().map(|error| (error.message.clone(), error.token.position.line)).collect()
+    }
+
+    // messages and lines of the errors compiling the source as a file in tests, so includes are found
+    fn compile_messages(source: &str) -> Vec<(String, u16)> {
+        error_messages(&crate::backend::compiler::compile(source, "tests/x.luck", &crate::backend::compiler::DefaultStorage::new()).unwrap_err())
+    }
+
     fn execute(filename: &str, function_names: &[ &str ]) {
         let clover = Clover::new();
 
@@ -130,6 +212,132 @@ mod tests {
         };
     }
 
+    fn invoke(env: &mut Env, parameters: &[ Object ]) -> Result<Object, crate::debug::RuntimeError> {
+        env.call(&parameters[0], &parameters[1..])
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn state_alias() {
+        fn run(state: &mut crate::State) -> Result<Object, crate::debug::RuntimeError> {
+            state.execute()
+        }
+
+        let program = Clover::new().compile_file("tests/directory/main.luck").unwrap();
+        let mut env: Env = program.into();
+
+        assert!(matches!(run(&mut env), Ok(Object::Boolean(true))));
+    }
+
+    #[test]
+    fn object_equality() {
+        assert_eq!(Object::Integer(3), Object::Integer(3));
+        assert_ne!(Object::Integer(3), Object::Float(3.0));
+        assert_eq!(Object::from("text"), Object::from("text"));
+        assert_eq!(Object::Null, Object::Null);
+
+        // arrays are compared item by item, also the ones which contain themselves
+        let array = Object::from(vec![ Object::from(1), Object::from(vec![ Object::from("two") ]) ]);
+        assert_eq!(array, Object::from(vec![ Object::from(1), Object::from(vec![ Object::from("two") ]) ]));
+        assert_ne!(array, Object::from(vec![ Object::from(1) ]));
+
+        let looped = Object::from(vec![ Object::from(1) ]);
+        if let Object::Array(reference) = &looped {
+            reference.borrow_mut().push(looped.clone());
+        };
+        assert_eq!(looped, looped.clone());
+
+        // maps are compared entry by entry, the order does not matter
+        let first = Object::NativeInstance(crate::helper::make_reference(TestMap(vec![ ("a".to_string(), Object::from(1)), ("b".to_string(), Object::from(2)) ])));
+        let second = Object::NativeInstance(crate::helper::make_reference(TestMap(vec![ ("b".to_string(), Object::from(2)), ("a".to_string(), Object::from(1)) ])));
+        assert_eq!(first, second);
+
+        // instances are equal when they are the same one
+        let instance = Object::Instance(crate::helper::make_reference(crate::runtime::object::ModelInstance { model_index: 0, properties: vec![ Object::from(1) ] }));
+        let same_values = Object::Instance(crate::helper::make_reference(crate::runtime::object::ModelInstance { model_index: 0, properties: vec![ Object::from(1) ] }));
+        assert_eq!(instance, instance.clone());
+        assert_ne!(instance, same_values);
+
+        assert_eq!(Object::NativeFunction(invoke), Object::NativeFunction(invoke));
+    }
+
+    #[test]
+    fn program_accessor() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/function.luck").unwrap();
+
+        let program = env.get_program();
+        let names: Vec<String> = (0..program.functions.len()).map(|function_index| program.function_name(function_index)).collect();
+        assert!(names.iter().any(|name| name == "with_return"));
+
+        let function_index = program.find_function("with_return").unwrap();
+        assert_eq!(program.function_name(function_index), "with_return");
+        assert!(program.find_function("missing").is_none());
+
+        assert!(env.execute_by_function_index(function_index, &[]).is_ok());
+    }
+
+    #[test]
+    fn native_callback() {
+        let clover = Clover::new();
+
+        let mut env = clover.create_state_by_filename("tests/callback.luck").unwrap();
+        env.add_native_function("invoke", invoke);
+
+        for function_name in [ "call_function", "call_instance_function", "call_native_function" ] {
+            execute_function(&mut env, function_name);
+        };
+    }
+
+    struct TestMap(Vec<(String, Object)>);
+
+    impl crate::NativeModelInstance for TestMap {
+        fn index_get(&self, _this: crate::Reference<dyn crate::NativeModelInstance>, _index: &Object) -> Result<Object, crate::debug::RuntimeError> { Ok(Object::Null) }
+        fn index_set(&mut self, _this: crate::Reference<dyn crate::NativeModelInstance>, index: &Object, value: Object) -> Result<(), crate::debug::RuntimeError> {
+            self.0.retain(|(key, _)| *key != index.to_string());
+            self.0.push((index.to_string(), value));
+            Ok(())
+        }
+        fn instance_get(&self, _this: crate::Reference<dyn crate::NativeModelInstance>, _key: &str) -> Result<Object, crate::debug::RuntimeError> { Ok(Object::Null) }
+        fn instance_set(&mut self, _this: crate::Reference<dyn crate::NativeModelInstance>, _key: &str, _value: Object) -> Result<(), crate::debug::RuntimeError> { Ok(()) }
+        fn call(&mut self, _this: crate::Reference<dyn crate::NativeModelInstance>, _env: &mut Env, _key: &str, _parameters: &[Object]) -> Result<Object, crate::debug::RuntimeError> { Ok(Object::Null) }
+
+        fn raw_entries(&self) -> Option<Vec<(String, Object)>> { Some(self.0.clone()) }
+        fn raw_copy(&self, entries: Vec<(String, Object)>) -> Option<crate::Reference<dyn crate::NativeModelInstance>> { Some(crate::helper::make_reference(TestMap(entries))) }
+    }
+
+    #[test]
+    fn display() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/display.luck").unwrap();
+
+        let function_index = env.get_program().find_function("nested").unwrap();
+        let nested = env.execute_by_function_index(function_index, &[]).unwrap();
+        assert_eq!(nested.display(env.get_program()), r#"[1, ["two", 3.5], Point { x: 1, y: null }, null, true]"#);
+
+        let first = Object::NativeInstance(crate::helper::make_reference(TestMap(vec![ ("b".to_string(), Object::from(2)), ("a".to_string(), Object::from("x")) ])));
+        let second = Object::NativeInstance(crate::helper::make_reference(TestMap(vec![ ("list".to_string(), Object::from(vec![ Object::from(true) ])) ])));
+        let maps = Object::from(vec![ first, second ]);
+        assert_eq!(maps.display(env.get_program()), r#"[{a: "x", b: 2}, {list: [true]}]"#);
+
+        let array = Object::from(vec![ Object::from(1) ]);
+        if let Object::Array(reference) = &array {
+            reference.borrow_mut().push(array.clone());
+        };
+        assert_eq!(array.display(env.get_program()), "[1, [...]]");
+
+        let function_index = env.get_program().find_function("functions").unwrap();
+        let functions = env.execute_by_function_index(function_index, &[]).unwrap();
+        assert_eq!(functions.display(env.get_program()), "[<function distance>, <function nested>]");
+
+        let function_index = env.get_program().find_function("describe").unwrap();
+        assert_eq!(env.execute_by_function_index(function_index, &[]).unwrap().to_string(), r#"point: Point { x: 1, y: [2.5, "a"] }"#);
+
+        let function_index = env.get_program().find_function("self_reference").unwrap();
+        let point = env.execute_by_function_index(function_index, &[]).unwrap();
+        assert_eq!(point.display(env.get_program()), "Point { x: 1, y: {...} }");
+    }
+
     #[test]
     fn integer_operations() {
         execute("tests/integer_operations.luck", &[ "add", "sub", "multiply", "divide" ]);
@@ -137,17 +345,140 @@ mod tests {
 
     #[test]
     fn for_loop() {
-        execute("tests/for_loop.luck", &[ "simple", "nests", "break_loop", "array", "for_model" ]);
+        execute("tests/for_loop.luck", &[ "simple", "nests", "break_loop", "array", "for_model", "for_model_values", "array_with_index" ]);
+    }
+
+    fn push(_env: &mut Env, parameters: &[ Object ]) -> Result<Object, crate::debug::RuntimeError> {
+        if let Object::Array(array) = &parameters[0] {
+            array.borrow_mut().push(parameters[1].clone());
+        };
+
+        Ok(parameters[0].clone())
+    }
+
+    #[test]
+    fn push_in_for_loop() {
+        let clover = Clover::new();
+
+        let mut env = clover.create_state_by_filename("tests/for_loop.luck").unwrap();
+        env.add_native_function("push", push);
+
+        // the loop would never end if it visited the pushed items
+        env.set_instruction_budget(10_000);
+        execute_function(&mut env, "push_in_loop");
     }
 
     #[test]
     fn error_handling() {
-        execute("tests/error_handling.luck", &[ "in_same_function", "in_child_function" ]);
+        execute("tests/error_handling.luck", &[ "in_same_function", "in_child_function", "rescue_runtime_error", "raise_and_rescue", "rescue_in_parent_function", "three_levels" ]);
+
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/error_handling.luck").unwrap();
+        let function_index = env.get_program().find_function("raise_map").unwrap();
+
+        let map = Object::NativeInstance(crate::helper::make_reference(TestMap(Vec::new())));
+        let rescued = env.execute_by_function_index(function_index, &[ map ]).unwrap();
+        assert_eq!(rescued, Object::NativeInstance(crate::helper::make_reference(TestMap(vec![ ("code".to_string(), Object::Integer(404)) ]))));
+    }
+
+    #[test]
+    fn small_integer() {
+        use crate::runtime::opcode::OpCode;
+
+        let source = "function main()\n    local a = 1\n    local b = 1\n    a + b + 256 + -128 + 257\nend\n";
+        let program = crate::backend::compiler::compile(source, "small_integer.luck", &crate::backend::compiler::DefaultStorage::new()).unwrap();
+
+        // small integers are in the instruction itself, the same value gives the same instruction
+        let instructions: Vec<u64> = program.functions[0].instructions.iter()
+            .filter(|instruction| instruction.opcode() == OpCode::PushInteger)
+            .map(|instruction| instruction.into())
+            .collect();
+
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[0], instructions[1]);
+
+        let integer_constants: Vec<i64> = program.constants.iter().filter_map(|object| if let Object::Integer(value) = object { Some(*value) } else { None }).collect();
+        assert_eq!(integer_constants, vec![ 257 ]);
+
+        assert!(matches!(Clover::new().run(program).unwrap(), Object::Integer(387)));
+    }
+
+    #[test]
+    fn negative_literal() {
+        use crate::runtime::opcode::OpCode;
+
+        let source = "function main()\n    local a = 2\n    -5 + -1000 + -0.5 + -a\nend\n";
+        let program = crate::backend::compiler::compile(source, "negative_literal.luck", &crate::backend::compiler::DefaultStorage::new()).unwrap();
+        let instructions = &program.functions[0].instructions;
+
+        // only the local is negated at runtime
+        assert_eq!(instructions.iter().filter(|instruction| instruction.opcode() == OpCode::Negative).count(), 1);
+
+        let integers: Vec<i64> = instructions.iter().filter_map(|instruction| match instruction.opcode() {
+            OpCode::PushInteger => Some(instruction.operand() as i64 + crate::Program::SMALL_INTEGER_MIN),
+            OpCode::PushConstant => if let Object::Integer(value) = program.constants[instruction.operand() as usize] { Some(value) } else { None },
+            _ => None
+        }).collect();
+        assert_eq!(integers, vec![ 2, -5, -1000 ]);
+
+        assert!(matches!(Clover::new().run(program).unwrap(), Object::Float(value) if value == -1007.5));
+    }
+
+    #[test]
+    fn concatenate_string_literals() {
+        use crate::runtime::opcode::OpCode;
+
+        let source = "function main()\n    \"foo\" + \"bar\"\nend\n";
+        let program = crate::backend::compiler::compile(source, "concatenation.luck", &crate::backend::compiler::DefaultStorage::new()).unwrap();
+        let instructions = &program.functions[0].instructions;
+
+        let opcodes: Vec<OpCode> = instructions.iter().map(|instruction| instruction.opcode()).collect();
+        assert_eq!(opcodes, vec![ OpCode::PushConstant, OpCode::Return ]);
+        assert_eq!(program.constants[instructions[0].operand() as usize], Object::from("foobar"));
+
+        // only the literals before the local are folded, the order of additions is kept
+        let source = "function main()\n    local a = \"c\"\n    \"a\" + \"b\" + a + \"d\"\nend\n";
+        let program = crate::backend::compiler::compile(source, "concatenation.luck", &crate::backend::compiler::DefaultStorage::new()).unwrap();
+        let operations = program.functions[0].instructions.iter().filter(|instruction| instruction.opcode() == OpCode::Operation).count();
+        assert_eq!(operations, 2);
+        assert_eq!(Clover::new().run(program).unwrap(), Object::from("abcd"));
+    }
+
+    #[test]
+    fn keyword_and_symbol_operators() {
+        fn compile(body: &str) -> (Vec<u64>, String) {
+            let source = format!("function main(a, b)\n    {}\nend\n", body);
+            let program = crate::backend::compiler::compile(&source, "operators.luck", &crate::backend::compiler::DefaultStorage::new()).unwrap();
+
+            let instructions = program.functions[0].instructions.iter().map(|instruction| instruction.into()).collect();
+            (instructions, format!("{:?}", program.constants))
+        }
+
+        assert_eq!(compile("a and b"), compile("a && b"));
+        assert_eq!(compile("a or b"), compile("a || b"));
+        assert_eq!(compile("not a"), compile("!a"));
+        assert_eq!(compile("not a and b or not b"), compile("!a && b || !b"));
+
+        // both forms give the same result too
+        execute("tests/logic.luck", &[ "keyword_and_symbol" ]);
+    }
+
+    #[test]
+    fn global_slot() {
+        execute("tests/global.luck", &[ "in_loop", "as_value", "raise_from_global" ]);
+
+        let program = Clover::new().compile_file("tests/global.luck").unwrap();
+        let opcodes: Vec<crate::runtime::opcode::OpCode> = program.functions.iter().flat_map(|function| function.instructions.iter().map(|instruction| instruction.opcode())).collect();
+
+        // every global is resolved to a slot, no lookup by name is left
+        assert!(opcodes.contains(&crate::runtime::opcode::OpCode::GlobalGetSlot));
+        assert!(!opcodes.contains(&crate::runtime::opcode::OpCode::GlobalGet));
+        assert_eq!(program.global_dependencies.len(), 3);
     }
 
     #[test]
     fn function() {
-        execute("tests/function.luck", &[ "recursive", "with_return", "first_class_function", "instance_first_class_function" ]);
+        execute("tests/function.luck", &[ "recursive", "with_return", "first_class_function", "instance_first_class_function", "trailing_comma" ]);
     }
 
     #[test]
@@ -155,24 +486,845 @@ mod tests {
         execute("tests/include.luck", &[ "include_function", "include_with_nickname", "include_model" ]);
     }
 
+    #[test]
+    fn wildcard_include() {
+        execute("tests/wildcard_include.luck", &[ "call_both" ]);
+
+        let source = "include factorial as double from \"./function.luck\"\ninclude * from \"./wildcard_module.luck\"\n";
+        assert_eq!(compile_messages(source), vec![ ("included [double] already exists".to_string(), 2) ]);
+    }
+
+    #[test]
+    fn compile_errors_as_json() {
+        let source = "include factorial as double from \"./function.luck\"\ninclude * from \"./wildcard_module.luck\"\n";
+        let errors = crate::backend::compiler::compile(source, "tests/\"quoted\".luck", &crate::backend::compiler::DefaultStorage::new()).unwrap_err();
+
+        assert_eq!(errors.to_json(), "[{\"filename\":\"tests/\\\"quoted\\\".luck\",\"line\":2,\"column\":9,\"severity\":\"error\",\"message\":\"included [double] already exists\"}]");
+
+        let (_, warnings) = Clover::new().compile_file_with_warnings("tests/warnings.luck").unwrap();
+        assert!(warnings.iter().any(|warnings| warnings.to_json().contains("\"severity\":\"warning\",\"message\":\"local variable [unused] is never used\"")));
+
+        // one array for the lists of all files
+        let json = crate::debug::CompileErrorList::lists_to_json(&warnings);
+        assert_eq!(json.matches("\"severity\"").count(), warnings.iter().map(|list| list.errors.len()).sum::<usize>());
+        assert!(json.starts_with('[') && json.ends_with(']'));
+        assert_eq!(crate::debug::CompileErrorList::lists_to_json(&[]), "[]");
+    }
+
+    #[test]
+    fn public_include() {
+        execute("tests/reexport.luck", &[ "reexported" ]);
+
+        // re-exported names are not unused in the facade
+        let (_, warnings) = Clover::new().compile_file_with_warnings("tests/reexport.luck").unwrap();
+        assert!(warnings.iter().all(|warnings| warnings.errors.is_empty()));
+    }
+
+    #[test]
+    fn directory() {
+        let clover = Clover::new();
+
+        let program = clover.compile_directory("tests/directory").unwrap();
+        assert_eq!(program.file_info.as_ref().unwrap().filenames.len(), 2);
+
+        let result = clover.run(program).unwrap();
+        assert!(matches!(result, Object::Boolean(true)));
+    }
+
+    struct MemoryStorage {
+        files: std::rc::Rc<std::cell::RefCell<std::collections::HashMap<String, String>>>,
+        load_count: std::rc::Rc<std::cell::Cell<usize>>
+    }
+
+    impl crate::helper::Storage for MemoryStorage {
+        fn load_file(&self, filename: &str) -> Result<String, crate::debug::CompileErrorList> {
+            self.load_count.set(self.load_count.get() + 1);
+            Ok(self.files.borrow().get(filename).cloned().unwrap_or_default())
+        }
+
+        fn get_reader(&self, filename: &str) -> Result<Box<dyn std::io::Read>, crate::debug::CompileErrorList> { Err(crate::debug::CompileErrorList::new(filename)) }
+        fn get_writer(&self, filename: &str) -> Result<Box<dyn std::io::Write>, crate::debug::CompileErrorList> { Err(crate::debug::CompileErrorList::new(filename)) }
+    }
+
+    #[test]
+    fn compile_cache() {
+        let files = std::rc::Rc::new(std::cell::RefCell::new(std::collections::HashMap::new()));
+        files.borrow_mut().insert("main.luck".to_string(), "include double from \"shape.luck\"\nfunction main()\n    double(2)\nend\n".to_string());
+        files.borrow_mut().insert("shape.luck".to_string(), "public function double(value)\n    value * 2\nend\n".to_string());
+
+        let load_count = std::rc::Rc::new(std::cell::Cell::new(0));
+        let clover = Clover::new_with_file_loader(Box::new(MemoryStorage { files: files.clone(), load_count: load_count.clone() }));
+        let mut cache = crate::CompileCache::new();
+
+        let program = clover.compile_file_with_cache("main.luck", &mut cache).unwrap();
+        assert_eq!((load_count.get(), cache.parsed_count()), (2, 2));
+        assert!(matches!(clover.run(program).unwrap(), Object::Integer(4)));
+
+        // nothing changed, files are read again but not parsed
+        clover.compile_file_with_cache("main.luck", &mut cache).unwrap();
+        assert_eq!((load_count.get(), cache.parsed_count()), (4, 2));
+
+        // only the changed file is parsed
+        files.borrow_mut().insert("main.luck".to_string(), "include double from \"shape.luck\"\nfunction main()\n    double(5)\nend\n".to_string());
+        let program = clover.compile_file_with_cache("main.luck", &mut cache).unwrap();
+        assert_eq!((load_count.get(), cache.parsed_count()), (6, 3));
+        assert!(matches!(clover.run(program).unwrap(), Object::Integer(10)));
+    }
+
+    #[test]
+    fn repl() {
+        let mut repl = crate::Repl::new();
+
+        assert!(matches!(repl.eval("local a = 40").unwrap(), Object::Integer(40)));
+        assert!(matches!(repl.eval("a + 2").unwrap(), Object::Integer(42)));
+
+        assert!(matches!(repl.eval("function double(value)\n    value * 2\nend").unwrap(), Object::Null));
+        assert!(matches!(repl.eval("local b = double(a)\nb += 1").unwrap(), Object::Integer(81)));
+        assert!(matches!(repl.eval("b").unwrap(), Object::Integer(81)));
+
+        // a failed input does not break the session
+        assert!(matches!(repl.eval("missing_function()"), Err(crate::ReplError::Runtime(_))));
+        assert!(matches!(repl.eval("a +"), Err(crate::ReplError::Compile(_))));
+        assert!(matches!(repl.eval("a").unwrap(), Object::Integer(40)));
+    }
+
+    #[test]
+    fn parallel_compile() {
+        use crate::backend::compiler::{compile_to_with_options, CompilerContext, DefaultStorage};
+
+        let storage = DefaultStorage::new();
+        let source = std::fs::read_to_string("tests/include.luck").unwrap();
+
+        let mut bytes = Vec::new();
+        for parallel in [ false, true ] {
+            let mut context = CompilerContext::new();
+            compile_to_with_options(&mut context, &source, "tests/include.luck", &storage, &mut crate::CompileCache::new(), parallel).unwrap();
+
+            let mut writer = Vec::new();
+            context.to_program().serialize(&mut writer, false).unwrap();
+            bytes.push(writer);
+        };
+
+        assert_eq!(bytes[0], bytes[1]);
+    }
+
+    #[test]
+    fn debug_info_round_trip() {
+        let clover = Clover::new();
+        let program = clover.compile_file("tests/model.luck").unwrap();
+
+        let mut writer = Vec::new();
+        program.serialize(&mut writer, false).unwrap();
+        let loaded = crate::Program::deserialize(&mut writer.as_slice()).unwrap();
+
+        let positions = |program: &crate::Program| -> Vec<Vec<(u16, u16)>> {
+            program.debug_info.as_ref().unwrap().functions.iter()
+                .map(|positions| positions.iter().map(|position| (position.line, position.column)).collect())
+                .collect()
+        };
+
+        assert_eq!(positions(&program), positions(&loaded));
+    }
+
+    #[test]
+    fn nan_and_infinity() {
+        execute("tests/float.luck", &[ "nan_from_infinity" ]);
+
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/float.luck").unwrap();
+        let nan_comparisons = env.get_program().find_function("nan_comparisons").unwrap();
+        assert_eq!(env.execute_by_function_index(nan_comparisons, &[ Object::Float(f64::NAN) ]).unwrap(), Object::Boolean(true));
+
+        let infinity_comparisons = env.get_program().find_function("infinity_comparisons").unwrap();
+        assert_eq!(env.execute_by_function_index(infinity_comparisons, &[ Object::Float(f64::INFINITY) ]).unwrap(), Object::Boolean(true));
+
+        // same for hosts
+        assert_ne!(Object::Float(f64::NAN), Object::Float(f64::NAN));
+        assert_eq!(Object::Float(f64::INFINITY), Object::Float(f64::INFINITY));
+    }
+
+    #[test]
+    fn non_finite_constants_round_trip() {
+        let clover = Clover::new();
+        let mut program = clover.compile_file("tests/float.luck").unwrap();
+        let first = program.constants.len();
+        program.constants.extend([ Object::Float(f64::INFINITY), Object::Float(f64::NEG_INFINITY), Object::Float(f64::NAN) ]);
+
+        let mut writer = Vec::new();
+        program.serialize(&mut writer, false).unwrap();
+        let loaded = crate::Program::deserialize(&mut writer.as_slice()).unwrap();
+
+        assert_eq!(loaded.constants[first], Object::Float(f64::INFINITY));
+        assert_eq!(loaded.constants[first + 1], Object::Float(f64::NEG_INFINITY));
+        assert!(matches!(loaded.constants[first + 2], Object::Float(value) if value.is_nan()));
+    }
+
+    #[test]
+    fn numeric_width_round_trip() {
+        let clover = Clover::new();
+        let source = "function main()\n    1234567890123 + 0.5\nend\n";
+        let program = crate::backend::compiler::compile(source, "width.luck", &crate::backend::compiler::DefaultStorage::new()).unwrap();
+
+        let mut writer = Vec::new();
+        program.serialize(&mut writer, false).unwrap();
+
+        // bytecode version, then widths of integers and floats after the header and the crate version
+        assert_eq!(&writer[19..22], &[ crate::version::BYTECODE_VERSION, 8, 8 ]);
+
+        let loaded = crate::Program::deserialize(&mut writer.as_slice()).unwrap();
+        assert_eq!(clover.run(loaded).unwrap(), Object::Float(1234567890123.5));
+
+        // older versions have for loops with fewer locals, they can not run
+        for version in 0..crate::version::BYTECODE_VERSION {
+            let mut old = writer.clone();
+            old[19] = version;
+            assert!(crate::Program::deserialize(&mut old.as_slice()).is_err());
+        };
+
+        let mut narrow = writer.clone();
+        narrow[20] = 4;
+        assert!(crate::Program::deserialize(&mut narrow.as_slice()).is_err());
+    }
+
+    #[test]
+    fn bytecode_version() {
+        let source = "function main()\n    1\nend\n";
+        let program = crate::backend::compiler::compile(source, "version.luck", &crate::backend::compiler::DefaultStorage::new()).unwrap();
+
+        let mut writer = Vec::new();
+        program.serialize(&mut writer, true).unwrap();
+        assert_eq!(writer[19], crate::version::BYTECODE_VERSION);
+
+        // written by another crate version with the same bytecode version
+        let mut other_crate = writer.clone();
+        other_crate[16..19].copy_from_slice(&[ crate::version::MAJOR, crate::version::MINOR, crate::version::PATCH.wrapping_add(1) ]);
+        assert!(crate::Program::read_header(&mut other_crate.as_slice()).unwrap().is_empty());
+        assert!(matches!(Clover::new().run(crate::Program::deserialize(&mut other_crate.as_slice()).unwrap()).unwrap(), Object::Integer(1)));
+
+        // older versions mean something else, newer ones are unknown
+        for version in [ 0, 1, 200 ] {
+            let mut other_bytecode = writer.clone();
+            other_bytecode[19] = version;
+            let error = crate::Program::deserialize(&mut other_bytecode.as_slice()).unwrap_err();
+            assert!(error.to_string().starts_with(&format!("bytecode version {} is not supported", version)));
+        };
+    }
+
+    #[test]
+    fn debug_info_tag() {
+        let mut builder = crate::ProgramBuilder::new();
+        let main = builder.add_function("main", 0, 0, vec![ crate::bytecode::OpCode::PushInteger.to_instruction(0), crate::bytecode::OpCode::Return.to_instruction(0) ]);
+        builder.set_entry_point(main);
+
+        let mut writer = Vec::new();
+        builder.build().unwrap().serialize(&mut writer, false).unwrap();
+
+        // the last byte tells there is no debug info
+        assert_eq!(writer.last(), Some(&0));
+        assert!(crate::Program::deserialize(&mut writer.as_slice()).unwrap().debug_info.is_none());
+
+        // ending before the tag is fine, an unknown tag is a broken file
+        assert!(crate::Program::deserialize(&mut &writer[..writer.len() - 1]).is_ok());
+
+        let last = writer.len() - 1;
+        writer[last] = 7;
+        assert_eq!(crate::Program::deserialize(&mut writer.as_slice()).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+        // debug info must have a position for every instruction of every function, runs of one line here
+        let instruction_counts: Vec<usize> = crate::Program::deserialize(&mut &writer[..last]).unwrap().functions.iter().map(|function| function.instructions.len()).collect();
+        let with_debug_info = |function_count: usize, extra_position: bool| {
+            let mut bytes = writer[..last].to_vec();
+            bytes.push(1);
+            bytes.extend_from_slice(&(function_count as u32).to_le_bytes());
+
+            for &count in instruction_counts.iter().take(function_count) {
+                let run_length = if extra_position && count > 0 { count + 1 } else { count };
+                bytes.extend_from_slice(&(count as u32).to_le_bytes());
+                if count > 0 {
+                    bytes.extend_from_slice(&1u16.to_le_bytes());
+                    bytes.extend_from_slice(&(run_length as u32).to_le_bytes());
+                    (0..run_length).for_each(|column| bytes.extend_from_slice(&(column as u16).to_le_bytes()));
+                };
+            };
+
+            crate::Program::deserialize(&mut bytes.as_slice())
+        };
+
+        assert!(with_debug_info(instruction_counts.len(), false).unwrap().debug_info.is_some());
+        assert_eq!(with_debug_info(0, false).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(with_debug_info(instruction_counts.len(), true).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn bytes() {
+        execute("tests/bytes.luck", &[ "from_string", "from_array", "set_byte", "iterate", "copied" ]);
+
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/bytes.luck").unwrap();
+        let function_index = env.get_program().find_function("set_out_of_range").unwrap();
+        assert_eq!(env.execute_by_function_index(function_index, &[ Object::Integer(256) ]).unwrap_err().message, "a byte must be an integer from 0 to 255");
+        assert!(env.execute_by_function_index(function_index, &[ Object::Integer(255) ]).is_ok());
+
+        let mut program = clover.compile_file("tests/bytes.luck").unwrap();
+        let index = program.constants.len();
+        program.constants.push(Object::from_bytes(vec![ 0, 7, 255 ]));
+
+        let mut writer = Vec::new();
+        program.serialize(&mut writer, false).unwrap();
+        let loaded = crate::Program::deserialize(&mut writer.as_slice()).unwrap();
+        assert_eq!(loaded.constants[index], Object::from_bytes(vec![ 0, 7, 255 ]));
+
+        // a broken length is an error, not an allocation of that size
+        let position = writer.windows(7).position(|window| window == [ 3, 0, 0, 0, 0, 7, 255 ]).unwrap();
+        writer[position..position + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(crate::Program::deserialize(&mut writer.as_slice()).is_err());
+
+        let function_index = env.get_program().find_function("make").unwrap();
+        assert_eq!(env.execute_by_function_index(function_index, &[ Object::Integer(3) ]).unwrap(), Object::from_bytes(vec![ 0; 3 ]));
+        assert_eq!(env.execute_by_function_index(function_index, &[ Object::Integer(i64::MAX) ]).unwrap_err().message, "can not make more than 16777216 bytes");
+    }
+
+    #[test]
+    fn custom_entry() {
+        let clover = Clover::new();
+        let source = "function main()\n    1\nend\n\nfunction start()\n    2\nend\n";
+
+        let program = clover.compile_with_entry(source, "entry.luck", "start").unwrap();
+        assert!(matches!(clover.run(program).unwrap(), Object::Integer(2)));
+
+        let errors = clover.compile_with_entry(source, "entry.luck", "missing").unwrap_err();
+        assert_eq!(errors.errors[0].message, "can not find entry function [missing]");
+    }
+
+    #[test]
+    fn no_entry() {
+        let clover = Clover::new();
+        let program = clover.compile_file("tests/local.luck").unwrap();
+
+        assert!(program.entry_point.is_none());
+        assert_eq!(clover.run(program).unwrap_err().message, "this program has no entry point, define a main function or compile with an entry");
+    }
+
+    #[test]
+    fn shadow_native_warning() {
+        let mut clover = Clover::new();
+        clover.add_native_name("Math");
+        clover.add_native_name("Array");
+        clover.add_native_name("Random");
+
+        let (_, warnings) = clover.compile_file_with_warnings("tests/shadow.luck").unwrap();
+        let messages: Vec<(String, u16)> = warnings.iter().flat_map(error_messages).collect();
+
+        assert_eq!(messages, vec![
+            ("[Math] shadows the native model with the same name".to_string(), 1),
+            ("[Math] shadows the native model with the same name".to_string(), 7),
+            ("[Array] shadows the native model with the same name".to_string(), 11),
+            ("[Random] shadows the native model with the same name".to_string(), 15)
+        ]);
+
+        // nothing is known about natives by default
+        let (_, warnings) = Clover::new().compile_file_with_warnings("tests/shadow.luck").unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn assignment_in_condition() {
+        let (_, warnings) = Clover::new().compile_file_with_warnings("tests/condition.luck").unwrap();
+        let messages: Vec<(String, u16)> = warnings.iter().flat_map(error_messages).collect();
+
+        // not for x == 5 at line 8
+        assert_eq!(messages, vec![ ("assignment used as condition, did you mean [==]?".to_string(), 4) ]);
+    }
+
+    #[test]
+    fn unused_warnings() {
+        let clover = Clover::new();
+        let (_, warnings) = clover.compile_file_with_warnings("tests/warnings.luck").unwrap();
+
+        let messages: Vec<(String, crate::debug::Severity)> = warnings.iter()
+            .filter(|list| list.filename == "tests/warnings.luck")
+            .flat_map(|list| list.errors.iter())
+            .map(|warning| (warning.message.clone(), warning.severity))
+            .collect();
+
+        assert_eq!(messages, vec![
+            ("local variable [unused] is never used".to_string(), crate::debug::Severity::Warning),
+            ("included [my_factorial] is never used".to_string(), crate::debug::Severity::Warning)
+        ]);
+    }
+
     #[test]
     fn model() {
-        execute("tests/model.luck", &[ "regular", "with_apply" ]);
+        execute("tests/model.luck", &[ "regular", "with_apply", "apply_keeps_own_function", "implement_overrides_applied", "reflection", "keys_and_values", "dynamic_property", "implement_property" ]);
+
+        let clover = Clover::new();
+        let (_, warnings) = clover.compile_file_with_warnings("tests/model.luck").unwrap();
+        let messages: Vec<String> = warnings.iter().flat_map(|list| list.errors.iter()).map(|warning| warning.message.clone()).collect();
+
+        assert_eq!(messages, vec![ "function [area] is already implemented in [Square], the one from [Rectangle] is not applied".to_string() ]);
+    }
+
+    #[test]
+    fn method_not_found() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/model.luck").unwrap();
+
+        let function_index = env.get_program().find_function("call_missing_method").unwrap();
+        let error = env.execute_by_function_index(function_index, &[]).unwrap_err();
+
+        assert_eq!(error.message, "model [Rectangle] does not have method or property [volume]");
+
+        // calling it or reading it by index is an error, reading a missing property gives null
+        let function_index = env.get_program().find_function("read_missing_property").unwrap();
+        assert_eq!(env.execute_by_function_index(function_index, &[]).unwrap(), Object::Boolean(true));
+
+        let function_index = env.get_program().find_function("set_missing_property").unwrap();
+        let error = env.execute_by_function_index(function_index, &[]).unwrap_err();
+
+        assert_eq!(error.message, "model [Rectangle] does not have property [depth]");
+
+        let function_index = env.get_program().find_function("get_missing_property_by_index").unwrap();
+        let error = env.execute_by_function_index(function_index, &[]).unwrap_err();
+
+        assert_eq!(error.message, "model [Rectangle] does not have method or property [depth]");
+    }
+
+    #[test]
+    fn stack_trace_limit() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/error_handling.luck").unwrap();
+        let function_index = env.get_program().find_function("countdown").unwrap();
+
+        // 101 frames, from countdown(100) to countdown(0)
+        let error = env.execute_by_function_index(function_index, &[ Object::Integer(100) ]).unwrap_err();
+        assert_eq!(error.stack.len(), 40);
+        assert_eq!(error.elided_frames, 61);
+
+        env.set_stack_trace_limit(4, 2);
+        let error = env.execute_by_function_index(function_index, &[ Object::Integer(100) ]).unwrap_err();
+        assert_eq!(error.stack.len(), 6);
+        assert_eq!(error.elided_frames, 95);
+
+        // innermost first, the left out frames are between the innermost and the outermost ones
+        let lines: Vec<String> = env.stack_trace(&error).lines().map(|line| line.to_string()).collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[5], "    ... 95 frame(s) left out");
+
+        // the outermost frame is the first call, the innermost is the one raised
+        assert!(matches!(error.stack.front().unwrap().locals[0], Object::Integer(100)));
+        assert!(matches!(error.stack.back().unwrap().locals[0], Object::Integer(0)));
+    }
+
+    #[test]
+    fn error_location() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/location.luck").unwrap();
+
+        let function_index = env.get_program().find_function("divide_by_zero").unwrap();
+        let error = env.execute_by_function_index(function_index, &[]).unwrap_err();
+
+        assert_eq!(error.filename.as_deref(), Some("tests/location_include.luck"));
+        assert_eq!(error.location(), "tests/location_include.luck:2:7: divide by zero");
+
+        // innermost frame is the last one
+        let locations: Vec<String> = error.stack.iter().map(|frame| env.get_program().frame_location(frame)).collect();
+        assert_eq!(locations, vec![ "tests/location.luck:4:11 in divide_by_zero", "tests/location_include.luck:2:7 in divide" ]);
+        assert_eq!(env.stack_trace(&error), "tests/location_include.luck:2:7: divide by zero\n    at tests/location_include.luck:2:7 in divide\n    at tests/location.luck:4:11 in divide_by_zero");
+    }
+
+    #[test]
+    fn length() {
+        execute("tests/length.luck", &[ "string_length", "array_length", "scalar_length" ]);
+
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/length.luck").unwrap();
+        let function_index = env.get_program().find_function("length_of").unwrap();
+
+        let map = Object::NativeInstance(crate::helper::make_reference(TestMap(vec![ ("a".to_string(), Object::from(1)), ("b".to_string(), Object::from(2)) ])));
+        assert_eq!(env.execute_by_function_index(function_index, &[ map ]).unwrap(), Object::Integer(2));
+        assert!(env.execute_by_function_index(function_index, &[ Object::Float(1.5) ]).is_err());
+    }
+
+    #[test]
+    fn operator_error_messages() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/operator_errors.luck").unwrap();
+
+        for name in [ "add", "sub", "mul", "div", "mod", "gt", "lt", "gte", "lte" ] {
+            let function_index = env.get_program().find_function(name).unwrap();
+            let is_comparison = matches!(name, "gt" | "lt" | "gte" | "lte");
+
+            for (left, type_name) in [ (Object::Integer(1), "integer"), (Object::Float(1.0), "float") ] {
+                let array = Object::Array(crate::helper::make_reference(vec![]));
+                let error = env.execute_by_function_index(function_index, &[ left, array ]).unwrap_err();
+
+                if is_comparison {
+                    assert_eq!(error.message, format!("can not compare {} with array", type_name));
+                } else {
+                    assert_eq!(error.message, format!("can not {} {} with object", name, type_name));
+                }
+            }
+        }
+
+        for (name, message) in [ ("mod_by_zero", "divide by zero"), ("float_mod_by_zero", "divide by zero"), ("divide_min_by_minus_one", "integer overflow"), ("mod_min_by_minus_one", "integer overflow") ] {
+            let function_index = env.get_program().find_function(name).unwrap();
+            assert_eq!(env.execute_by_function_index(function_index, &[]).unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn compare_incomparable_types() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/operator_errors.luck").unwrap();
+
+        let function_index = env.get_program().find_function("string_less_than_integer").unwrap();
+        assert_eq!(env.execute_by_function_index(function_index, &[]).unwrap_err().message, "can not compare string with integer");
+
+        for name in [ "gt", "lt", "gte", "lte" ] {
+            let function_index = env.get_program().find_function(name).unwrap();
+
+            let error = env.execute_by_function_index(function_index, &[ Object::Integer(1), Object::String(crate::helper::make_reference("a".to_string())) ]).unwrap_err();
+            assert_eq!(error.message, "can not compare integer with string");
+
+            let error = env.execute_by_function_index(function_index, &[ Object::String(crate::helper::make_reference("a".to_string())), Object::Boolean(true) ]).unwrap_err();
+            assert_eq!(error.message, "can not compare string with boolean");
+        }
+    }
+
+    #[test]
+    fn program_builder() {
+        use crate::ProgramBuilder;
+        use crate::bytecode::OpCode;
+
+        let mut builder = ProgramBuilder::new();
+        let answer = builder.add_constant(Object::Integer(42));
+        let main = builder.add_function("main", 0, 0, vec![ OpCode::PushConstant.to_instruction(answer as u64), OpCode::Return.to_instruction(0) ]);
+        builder.set_entry_point(main);
+
+        let mut env: Env = builder.build().unwrap().into();
+        assert_eq!(env.execute().unwrap(), Object::Integer(42));
+        assert_eq!(env.get_program().find_function("main"), Some(main));
+
+        let mut builder = ProgramBuilder::new();
+        builder.add_function("main", 0, 0, vec![ OpCode::PushConstant.to_instruction(100), OpCode::Return.to_instruction(0) ]);
+        assert_eq!(builder.build().unwrap_err().message, "operand of instruction 0 ((PushConstant, 100)) is out of range in function [main]");
+
+        let mut builder = ProgramBuilder::new();
+        builder.add_function("main", 0, 0, vec![ OpCode::PushConstant.to_instruction(0) ]);
+        assert_eq!(builder.build().unwrap_err().message, "last instruction is not return in function [main]");
+
+        let mut builder = ProgramBuilder::new();
+        builder.add_constant(Object::Model(3));
+        assert_eq!(builder.build().unwrap_err().message, "constant 3 refers to a model which does not exist");
+
+        let mut builder = ProgramBuilder::new();
+        builder.set_entry_point(0);
+        assert!(builder.build().is_err());
+
+        // for loops read the iterator and the length in the two locals after the enumerable
+        let mut builder = ProgramBuilder::new();
+        builder.add_function("main", 0, 2, vec![ OpCode::ForNext.to_instruction(0), OpCode::Return.to_instruction(0) ]);
+        assert_eq!(builder.build().unwrap_err().message, "operand of instruction 0 ((ForNext, 0)) is out of range in function [main]");
+
+        let mut builder = ProgramBuilder::new();
+        builder.add_function("main", 0, 3, vec![ OpCode::ForNextPair.to_instruction(0), OpCode::Return.to_instruction(0) ]);
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn copy() {
+        execute("tests/copy.luck", &[ "shallow_copy", "shallow_copy_shares_items", "deep_copy_items", "deep_copy_cycle", "copy_scalar" ]);
+
+        let mut env = Clover::new().create_state_by_filename("tests/copy.luck").unwrap();
+        let copy_of = env.get_program().find_function("copy_of").unwrap();
+        let deep_copy_of = env.get_program().find_function("deep_copy_of").unwrap();
+
+        let map = Object::NativeInstance(crate::helper::make_reference(TestMap(vec![ ("items".to_string(), Object::from(vec![ Object::from(1) ])) ])));
+
+        // a new map, the items are shared by the shallow copy only
+        for (function_index, shared) in [ (copy_of, true), (deep_copy_of, false) ] {
+            let copied = env.execute_by_function_index(function_index, std::slice::from_ref(&map)).unwrap();
+            assert_eq!(copied, map);
+
+            let (Object::NativeInstance(original), Object::NativeInstance(copied)) = (&map, &copied) else { panic!("not a map") };
+            assert!(!std::rc::Rc::ptr_eq(original, copied));
+
+            let items = |instance: &crate::Reference<dyn crate::NativeModelInstance>| instance.borrow().raw_entries().unwrap()[0].1.clone();
+            let (Object::Array(original_items), Object::Array(copied_items)) = (items(original), items(copied)) else { panic!("not an array") };
+            assert_eq!(std::rc::Rc::ptr_eq(&original_items, &copied_items), shared);
+        };
+    }
+
+    #[test]
+    fn map_keys_and_values() {
+        let mut env = Clover::new().create_state_by_filename("tests/entries.luck").unwrap();
+        let keys_of = env.get_program().find_function("keys_of").unwrap();
+        let values_of = env.get_program().find_function("values_of").unwrap();
+
+        let map = Object::NativeInstance(crate::helper::make_reference(TestMap(vec![ ("b".to_string(), Object::from(2)), ("a".to_string(), Object::from(1)) ])));
+
+        // sorted by key, a map has no order
+        assert_eq!(env.execute_by_function_index(keys_of, std::slice::from_ref(&map)).unwrap(), Object::from(vec![ Object::from("a"), Object::from("b") ]));
+        assert_eq!(env.execute_by_function_index(values_of, &[ map ]).unwrap(), Object::from(vec![ Object::from(1), Object::from(2) ]));
+        assert!(env.execute_by_function_index(keys_of, &[ Object::from(1) ]).is_err());
+    }
+
+    #[test]
+    fn coverage() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/coverage.luck").unwrap();
+
+        env.enable_coverage();
+        env.execute().unwrap();
+
+        let report = env.coverage_report();
+        let main = report.iter().find(|coverage| coverage.name == "main").unwrap();
+        assert_eq!(main.covered, main.total);
+
+        // only the else branch ran
+        let sign = report.iter().find(|coverage| coverage.name == "sign").unwrap();
+        assert!(sign.covered < sign.total);
+
+        let uncovered_lines: Vec<u16> = sign.uncovered_positions.iter().map(|position| position.line).collect();
+        assert!(uncovered_lines.contains(&3));
+        assert!(!uncovered_lines.contains(&5));
+    }
+
+    #[test]
+    fn globals_after_execute() {
+        let mut env = Clover::new().create_state_by_filename("tests/host_global.luck").unwrap();
+        env.set_global("counter", Object::Integer(0));
+
+        assert_eq!(env.execute().unwrap(), Object::Integer(10));
+        assert_eq!(env.get_global("counter"), Some(Object::Integer(1)));
+
+        // the same env runs again from what the last run left
+        assert_eq!(env.execute().unwrap(), Object::Integer(20));
+        assert_eq!(env.get_global("counter"), Some(Object::Integer(2)));
+        assert_eq!(env.get_global("missing"), None);
+    }
+
+    #[test]
+    fn conversion_meta_methods() {
+        execute("tests/conversion.luck", &[ "to_float", "to_integer" ]);
+
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/conversion.luck").unwrap();
+
+        // the host gets the converted value too
+        let function_index = env.get_program().find_function("to_float").unwrap();
+        let half = env.execute_by_object(Object::Model(0), &[ Object::Integer(1), Object::Integer(4) ]).unwrap();
+        assert_eq!(env.get_object_property_by_name(half, "float").unwrap(), Object::Float(0.25));
+        assert_eq!(env.execute_by_function_index(function_index, &[]).unwrap(), Object::Boolean(true));
+
+        let function_index = env.get_program().find_function("without_meta_method").unwrap();
+        let plain = env.execute_by_object(Object::Model(1), &[ Object::Integer(1) ]).unwrap();
+        assert_eq!(env.execute_by_function_index(function_index, &[ plain ]).unwrap(), Object::Null);
+    }
+
+    #[test]
+    fn call_not_callable() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/not_callable.luck").unwrap();
+
+        let function_index = env.get_program().find_function("call_integer").unwrap();
+        let error = env.execute_by_function_index(function_index, &[]).unwrap_err();
+        assert_eq!(error.message, "value of type integer is not callable");
+        assert_eq!(error.position.line, 3);
+
+        env.reset();
+        let function_index = env.get_program().find_function("call_parameter").unwrap();
+        for (value, type_name) in [ (Object::Null, "null"), (Object::from("text"), "string"), (Object::from(vec![]), "array") ] {
+            let error = env.execute_by_function_index(function_index, &[ value ]).unwrap_err();
+            assert_eq!(error.message, format!("value of type {} is not callable", type_name));
+            env.reset();
+        };
+    }
+
+    #[test]
+    fn array_index_set() {
+        execute("tests/array_set.luck", &[ "in_range" ]);
+
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/array_set.luck").unwrap();
+        let function_index = env.get_program().find_function("out_of_range").unwrap();
+
+        for index in [ 3, 5, -1 ] {
+            let error = env.execute_by_function_index(function_index, &[ Object::Integer(index) ]).unwrap_err();
+            assert_eq!(error.message, format!("index {} is out of range for an array of 3 items", index));
+            env.reset();
+        };
+    }
+
+    #[test]
+    fn loop_iterations() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/for_loop.luck").unwrap();
+        assert_eq!(env.loop_iterations(), 0);
+
+        execute_function(&mut env, "simple");
+        assert_eq!(env.loop_iterations(), 10);
+
+        // 5 outer and 5 * 10 inner
+        execute_function(&mut env, "nests");
+        assert_eq!(env.loop_iterations(), 65);
+
+        env.clear_loop_iterations();
+        execute_function(&mut env, "simple");
+        assert_eq!(env.loop_iterations(), 10);
+    }
+
+    #[test]
+    fn freeze_array() {
+        execute("tests/freeze.luck", &[ "frozen_rejects_set", "frozen" ]);
+    }
+
+    #[test]
+    fn weak_reference() {
+        execute("tests/weak.luck", &[ "upgrade_parent" ]);
+
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/weak.luck").unwrap();
+
+        let function_index = env.get_program().find_function("build_tree").unwrap();
+        let parent = match env.execute_by_function_index(function_index, &[]).unwrap() {
+            Object::Instance(parent) => parent,
+            _ => panic!("build_tree should return an instance")
+        };
+
+        // the child only holds a weak reference back, so the returned one is the only strong reference
+        assert_eq!(std::rc::Rc::strong_count(&parent), 1);
+        assert_eq!(std::rc::Rc::weak_count(&parent), 1);
+
+        let children = parent.borrow().properties[1].clone();
+        let back_reference = match &children {
+            Object::Array(children) => match &children.borrow()[0] {
+                Object::Instance(child) => child.borrow().properties[0].clone(),
+                _ => panic!("child should be an instance")
+            },
+            _ => panic!("children should be an array")
+        };
+
+        drop(parent);
+
+        assert!(matches!(back_reference, Object::WeakRef(ref weak_object) if weak_object.upgrade().is_none()));
+    }
+
+    #[test]
+    fn null_safety() {
+        execute("tests/null_safety.luck", &[ "optional_chaining", "chain_after_null", "null_coalescing" ]);
+    }
+
+    #[test]
+    fn missing_end() {
+        let source = "function first()\n    local a = 1\n\nfunction second()\n    if true\n        1\nend\n";
+        assert_eq!(compile_messages(source), vec![
+            ("expected `end` to close `function` opened at line 1".to_string(), 1),
+            ("expected `end` to close `function` opened at line 4".to_string(), 4)
+        ]);
+    }
+
+    #[test]
+    fn break_outside_loop() {
+        let source = "function main()\n    for i in [ 1, 2 ]\n        break\n    end\n\n    break\nend\n";
+        assert_eq!(compile_messages(source), vec![ ("break can only be used inside a loop".to_string(), 6) ]);
+    }
+
+    #[test]
+    fn for_pair_duplicate_name() {
+        let source = "function main()\n    for key, key in [ 1, 2 ]\n    end\nend\n";
+        assert_eq!(compile_messages(source), vec![ ("variable already exists".to_string(), 2) ]);
+    }
+
+    #[test]
+    fn implement_duplicate_property() {
+        let source = "model Point\n    x\nend\n\nimplement Point\n    y\n    x\nend\n";
+        assert_eq!(compile_messages(source), vec![ ("property already exists".to_string(), 7) ]);
+    }
+
+    #[test]
+    fn this_outside_instance_function() {
+        let source = "model Point\n    x\nend\n\nimplement Point\n    function origin()\n        this\n    end\nend\n\nfunction main()\n    this.x\nend\n";
+        assert_eq!(compile_messages(source), vec![
+            ("this can only be used in an instance function".to_string(), 7),
+            ("this can only be used in an instance function".to_string(), 12)
+        ]);
     }
 
     #[test]
     fn local() {
-        execute("tests/local.luck", &[ "in_file", "in_file_again", "in_function", "in_scope" ]);
+        execute("tests/local.luck", &[ "in_file", "in_file_again", "in_function", "in_scope", "semicolon" ]);
+    }
+
+    #[test]
+    fn instruction_budget() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/budget.luck").unwrap();
+
+        for name in [ "forever", "forever_with_rescue" ] {
+            env.set_instruction_budget(10000);
+
+            let function_index = env.get_program().find_function(name).unwrap();
+            let error = env.execute_by_function_index(function_index, &[]).unwrap_err();
+            assert_eq!(error.message, "instruction budget exhausted");
+            assert_eq!(env.instruction_budget(), Some(0));
+        };
+
+        env.set_instruction_budget(100);
+        let short = env.get_program().find_function("short").unwrap();
+        assert!(matches!(env.execute_by_function_index(short, &[]).unwrap(), Object::Integer(3)));
+        assert!(env.instruction_budget().unwrap() > 0);
+
+        env.clear_instruction_budget();
+        let short = env.get_program().find_function("short").unwrap();
+        assert!(matches!(env.execute_by_function_index(short, &[]).unwrap(), Object::Integer(3)));
+    }
+
+    #[test]
+    fn reset() {
+        let clover = Clover::new();
+        let mut env = clover.create_state_by_filename("tests/local.luck").unwrap();
+
+        // file locals are back to their initial values, so the same functions pass again
+        for _ in 0..2 {
+            execute_function(&mut env, "in_file");
+            execute_function(&mut env, "in_file_again");
+            env.reset();
+        };
+
+        let mut env = clover.create_state_by_filename("tests/for_loop.luck").unwrap();
+
+        for _ in 0..2 {
+            env.reset();
+
+            for function_name in [ "simple", "nests", "break_loop", "array", "for_model" ] {
+                execute_function(&mut env, function_name);
+            };
+        };
     }
 
     #[test]
     fn convert() {
-        execute("tests/convert.luck", &[ "string_to_integer", "string_to_float", "integer_to_string", "integer_to_float", "float_to_string", "float_to_integer" ]);
+        execute("tests/convert.luck", &[ "string_to_integer", "string_to_float", "integer_to_string", "integer_to_float", "float_to_string", "float_to_integer", "concatenate" ]);
     }
 
     #[test]
     fn logic() {        
-        execute("tests/logic.luck", &[ "equal", "not_equal" ]);
+        execute("tests/logic.luck", &[ "equal", "not_equal", "else_if", "else_then_nested_if" ]);
+    }
+
+    #[test]
+    fn negative_array_literal() {
+        execute("tests/array_literal.luck", &[ "negative_integers", "negative_floats", "mixed" ]);
+
+        let source = "function main()\n    [-1, -2.5, -2.5, -0.0, 0.0]\nend\n";
+        let program = crate::backend::compiler::compile(source, "array_literal.luck", &crate::backend::compiler::DefaultStorage::new()).unwrap();
+        let opcodes: Vec<crate::runtime::opcode::OpCode> = program.functions[0].instructions.iter().map(|instruction| instruction.opcode()).collect();
+
+        // the minus is folded into the constants, and the same float is stored once
+        assert!(!opcodes.contains(&crate::runtime::opcode::OpCode::Negative));
+        let floats: Vec<&Object> = program.constants.iter().filter(|constant| matches!(constant, Object::Float(_))).collect();
+        assert_eq!(floats.len(), 3);
     }
 
     #[test]
@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+
+use crate::backend::compiler::{CompilerContext, CompilerEnv, DefaultStorage, Storage, compile_repl_input, create_repl_compiler_env};
+use crate::intermediate::CompileErrorList;
+use crate::runtime::env::Env;
+use crate::runtime::object::Object;
+use crate::runtime::program::RuntimeError;
+
+// filename of the inputs in error messages
+const REPL_FILENAME: &str = "<repl>";
+
+#[derive(Debug)]
+pub enum ReplError {
+    Compile(CompileErrorList),
+    Runtime(RuntimeError)
+}
+
+impl Display for ReplError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplError::Compile(errors) => errors.fmt(f),
+            ReplError::Runtime(error) => error.fmt(f)
+        }
+    }
+}
+
+impl Error for ReplError {}
+
+/// An interactive session, inputs are evaluated one by one and the state is kept between them.
+///
+/// An input is either definitions (function, model, implement, apply, include) or statements. Locals
+/// declared by `local` statements stay visible to the later inputs.
+pub struct Repl {
+    storage: Box<dyn Storage>,
+    context: CompilerContext,
+    compiler_env: CompilerEnv,
+    env: Env
+}
+
+impl Repl {
+    pub fn new_with_file_loader(storage: Box<dyn Storage>) -> Repl {
+        let mut context = CompilerContext::new();
+        let compiler_env = create_repl_compiler_env(&mut context, REPL_FILENAME);
+        let env = context.to_program().into();
+
+        Repl {
+            storage,
+            context,
+            compiler_env,
+            env
+        }
+    }
+
+    pub fn new() -> Repl {
+        Repl::new_with_file_loader(Box::new(DefaultStorage::new()))
+    }
+
+    /// The env which runs the inputs, native functions and models can be added to it.
+    pub fn env(&mut self) -> &mut Env {
+        &mut self.env
+    }
+
+    /// Compiles the input against everything evaluated before and runs it, returns the value of the
+    /// last expression. Definitions evaluate to null.
+    pub fn eval(&mut self, source: &str) -> Result<Object, ReplError> {
+        let function_index = compile_repl_input(&mut self.context, &mut self.compiler_env, source, self.storage.deref()).map_err(ReplError::Compile)?;
+
+        self.env.update_program(self.context.to_program());
+
+        match function_index {
+            Some(index) => self.env.execute_by_function_index(index, &[]).map_err(ReplError::Runtime),
+            None => Ok(Object::Null)
+        }
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Self { Repl::new() }
+}
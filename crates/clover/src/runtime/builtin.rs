@@ -0,0 +1,276 @@
+use crate::runtime::env::Env;
+use std::ops::Deref;
+use std::collections::HashMap;
+use crate::runtime::object::{Object, Integer, NativeFunction, WeakObject, ModelInstance, make_reference, ensure_parameters_length, MAX_ALLOCATION_LENGTH};
+use crate::runtime::program::RuntimeError;
+use crate::runtime::object_display::reference_address;
+
+// functions available in every env, no need to inject
+pub const BUILTIN_FUNCTIONS: &[ (&str, NativeFunction) ] = &[
+    ("raise", raise),
+    ("throw", raise),
+    ("has_method", has_method),
+    ("has_property", has_property),
+    ("weak", weak),
+    ("upgrade", upgrade),
+    ("freeze", freeze),
+    ("is_frozen", is_frozen),
+    ("len", len),
+    ("keys", keys),
+    ("values", values),
+    ("copy", copy),
+    ("deep_copy", deep_copy),
+    ("bytes", bytes)
+];
+
+// abort execution with the given object, the object can be received by rescue
+pub fn raise(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let value = parameters.first().cloned().unwrap_or(Object::Null);
+
+    Err(RuntimeError::new_with_value(value, env.last_position()))
+}
+
+// model index of script models and their instances, and the name to look up
+fn reflection_parameters(env: &Env, parameters: &[ Object ]) -> Result<(Option<usize>, String), RuntimeError> {
+    ensure_parameters_length(parameters, 2).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    let name = if let Object::String(name) = &parameters[1] {
+        name.borrow().deref().clone()
+    } else {
+        return Err(RuntimeError::new("name must be a string", env.last_position()));
+    };
+
+    let model_index = match &parameters[0] {
+        Object::Model(model_index) => Some(*model_index),
+        Object::Instance(instance) => Some(instance.borrow().model_index),
+        _ => None
+    };
+
+    Ok((model_index, name))
+}
+
+pub fn has_method(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let (model_index, name) = reflection_parameters(env, parameters)?;
+
+    let found = model_index
+        .and_then(|model_index| env.get_program().models.get(model_index))
+        .is_some_and(|model| model.functions.contains_key(&name));
+
+    Ok(Object::Boolean(found))
+}
+
+pub fn has_property(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let (model_index, name) = reflection_parameters(env, parameters)?;
+
+    let found = model_index
+        .and_then(|model_index| env.get_program().models.get(model_index))
+        .is_some_and(|model| model.property_indices.contains_key(&name));
+
+    Ok(Object::Boolean(found))
+}
+
+// weak reference to a string, instance or array, it does not keep the object alive
+pub fn weak(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    ensure_parameters_length(parameters, 1).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    match WeakObject::new(&parameters[0]) {
+        Some(weak_object) => Ok(Object::WeakRef(weak_object)),
+        None => Err(RuntimeError::new("can only make weak reference of reference types", env.last_position()))
+    }
+}
+
+// the object of a weak reference, null if it was freed
+pub fn upgrade(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    ensure_parameters_length(parameters, 1).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    match &parameters[0] {
+        Object::WeakRef(weak_object) => Ok(weak_object.upgrade().unwrap_or(Object::Null)),
+        _ => Err(RuntimeError::new("value is not a weak reference", env.last_position()))
+    }
+}
+
+// makes the array read only and returns it, changing it afterwards is an error
+pub fn freeze(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    ensure_parameters_length(parameters, 1).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    match &parameters[0] {
+        Object::Array(array) => {
+            env.freeze_array(array);
+            Ok(parameters[0].clone())
+        },
+        _ => Err(RuntimeError::new("can only freeze an array", env.last_position()))
+    }
+}
+
+pub fn is_frozen(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    ensure_parameters_length(parameters, 1).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    match &parameters[0] {
+        Object::Array(array) => Ok(Object::Boolean(env.is_array_frozen(array))),
+        _ => Ok(Object::Boolean(false))
+    }
+}
+
+// characters of a string, items of an array, entries of a map or length of bytes
+pub fn len(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    ensure_parameters_length(parameters, 1).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    let length = match &parameters[0] {
+        Object::String(string) => string.borrow().chars().count(),
+        Object::Array(array) => array.borrow().len(),
+        Object::Bytes(bytes) => bytes.borrow().len(),
+        Object::NativeInstance(instance) => match instance.borrow().raw_entries() {
+            Some(entries) => entries.len(),
+            None => return Err(RuntimeError::new("this native instance has no length", env.last_position()))
+        },
+        _ => return Err(RuntimeError::new("can only get length of a string, an array, a map or bytes", env.last_position()))
+    };
+
+    Ok(Object::Integer(length as Integer))
+}
+
+// property names and values of an instance, or keys and values of a map sorted by key
+fn entries(env: &Env, parameters: &[ Object ]) -> Result<Vec<(Object, Object)>, RuntimeError> {
+    ensure_parameters_length(parameters, 1).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    match &parameters[0] {
+        Object::Instance(instance) => {
+            let instance = instance.borrow();
+            let model = &env.get_program().models[instance.model_index];
+
+            Ok(model.property_names.iter().zip(instance.properties.iter()).map(|(name, value)| (Object::String(name.clone()), value.clone())).collect())
+        },
+        Object::NativeInstance(instance) => match instance.borrow().raw_entries() {
+            Some(mut entries) => {
+                entries.sort_by(|left, right| left.0.cmp(&right.0));
+                Ok(entries.into_iter().map(|(key, value)| (Object::from(key), value)).collect())
+            },
+            None => Err(RuntimeError::new("this native instance has no entries", env.last_position()))
+        },
+        _ => Err(RuntimeError::new("can only get entries of an instance or a map", env.last_position()))
+    }
+}
+
+// property names of an instance in the order of the model, or keys of a map in sorted order
+pub fn keys(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let keys: Vec<Object> = entries(env, parameters)?.into_iter().map(|(key, _)| key).collect();
+    Ok(Object::from(keys))
+}
+
+// values in the same order as keys gives
+pub fn values(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    let values: Vec<Object> = entries(env, parameters)?.into_iter().map(|(_, value)| value).collect();
+    Ok(Object::from(values))
+}
+
+// a new instance, array, map or bytes with the same items, items are not copied
+pub fn copy(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    ensure_parameters_length(parameters, 1).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    match &parameters[0] {
+        Object::Instance(instance) => {
+            let instance = instance.borrow();
+            Ok(Object::Instance(make_reference(ModelInstance { model_index: instance.model_index, properties: instance.properties.clone() })))
+        },
+        Object::Array(array) => Ok(Object::from(array.borrow().clone())),
+        Object::Bytes(bytes) => Ok(Object::from_bytes(bytes.borrow().clone())),
+        Object::NativeInstance(instance) => {
+            let copied = instance.borrow().raw_entries().and_then(|entries| instance.borrow().raw_copy(entries));
+            copied.map(Object::NativeInstance).ok_or_else(|| RuntimeError::new("this native instance can not be copied", env.last_position()))
+        },
+        _ => Err(RuntimeError::new("can only copy an instance, an array, a map or bytes", env.last_position()))
+    }
+}
+
+// same as copy, and items which are instances, arrays or maps are deep copied too. An object reached twice is
+// copied once, so shared items stay shared and cycles are kept. Other values are not copied.
+pub fn deep_copy(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    ensure_parameters_length(parameters, 1).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    deep_copy_object(&parameters[0], &mut HashMap::new()).map_err(|error| RuntimeError::new(&error.message, env.last_position()))
+}
+
+// copies are keyed by the address of the original, the copy is added before its items so cycles end there
+fn deep_copy_object(object: &Object, copies: &mut HashMap<usize, Object>) -> Result<Object, RuntimeError> {
+    let address = match object {
+        Object::Instance(instance) => reference_address(instance),
+        Object::Array(array) => reference_address(array),
+        Object::NativeInstance(instance) => reference_address(instance),
+        Object::Bytes(bytes) => reference_address(bytes),
+        _ => return Ok(object.clone())
+    };
+
+    if let Some(copied) = copies.get(&address) {
+        return Ok(copied.clone());
+    };
+
+    match object {
+        Object::Bytes(bytes) => {
+            let copied = Object::from_bytes(bytes.borrow().clone());
+            copies.insert(address, copied.clone());
+
+            Ok(copied)
+        },
+        Object::Instance(instance) => {
+            let copied = make_reference(ModelInstance { model_index: instance.borrow().model_index, properties: Vec::new() });
+            copies.insert(address, Object::Instance(copied.clone()));
+
+            let properties = instance.borrow().properties.clone();
+            for property in &properties {
+                let property = deep_copy_object(property, copies)?;
+                copied.borrow_mut().properties.push(property);
+            };
+
+            Ok(Object::Instance(copied))
+        },
+        Object::Array(array) => {
+            let copied = make_reference(Vec::new());
+            copies.insert(address, Object::Array(copied.clone()));
+
+            let items = array.borrow().clone();
+            for item in &items {
+                let item = deep_copy_object(item, copies)?;
+                copied.borrow_mut().push(item);
+            };
+
+            Ok(Object::Array(copied))
+        },
+        Object::NativeInstance(instance) => {
+            let entries = instance.borrow().raw_entries();
+
+            // instances which are not map like are shared
+            let (entries, copied) = match entries.and_then(|entries| instance.borrow().raw_copy(Vec::new()).map(|copied| (entries, copied))) {
+                Some(copy) => copy,
+                None => return Ok(object.clone())
+            };
+            copies.insert(address, Object::NativeInstance(copied.clone()));
+
+            for (key, value) in entries {
+                let value = deep_copy_object(&value, copies)?;
+                copied.borrow_mut().index_set(copied.clone(), &Object::from(key), value)?;
+            };
+
+            Ok(Object::NativeInstance(copied))
+        },
+        _ => Ok(object.clone())
+    }
+}
+
+// bytes from an array of integers (0 to 255), the UTF-8 of a string, or a count of zero bytes
+pub fn bytes(env: &mut Env, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+    ensure_parameters_length(parameters, 1).map_err(|error| RuntimeError::new(&error.message, env.last_position()))?;
+
+    let bytes = match &parameters[0] {
+        Object::Array(array) => array.borrow().iter().map(|item| match item {
+            Object::Integer(value) if (0..=255).contains(value) => Ok(*value as u8),
+            _ => Err(RuntimeError::new("a byte must be an integer from 0 to 255", env.last_position()))
+        }).collect::<Result<Vec<u8>, RuntimeError>>()?,
+        Object::String(string) => string.borrow().as_bytes().to_vec(),
+        Object::Integer(count) if *count >= 0 && *count as u64 <= MAX_ALLOCATION_LENGTH as u64 => vec![0; *count as usize],
+        Object::Integer(count) if *count >= 0 => return Err(RuntimeError::new(&format!("can not make more than {} bytes", MAX_ALLOCATION_LENGTH), env.last_position())),
+        Object::Bytes(bytes) => bytes.borrow().clone(),
+        _ => return Err(RuntimeError::new("can only make bytes from an array, a string or a count", env.last_position()))
+    };
+
+    Ok(Object::from_bytes(bytes))
+}
@@ -1,10 +1,16 @@
 use crate::runtime::program::{Program, RuntimeError};
-use std::collections::{HashMap, LinkedList};
-use crate::runtime::object::{Object, ModelInstance, Reference, make_reference, NativeModel, NativeFunction, NativeModelInstance};
+use std::collections::{HashMap, HashSet, LinkedList};
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use crate::runtime::object::{Object, Integer, ModelInstance, Reference, make_reference, NativeModel, NativeFunction, NativeModelInstance};
 use crate::intermediate::Position;
-use crate::runtime::opcode::{Instruction, OpCode};
+use crate::runtime::opcode::{Instruction, OpCode, INSTANCE_GET_METHOD};
 use std::ops::Deref;
-use crate::runtime::object_property::{instance_get_array, instance_get_integer, instance_get_float, instance_get_string};
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use crate::runtime::builtin::BUILTIN_FUNCTIONS;
+use crate::runtime::runtime_info::FunctionCoverage;
+use crate::runtime::object_property::{instance_get_array, instance_get_bytes, instance_get_integer, instance_get_float, instance_get_string};
 
 #[derive(Debug, Clone)]
 pub struct Frame {
@@ -27,44 +33,263 @@ impl Frame {
     }
 }
 
+// buffered reader shared by every read from script, so data buffered by one read is not lost
+struct Input(Box<dyn BufRead>);
+
+impl fmt::Debug for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Input")
+    }
+}
+
+// writer used by native functions which print, stdout by default and stderr for the error output
+struct Output(Box<dyn Write>);
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Output")
+    }
+}
+
 #[derive(Debug)]
 pub struct Env {
-    globals: HashMap<String, Object>,
+    input: Input,
+    output: Output,
+    error_output: Output,
+    // values of globals are kept in a list, so a global can be found by index once its name is resolved
+    globals: HashMap<String, usize>,
+    global_values: Vec<Object>,
+    // index in global values of each global dependency of the program, resolved at first access
+    global_slots: Vec<Option<usize>>,
     locals: Vec<Object>,
     native_models: Vec<Reference<dyn NativeModel>>,
-    stack: LinkedList<Object>,
-    frames: LinkedList<Frame>,
+    stack: Vec<Object>,
+    frames: Vec<Frame>,
+    // locals of returned frames, reused by later calls so calling a function does not allocate
+    spare_locals: Vec<Vec<Object>>,
+    // instructions left to run, no limit when None
+    instruction_budget: Option<u64>,
+    // frames kept in the stack of an error, innermost and outermost
+    stack_trace_limit: (usize, usize),
+    // arrays made read only by freeze, keyed by address, freed arrays are forgotten at the next freeze
+    frozen_arrays: HashMap<usize, Weak<RefCell<Vec<Object>>>>,
+    // indices of the instructions run in each function, not recorded when None
+    coverage: Option<Vec<HashSet<usize>>>,
+    // bodies of for loops entered, see loop_iterations
+    pub(crate) loop_iterations: u64,
     program: Program
 }
 
 impl From<Program> for Env {
     fn from(program: Program) -> Self {
         let mut locals = Vec::new();
+        load_local_values(&program, &mut locals);
 
-        for i in 0..program.local_variable_count {
-            locals.push(if let Some(constant_index) = program.local_values.get(&i) {
-                program.constants.get(*constant_index).unwrap().clone()
-            } else {
-                Object::Null
-            });
-        };
-
-        Env {
+        let mut env = Env {
+            input: Input(Box::new(BufReader::new(std::io::stdin()))),
+            output: Output(Box::new(std::io::stdout())),
+            error_output: Output(Box::new(std::io::stderr())),
             globals: HashMap::new(),
+            global_values: Vec::new(),
+            global_slots: vec![None; program.global_dependencies.len()],
             locals,
             native_models: Vec::new(),
-            stack: LinkedList::new(),
-            frames: LinkedList::new(),
+            stack: Vec::with_capacity(Env::INITIAL_STACK_CAPACITY),
+            frames: Vec::new(),
+            spare_locals: Vec::new(),
+            instruction_budget: None,
+            stack_trace_limit: Env::DEFAULT_STACK_TRACE_LIMIT,
+            frozen_arrays: HashMap::new(),
+            coverage: None,
+            loop_iterations: 0,
             program
-        }
+        };
+
+        for &(name, function) in BUILTIN_FUNCTIONS {
+            env.add_native_function(name, function);
+        };
+
+        env
     }
 }
 
 impl Env {
+    const INITIAL_STACK_CAPACITY: usize = 256;
+    const DEFAULT_STACK_TRACE_LIMIT: (usize, usize) = (32, 8);
+
+    /// The program the env runs, for hosts and tools which look into it (such as `file_info` for function names).
+    /// The env owns the program, it is not shared, so the reference can be read only between calls into the env.
     pub fn get_program(&self) -> &Program {
         &self.program
     }
 
+    // program grows when the repl compiles more inputs, locals and globals set so far are kept
+    pub(crate) fn update_program(&mut self, program: Program) {
+        load_local_values(&program, &mut self.locals);
+
+        // slots may point to other names in the new program
+        self.global_slots.clear();
+        self.global_slots.resize(program.global_dependencies.len(), None);
+
+        self.program = program;
+    }
+
+    /// Limits how many instructions can run from now on, every instruction takes one from the budget. When
+    /// nothing is left the running script stops with an `instruction budget exhausted` error, which can not
+    /// be rescued by the script. The budget is not refilled by itself, set it again before the next run.
+    pub fn set_instruction_budget(&mut self, budget: u64) {
+        self.instruction_budget = Some(budget);
+    }
+
+    /// Removes the limit set by `set_instruction_budget`.
+    pub fn clear_instruction_budget(&mut self) {
+        self.instruction_budget = None;
+    }
+
+    /// Instructions left to run, None when there is no limit.
+    pub fn instruction_budget(&self) -> Option<u64> {
+        self.instruction_budget
+    }
+
+    /// Limits the frames kept in the stack of a runtime error to the `innermost` frames where it failed and the
+    /// `outermost` frames it was called from, the count of frames between them is in `elided_frames`. A deep
+    /// recursion would make a huge stack otherwise. The default is 32 innermost and 8 outermost frames.
+    pub fn set_stack_trace_limit(&mut self, innermost: usize, outermost: usize) {
+        self.stack_trace_limit = (innermost, outermost);
+    }
+
+    /// The location of a runtime error followed by the frames of its stack, innermost first, for printing
+    /// the error. The frames left out are counted where they were, by the limit set now.
+    pub fn stack_trace(&self, error: &RuntimeError) -> String {
+        let mut output = error.location();
+        let (_, outermost_limit) = self.stack_trace_limit;
+
+        for (index, frame) in error.stack.iter().enumerate().rev() {
+            output += &format!("\n    at {}", self.program.frame_location(frame));
+
+            // the first innermost frame, the outermost ones come after the left out frames
+            if error.elided_frames > 0 && index == outermost_limit {
+                output += &format!("\n    ... {} frame(s) left out", error.elided_frames);
+            };
+        };
+
+        output
+    }
+
+    /// Starts recording which instructions run, for measuring what a test suite exercises. Coverage recorded
+    /// before is dropped, it is kept by `reset` so several runs add up.
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(vec![HashSet::new(); self.program.functions.len()]);
+    }
+
+    pub fn disable_coverage(&mut self) {
+        self.coverage = None;
+    }
+
+    /// Covered and total instruction count of every function in the order of function indices, the instructions
+    /// never run are mapped to source positions by the debug info. Empty when coverage is not enabled.
+    pub fn coverage_report(&self) -> Vec<FunctionCoverage> {
+        let coverage = match &self.coverage {
+            Some(coverage) => coverage,
+            None => return Vec::new()
+        };
+
+        self.program.functions.iter().enumerate().map(|(function_index, function)| {
+            let covered = coverage.get(function_index);
+            let is_covered = |index: &usize| covered.is_some_and(|covered| covered.contains(index));
+
+            let uncovered_positions = match &self.program.debug_info {
+                Some(debug_info) => (0..function.instructions.len())
+                    .filter(|index| !is_covered(index))
+                    .filter_map(|index| debug_info.functions.get(function_index).and_then(|positions| positions.get(index)).copied())
+                    .collect(),
+                None => Vec::new()
+            };
+
+            FunctionCoverage {
+                function_index,
+                name: self.program.function_name(function_index),
+                covered: covered.map_or(0, |covered| covered.len()),
+                total: function.instructions.len(),
+                uncovered_positions
+            }
+        }).collect()
+    }
+
+    /// How many times the body of a for loop was entered, over all loops and runs. Kept by `reset`, so
+    /// several runs add up, use `clear_loop_iterations` to count from zero again.
+    pub fn loop_iterations(&self) -> u64 {
+        self.loop_iterations
+    }
+
+    pub fn clear_loop_iterations(&mut self) {
+        self.loop_iterations = 0;
+    }
+
+    /// Makes the array read only, changing it from script or native functions is an error from now on.
+    /// Copies of the array (such as `Array.clone`) are not frozen.
+    pub fn freeze_array(&mut self, array: &Reference<Vec<Object>>) {
+        // forget arrays which were freed
+        self.frozen_arrays.retain(|_, frozen| frozen.strong_count() > 0);
+
+        self.frozen_arrays.insert(Rc::as_ptr(array) as usize, Rc::downgrade(array));
+    }
+
+    pub fn is_array_frozen(&self, array: &Reference<Vec<Object>>) -> bool {
+        // every index set of an array asks, skip hashing when nothing was frozen
+        !self.frozen_arrays.is_empty() && self.frozen_arrays.get(&(Rc::as_ptr(array) as usize)).is_some_and(|frozen| frozen.strong_count() > 0)
+    }
+
+    /// Error for native functions which change an array, Ok when the array is not frozen.
+    pub fn ensure_array_mutable(&self, array: &Reference<Vec<Object>>) -> Result<(), RuntimeError> {
+        if self.is_array_frozen(array) {
+            return Err(RuntimeError::new("can not change a frozen array", self.last_position()));
+        };
+
+        Ok(())
+    }
+
+    /// Brings the env back to the state right after it was created from the program, so the program can
+    /// run again without creating a new env. Globals (such as native functions) and native models are kept,
+    /// memory allocated by former runs is reused.
+    pub fn reset(&mut self) {
+        self.stack.clear();
+
+        while let Some(frame) = self.frames.pop() {
+            self.recycle_locals(frame.locals);
+        };
+
+        self.locals.clear();
+        load_local_values(&self.program, &mut self.locals);
+    }
+
+    /// Replaces the input used by native functions (such as `IO.readline`), default is stdin.
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.input = Input(input);
+    }
+
+    pub fn input(&mut self) -> &mut dyn BufRead {
+        self.input.0.as_mut()
+    }
+
+    /// Replaces the output used by native functions (such as `print`), default is stdout.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = Output(output);
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.output.0.as_mut()
+    }
+
+    /// Replaces the output used by native functions which print errors (such as `IO.eprint`), default is stderr.
+    pub fn set_error_output(&mut self, error_output: Box<dyn Write>) {
+        self.error_output = Output(error_output);
+    }
+
+    pub fn error_output(&mut self) -> &mut dyn Write {
+        self.error_output.0.as_mut()
+    }
+
     pub fn call_function_by_index(&mut self, function_index: usize, parameters: &[ Object ]) -> Result<(), RuntimeError> {
         let function = self.program.functions.get(function_index).unwrap();
 
@@ -73,49 +298,44 @@ impl Env {
             return Err(RuntimeError::new("too many parameters", Position::none()));
         }
 
-        let mut frame = Frame::new(function.local_variable_count, function_index, self.stack.len());
-
-        //for (i, object) in parameters.iter().enumerate() {
-        //    frame.locals[i] = object.clone();
-        //}
-        frame.locals.splice(..parameters.len(), parameters.iter().cloned());
+        let mut locals = self.spare_locals.pop().unwrap_or_default();
+        locals.extend_from_slice(parameters);
 
-        self.push_frame(frame);
+        self.push_function_frame(function_index, locals);
 
         Ok(())
     }
 
     pub fn current_frame_as_mut(&mut self) -> &mut Frame {
-        self.frames.back_mut().unwrap()
+        self.frames.last_mut().unwrap()
     }
 
     pub fn current_frame(&self) -> &Frame {
-        self.frames.back().unwrap()
+        self.frames.last().unwrap()
     }
 
     pub fn pop(&mut self) -> Option<Object> {
-        self.stack.pop_back()
+        self.stack.pop()
     }
 
     pub fn push(&mut self, object: Object) {
-        self.stack.push_back(object)
+        self.stack.push(object)
     }
 
     pub fn top(&self) -> Object {
-        self.stack.back().unwrap().clone()
+        self.stack.last().unwrap().clone()
     }
 
     pub fn last_position(&self) -> Position {
-        let program_counter = self.current_frame().program_counter;
-        if let Some(debug_info) = &self.program.debug_info {
-            if program_counter > 0 {
-                let function_index = self.current_frame().function_index;
-
-                return debug_info.functions[function_index][program_counter - 1];
-            };
-        };
+        // native function may be called by host directly, without any frame
+        match self.frames.last() {
+            Some(frame) => self.program.frame_position(frame),
+            None => Position::none()
+        }
+    }
 
-        Position::none()
+    fn current_filename(&self) -> Option<String> {
+        self.program.function_filename(self.frames.last()?.function_index).map(|filename| filename.to_string())
     }
 
     fn execute_until_frame_size_equal(&mut self, frame_size: usize) -> Result<Object, RuntimeError> {
@@ -134,18 +354,26 @@ impl Env {
         self.execute_until_frame_size_equal(frame_size)
     }
 
+    /// Calls a callable object (Luck function, instance function, native function or model) and
+    /// runs it to completion, so native functions can call back into Luck.
+    pub fn call(&mut self, callable: &Object, parameters: &[ Object ]) -> Result<Object, RuntimeError> {
+        self.execute_by_object(callable.clone(), parameters)
+    }
+
     pub fn get_object_property_by_name(&mut self, object: Object, name: &str) -> Result<Object, RuntimeError> {
         let name_index = Object::String(make_reference(name.to_string()));
+        let frame_size = self.frames.len();
 
-        self.instance_get_with_index(object, &name_index)?;
+        // a meta method (such as _to_float) runs in a frame of its own
+        self.instance_get_with_index(object, &name_index, false)?;
 
-        self.get_top()
+        self.execute_until_frame_size_equal(frame_size)
     }
 
-    pub fn get_object_property_by_index(&mut self, object: Object, index: i64) -> Result<Object, RuntimeError> {
+    pub fn get_object_property_by_index(&mut self, object: Object, index: Integer) -> Result<Object, RuntimeError> {
         let number_index = Object::Integer(index);
 
-        self.instance_get_with_index(object, &number_index)?;
+        self.instance_get_with_index(object, &number_index, false)?;
 
         self.get_top()
     }
@@ -168,6 +396,9 @@ impl Env {
         self.execute_until_frame_size_equal(frame_size)
     }
 
+    /// Runs the entry point of the program. The env is kept, globals (see `get_global`) and the file level locals
+    /// keep the values the run left, so the host can read them or call functions of the program afterwards.
+    /// After an error, call `reset` before running again.
     pub fn execute(&mut self) -> Result<Object, RuntimeError> {
         for &global_index in self.program.global_dependencies.iter() {
             if let Some(Object::String(global_name)) = self.program.constants.get(global_index) {
@@ -177,43 +408,119 @@ impl Env {
             }
         }
 
-        self.execute_by_function_index(self.program.entry_point, &[])
+        match self.program.entry_point {
+            Some(entry_point) => self.execute_by_function_index(entry_point, &[]),
+            None => Err(RuntimeError::new("this program has no entry point, define a main function or compile with an entry", Position::none()))
+        }
     }
 
     pub fn add_native_function(&mut self, name: &str, function: NativeFunction)  {
-        self.globals.insert(name.to_string(), Object::NativeFunction(function));
+        self.set_global(name, Object::NativeFunction(function));
     }
 
     pub fn add_native_model(&mut self, name: &str, native_model: Reference<dyn NativeModel>) -> usize {
         let index = self.native_models.len();
         self.native_models.push(native_model);
 
-        self.globals.insert(name.to_string(), Object::NativeModel(index));
+        self.set_global(name, Object::NativeModel(index));
 
         index
     }
 
+    /// Value of the global with the name, such as a native function or one a script assigned.
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.globals.get(name).map(|&index| self.global_values[index].clone())
+    }
+
+    /// Adds a global or replaces its value, scripts read it and assign it by name.
+    pub fn set_global(&mut self, name: &str, object: Object) {
+        if let Some(&index) = self.globals.get(name) {
+            self.global_values[index] = object;
+        } else {
+            self.globals.insert(name.to_string(), self.global_values.len());
+            self.global_values.push(object);
+        };
+    }
+
+    fn find_global_by_constant(&self, constant_index: usize) -> Result<usize, RuntimeError> {
+        if let Some(Object::String(global_name)) = self.program.constants.get(constant_index) {
+            if let Some(&index) = self.globals.get(global_name.borrow().deref()) {
+                return Ok(index);
+            };
+        };
+
+        Err(RuntimeError::new("global not found", self.last_position()))
+    }
+
+    fn find_global_by_slot(&mut self, slot: usize) -> Result<usize, RuntimeError> {
+        if let Some(index) = self.global_slots[slot] {
+            return Ok(index);
+        };
+
+        let index = self.find_global_by_constant(self.program.global_dependencies[slot])?;
+        self.global_slots[slot] = Some(index);
+
+        Ok(index)
+    }
+
     pub fn step(&mut self) -> Result<(), RuntimeError> {
-        if let Err(mut error) = self.internal_step() {
+        // an exhausted budget can not be rescued, the rescue block would run out of it again
+        let (result, rescuable) = match self.instruction_budget.as_mut() {
+            Some(0) => (Err(RuntimeError::new("instruction budget exhausted", self.last_position())), false),
+            Some(budget) => {
+                *budget -= 1;
+                (self.internal_step(), true)
+            },
+            None => (self.internal_step(), true)
+        };
 
-            let mut call_stack = LinkedList::new();
+        if let Err(mut error) = result {
+            // the script asked to exit, a rescue block would keep it running
+            let rescuable = rescuable && error.exit_code.is_none();
+
+            // errors passing through native code already have the file of the innermost function
+            if error.filename.is_none() {
+                error.filename = self.current_filename();
+            }
+
+            // frames are popped from the innermost one, which of them are the outermost is known at the end only
+            let (innermost_limit, outermost_limit) = self.stack_trace_limit;
+            let mut innermost = LinkedList::new();
+            let mut outermost = LinkedList::new();
+            let mut elided_frames = 0;
 
             while self.frames.len() > 0 {
                 let rescue_position = self.program.functions.get(self.current_frame().function_index).unwrap().rescue_position;
 
-                if rescue_position > 0 {
+                if rescuable && rescue_position > 0 {
+                    let stack_size = self.current_frame().stack_size;
+                    self.stack.truncate(stack_size);
+
+                    // the rescue block will take the error object from stack
+                    self.push(error.to_object());
                     self.current_frame_as_mut().program_counter = rescue_position;
                     return Ok(());
                 } else {
-                    let frame = self.frames.pop_back().unwrap();
-                    while self.stack.len() > frame.stack_size {
-                        self.stack.pop_back();
+                    let frame = self.frames.pop().unwrap();
+                    self.stack.truncate(frame.stack_size);
+
+                    if innermost.len() < innermost_limit {
+                        innermost.push_front(frame);
+                    } else {
+                        outermost.push_front(frame);
+
+                        if outermost.len() > outermost_limit {
+                            let elided_frame = outermost.pop_back().unwrap();
+                            self.recycle_locals(elided_frame.locals);
+                            elided_frames += 1;
+                        };
                     };
-                    call_stack.push_front(frame);
                 }
             }
 
-            error.stack = call_stack;
+            outermost.append(&mut innermost);
+            error.stack = outermost;
+            error.elided_frames = elided_frames;
 
             Err(error)
         } else {
@@ -282,25 +589,56 @@ impl Env {
             Object::InstanceNativeFunction(instance, function_name) => self.call_instance_native_function(instance, &function_name, parameters),
             Object::NativeModel(model_index) => self.call_native_model_by_index(model_index, parameters),
             Object::Model(model_index) => self.call_model_by_index(model_index, parameters),
-            _ => Err(RuntimeError::new(&format!("can not call {:?}", object), self.last_position()))
+            _ => Err(RuntimeError::new(&format!("value of type {} is not callable", object.type_name()), self.last_position()))
         }
     }
 
     fn execute_call_opcode(&mut self, parameter_count: usize) -> Result<(), RuntimeError> {
-        let mut parameters = vec![Object::Null; parameter_count];
+        let function_position = self.stack.len() - parameter_count - 1;
+
+        // parameters of luck functions are moved from the stack into the frame directly
+        if let Object::Function(function_index) = self.stack[function_position] {
+            if parameter_count > self.program.functions[function_index].parameter_count {
+                return Err(RuntimeError::new("too many parameters", self.last_position()));
+            };
+
+            let mut locals = self.spare_locals.pop().unwrap_or_default();
+            locals.extend(self.stack.drain(function_position + 1..));
+            self.stack.pop();
+
+            self.push_function_frame(function_index, locals);
 
-        for i in (0..parameter_count).rev() {
-            parameters[i] = self.stack.pop_back().unwrap();
+            return Ok(());
         };
 
-        let function_object = self.stack.pop_back().unwrap();
+        let parameters = self.stack.split_off(function_position + 1);
+        let function_object = self.stack.pop().unwrap();
 
         self.call_object(function_object, &parameters)
     }
 
+    // locals start with the parameters, the rest are filled with null
+    fn push_function_frame(&mut self, function_index: usize, mut locals: Vec<Object>) {
+        locals.resize(self.program.functions[function_index].local_variable_count, Object::Null);
+
+        let frame = Frame {
+            locals,
+            program_counter: 0,
+            function_index,
+            stack_size: self.stack.len()
+        };
+
+        self.push_frame(frame);
+    }
+
+    fn recycle_locals(&mut self, mut locals: Vec<Object>) {
+        locals.clear();
+        self.spare_locals.push(locals);
+    }
+
     fn current_instruction(&self) -> Instruction {
         let (function_index, program_counter) = {
-            let frame = self.frames.back().unwrap();
+            let frame = self.frames.last().unwrap();
             (frame.function_index, frame.program_counter)
         };
 
@@ -310,22 +648,23 @@ impl Env {
     }
 
     fn push_frame(&mut self, frame: Frame) {
-        self.frames.push_back(frame);
+        self.frames.push(frame);
     }
 
     fn pop_frame(&mut self) {
-        let frame = self.frames.pop_back().unwrap();
+        let frame = self.frames.pop().unwrap();
+        let stack_size = frame.stack_size;
+
+        self.recycle_locals(frame.locals);
 
-        if self.stack.len() > frame.stack_size + 1 {
+        if self.stack.len() > stack_size + 1 {
             return;
         };
 
         let return_value = self.pop().unwrap();
 
         // clean up stack
-        while self.stack.len() > frame.stack_size {
-            self.pop();
-        };
+        self.stack.truncate(stack_size);
 
         self.push(return_value);
     }
@@ -345,10 +684,11 @@ impl Env {
         Ok(())
     }
 
-    fn instance_get_with_index(&mut self, instance: Object, index: &Object) -> Result<(), RuntimeError> {
+    // method is true when the result is called, a model instance without it is an error instead of null
+    fn instance_get_with_index(&mut self, instance: Object, index: &Object, method: bool) -> Result<(), RuntimeError> {
         match instance {
             Object::Model(model_index) => self.index_get_model(model_index, index)?,
-            Object::Instance(model_instance) => self.index_get_model_instance(model_instance, index)?,
+            Object::Instance(model_instance) => self.index_get_model_instance(model_instance, index, method)?,
             Object::NativeModel(model_index) => self.instance_get_native_model(model_index, index.as_reference_string().borrow().deref())?,
             Object::NativeInstance(instance) => self.instance_get_native_instance(instance, index.as_reference_string().borrow().deref())?,
 
@@ -357,6 +697,7 @@ impl Env {
             Object::String(value) => instance_get_string(self, value, index.as_reference_string().borrow().deref())?,
 
             Object::Array(array) => instance_get_array(self, array, index.as_reference_string().borrow().deref())?,
+            Object::Bytes(bytes) => instance_get_bytes(self, bytes, index.as_reference_string().borrow().deref())?,
             _ => {
                 return Err(RuntimeError::new("this object's instance get did not implemented yet", self.last_position()));
             }
@@ -365,11 +706,11 @@ impl Env {
         Ok(())
     }
 
-    fn instance_get(&mut self) -> Result<(), RuntimeError> {
+    fn instance_get(&mut self, operand: u64) -> Result<(), RuntimeError> {
         let index = self.pop().unwrap();
         let instance = self.pop().unwrap();
 
-        self.instance_get_with_index(instance, &index)
+        self.instance_get_with_index(instance, &index, operand == INSTANCE_GET_METHOD)
     }
 
     // index get for model
@@ -387,7 +728,8 @@ impl Env {
         Ok(())
     }
 
-    fn index_get_model_instance(&mut self, model_instance: Reference<ModelInstance>, index: &Object) -> Result<(), RuntimeError> {
+    // a missing key is null for instance.key, an error for calls and for instance[key]
+    fn index_get_model_instance(&mut self, model_instance: Reference<ModelInstance>, index: &Object, missing_is_error: bool) -> Result<(), RuntimeError> {
         let model = self.program.models.get(model_instance.borrow().deref().model_index).unwrap();
 
         match index {
@@ -401,6 +743,12 @@ impl Env {
                         Object::Function(function_index)
                     };
                     self.push(object);
+                } else if let Some(&function_index) = conversion_meta_method(key.borrow().deref()).and_then(|name| model.functions.get(name)) {
+                    // .integer and .float of an instance are what its _to_integer and _to_float return
+                    self.call_function_by_index(function_index, &[ Object::Instance(model_instance.clone()) ])?;
+                } else if missing_is_error {
+                    let model_name = self.program.model_name(model_instance.borrow().model_index);
+                    return Err(RuntimeError::new(&format!("model [{}] does not have method or property [{}]", model_name, key.borrow().deref()), self.last_position()));
                 } else {
                     self.push(Object::Null);
                 }
@@ -422,7 +770,7 @@ impl Env {
         match index {
             Object::Integer(i) => {
                 let array_index = *i;
-                if array_index < 0 || array_index >= array.borrow().deref().len() as i64 {
+                if array_index < 0 || array_index >= array.borrow().deref().len() as Integer {
                     return Err(RuntimeError::new("index out of range", self.last_position()));
                 };
 
@@ -436,14 +784,33 @@ impl Env {
         Ok(())
     }
 
+    fn index_get_bytes(&mut self, bytes: Reference<Vec<u8>>, index: &Object) -> Result<(), RuntimeError> {
+        match index {
+            Object::Integer(i) => {
+                if *i < 0 || *i >= bytes.borrow().len() as Integer {
+                    return Err(RuntimeError::new("index out of range", self.last_position()));
+                };
+
+                let byte = bytes.borrow()[*i as usize];
+                self.push(Object::Integer(byte as Integer));
+            },
+            _ => {
+                return Err(RuntimeError::new("can not get bytes with object index", self.last_position()));
+            }
+        };
+
+        Ok(())
+    }
+
     fn index_get(&mut self) -> Result<(), RuntimeError> {
         let index = self.pop().unwrap();
         let instance = self.pop().unwrap();
 
         match instance {
             Object::Model(model_index) => self.index_get_model(model_index, &index)?,
-            Object::Instance(model_instance) => self.index_get_model_instance(model_instance, &index)?,
+            Object::Instance(model_instance) => self.index_get_model_instance(model_instance, &index, true)?,
             Object::Array(array) => self.index_get_array(array, &index)?,
+            Object::Bytes(bytes) => self.index_get_bytes(bytes, &index)?,
             Object::NativeInstance(instance) => {
                 let instance_copy = instance.clone();
                 self.push(instance.borrow_mut().index_get(instance_copy, &index)?);
@@ -490,10 +857,15 @@ impl Env {
             // have property?
             if let Some(&property_index) = model.property_indices.get(key.borrow().deref()) {
                 self.index_set_model_instance_by_index(model_instance, property_index)?;
+            } else {
+                let model_name = self.program.model_name(model_instance.borrow().model_index);
+                return Err(RuntimeError::new(&format!("model [{}] does not have property [{}]", model_name, key.borrow().deref()), self.last_position()));
             };
 
         } else if let Object::Integer(i) = &index {
             self.index_set_model_instance_by_index(model_instance, *i as usize)?;
+        } else {
+            return Err(RuntimeError::new("can not set instance with object index", self.last_position()));
         }
 
         Ok(())
@@ -502,10 +874,14 @@ impl Env {
     fn index_set_array(&mut self, array: Reference<Vec<Object>>, index: &Object) -> Result<(), RuntimeError> {
         match index {
             Object::Integer(i) => {
-                if *i < 0 || *i >= array.borrow().deref().len() as i64 {
-                    return Err(RuntimeError::new("index out of range", self.last_position()));
+                // arrays do not grow by index, Array.set of the std can grow them
+                let length = array.borrow().deref().len();
+                if *i < 0 || *i >= length as Integer {
+                    return Err(RuntimeError::new(&format!("index {} is out of range for an array of {} items", i, length), self.last_position()));
                 };
 
+                self.ensure_array_mutable(&array)?;
+
                 array.borrow_mut()[*i as usize] = self.top();
             },
             _ => {
@@ -516,6 +892,21 @@ impl Env {
         Ok(())
     }
 
+    fn index_set_bytes(&mut self, bytes: Reference<Vec<u8>>, index: &Object) -> Result<(), RuntimeError> {
+        let i = match index {
+            Object::Integer(i) if *i >= 0 && *i < bytes.borrow().len() as Integer => *i as usize,
+            Object::Integer(_) => return Err(RuntimeError::new("index out of range", self.last_position())),
+            _ => return Err(RuntimeError::new("can not set bytes with object index", self.last_position()))
+        };
+
+        bytes.borrow_mut()[i] = match self.top() {
+            Object::Integer(value) if (0..=255).contains(&value) => value as u8,
+            _ => return Err(RuntimeError::new("a byte must be an integer from 0 to 255", self.last_position()))
+        };
+
+        Ok(())
+    }
+
     fn index_set(&mut self) -> Result<(), RuntimeError> {
         let index = self.pop().unwrap();
         let instance = self.pop().unwrap();
@@ -527,6 +918,7 @@ impl Env {
                 instance.borrow_mut().index_set(instance_copy, &index, self.top())?
             },
             Object::Array(array) => self.index_set_array(array, &index)?,
+            Object::Bytes(bytes) => self.index_set_bytes(bytes, &index)?,
             _ => {
                 return Err(RuntimeError::new("this object's instance set did not implemented yet", self.last_position()));
             }
@@ -558,18 +950,37 @@ impl Env {
         Ok(())
     }
 
+    fn record_coverage(&mut self) {
+        let frame = self.frames.last().unwrap();
+        let (function_index, program_counter) = (frame.function_index, frame.program_counter);
+
+        if let Some(coverage) = self.coverage.as_mut() {
+            // the repl adds functions after coverage was enabled
+            if function_index >= coverage.len() {
+                coverage.resize(function_index + 1, HashSet::new());
+            };
+
+            coverage[function_index].insert(program_counter);
+        };
+    }
+
     fn internal_step(&mut self) -> Result<(), RuntimeError> {
         let instruction = self.current_instruction();
         let opcode = instruction.opcode();
 
+        if self.coverage.is_some() {
+            self.record_coverage();
+        };
+
         self.current_frame_as_mut().program_counter += 1;
 
         match opcode {
-            OpCode::Pop => { self.stack.pop_back(); },
+            OpCode::Pop => { self.stack.pop(); },
             OpCode::PushConstant => {
                 let constant = self.program.constants[instruction.operand() as usize].clone();
                 self.push(constant);
             },
+            OpCode::PushInteger => self.push(Object::Integer(instruction.operand() as Integer + Program::SMALL_INTEGER_MIN)),
             OpCode::Return => { self.pop_frame(); },
 
             OpCode::LocalGet => self.push(self.current_frame().locals.get(instruction.operand() as usize).unwrap().clone()),
@@ -580,31 +991,22 @@ impl Env {
             OpCode::ContextSet => { self.locals[instruction.operand() as usize] = self.top(); },
 
             OpCode::GlobalGet => {
-                let global_object_option = if let Some(Object::String(global_name)) = self.program.constants.get(instruction.operand() as usize) {
-                    if let Some(object) = self.globals.get(global_name.borrow().deref()) {
-                       Some(object.clone())
-                    } else {
-                        return Err(RuntimeError::new("global not found", self.last_position()));
-                    }
-                } else {
-                    None
-                };
-
-                if let Some(global_object) = global_object_option {
-                    self.push(global_object);
-                };
-
+                let index = self.find_global_by_constant(instruction.operand() as usize)?;
+                self.push(self.global_values[index].clone());
             },
             OpCode::GlobalSet => {
-                if let Some(Object::String(global_name)) = self.program.constants.get(instruction.operand() as usize) {
-                    if let Some(object) = self.globals.get_mut(global_name.borrow().deref()) {
-                        *object = self.stack.back().unwrap().clone();
-                    } else {
-                        return Err(RuntimeError::new("global not found", self.last_position()));
-                    }
-                }
+                let index = self.find_global_by_constant(instruction.operand() as usize)?;
+                self.global_values[index] = self.top();
+            },
+            OpCode::GlobalGetSlot => {
+                let index = self.find_global_by_slot(instruction.operand() as usize)?;
+                self.push(self.global_values[index].clone());
+            },
+            OpCode::GlobalSetSlot => {
+                let index = self.find_global_by_slot(instruction.operand() as usize)?;
+                self.global_values[index] = self.top();
             },
-            OpCode::InstanceGet => self.instance_get()?,
+            OpCode::InstanceGet => self.instance_get(instruction.operand())?,
             OpCode::InstanceSet => self.instance_set()?,
             OpCode::IndexGet => self.index_get()?,
             OpCode::IndexSet => self.index_set()?,
@@ -626,7 +1028,12 @@ impl Env {
                     self.current_frame_as_mut().program_counter = instruction.operand() as usize;
                 };
             },
+            OpCode::JumpIfNull if self.top().is_null() => {
+                self.current_frame_as_mut().program_counter = instruction.operand() as usize;
+            },
+            OpCode::JumpIfNull => {},
             OpCode::ForNext => { self.for_next(instruction.operand() as usize)?; },
+            OpCode::ForNextPair => { self.for_next_pair(instruction.operand() as usize)?; },
             OpCode::Iterate => { self.iterate(instruction.operand() as usize); },
             _ => {
                 // not implemented
@@ -642,4 +1049,23 @@ fn make_instance_call_parameters(object: Object, parameters: &[ Object ]) -> Vec
     let mut new_parameters = vec![ object ];
     new_parameters.extend_from_slice(parameters);
     new_parameters
-}
\ No newline at end of file
+}
+
+// file locals from the current length up to the count of the program, with their values from the program
+fn load_local_values(program: &Program, locals: &mut Vec<Object>) {
+    for i in locals.len()..program.local_variable_count {
+        locals.push(if let Some(constant_index) = program.local_values.get(&i) {
+            program.constants.get(*constant_index).unwrap().clone()
+        } else {
+            Object::Null
+        });
+    };
+}
+
+fn conversion_meta_method(key: &str) -> Option<&'static str> {
+    match key {
+        "integer" => Some("_to_integer"),
+        "float" => Some("_to_float"),
+        _ => None
+    }
+}
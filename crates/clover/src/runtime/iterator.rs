@@ -1,67 +1,116 @@
 use crate::runtime::env::Env;
 use crate::runtime::program::RuntimeError;
-use crate::runtime::object::Object;
+use crate::runtime::object::{Object, Integer};
 
 impl Env {
     /// Advances the iterator of the current enumerable object in the runtime env.
     ///
     /// This function is responsible for iterating over different types of enumerable objects
-    /// (integers, arrays, and instances) and pushing the next value onto the stack. It also
+    /// (integers, arrays, bytes and instances) and pushing the next value onto the stack. It also
     /// pushes a boolean value indicating whether the loop should continue or not.
     ///
     /// # Errors
     /// This function may return a `RuntimeError` if the enumerable object is not a valid type.
     pub fn for_next(&mut self, enumerable_index: usize) -> Result<(), RuntimeError> {
-        let iterator_index = enumerable_index + 1;
+        self.snapshot_length(enumerable_index);
 
         let enumerable = self.current_frame().locals[enumerable_index].clone();
 
+        let jump = match self.next_entry(enumerable_index) {
+            // the name of a property for instances, the value for the others
+            Some((key, value)) => {
+                self.push(if let Object::Instance(_) = enumerable { key } else { value });
+                self.loop_iterations += 1;
+                false
+            },
+            // iterator reached the end of enumerable object, finish loop
+            None => true
+        };
+
+        self.push(Object::Boolean(jump));
+
+        Ok(())
+    }
+
+    /// Same as `for_next`, but pushes both the key and the value for `for key, value in`. The key is the index
+    /// for arrays and integers, and the name of the property for instances.
+    pub fn for_next_pair(&mut self, enumerable_index: usize) -> Result<(), RuntimeError> {
+        self.snapshot_length(enumerable_index);
+
+        let jump = match self.next_entry(enumerable_index) {
+            Some((key, value)) => {
+                self.push(key);
+                self.push(value);
+                self.loop_iterations += 1;
+                false
+            },
+            None => true
+        };
+
+        self.push(Object::Boolean(jump));
+
+        Ok(())
+    }
+
+    // keeps the length of an array at the first step, items pushed in the loop are not visited
+    fn snapshot_length(&mut self, enumerable_index: usize) {
+        let length_index = enumerable_index + 2;
+
+        let length = match (&self.current_frame().locals[enumerable_index], &self.current_frame().locals[length_index]) {
+            (Object::Array(array), Object::Null) => array.borrow().len() as Integer,
+            _ => return
+        };
+
+        self.current_frame_as_mut().locals[length_index] = Object::Integer(length);
+    }
+
+    // key and value at the iterator of the enumerable object, None when the loop is finished
+    fn next_entry(&self, enumerable_index: usize) -> Option<(Object, Object)> {
+        let iterator_index = enumerable_index + 1;
+        let length_index = enumerable_index + 2;
+
+        let enumerable = &self.current_frame().locals[enumerable_index];
+
         let iterator = if let Object::Integer(iterator) = self.current_frame().locals[iterator_index].clone() {
             iterator
         } else {
             0
         };
 
-        let jump = match enumerable {
+        match enumerable {
             Object::Integer(value) => {
-                if iterator < value {
-                    self.push(Object::Integer(iterator));
-                    false
+                if iterator < *value {
+                    Some((Object::Integer(iterator), Object::Integer(iterator)))
                 } else {
-                    // iterator greater than enumerable object, finish loop
-                    true
+                    None
                 }
             },
             Object::Array(array) => {
+                // the array may also be shorter than at the start when items were removed in the loop
+                if let Object::Integer(length) = self.current_frame().locals[length_index] {
+                    if iterator >= length {
+                        return None;
+                    };
+                };
+
                 let index = iterator as usize;
 
-                if index < array.borrow().len() {
-                    self.push(array.borrow()[index].clone());
-                    false
-                } else {
-                    // iterator greater than enumerable array len, finish loop
-                    true
-                }
+                array.borrow().get(index).map(|value| (Object::Integer(iterator), value.clone()))
             },
+            Object::Bytes(bytes) => bytes.borrow().get(iterator as usize).map(|value| (Object::Integer(iterator), Object::Integer(*value as Integer))),
             Object::Instance(instance) => {
                 let model_index = instance.borrow().model_index;
                 let model = &self.get_program().models[model_index];
                 let index = iterator as usize;
 
                 if index < model.property_names.len() {
-                    let object = Object::String(model.property_names[index].clone());
-                    self.push(object);
-                    false
+                    Some((Object::String(model.property_names[index].clone()), instance.borrow().properties[index].clone()))
                 } else {
-                    true
+                    None
                 }
             }
-            _ => true
-        };
-
-        self.push(Object::Boolean(jump));
-
-        Ok(())
+            _ => None
+        }
     }
 
     pub fn iterate(&mut self, iterator_index: usize) {
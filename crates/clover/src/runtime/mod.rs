@@ -1,10 +1,13 @@
 pub mod object;
 pub mod program;
+pub mod program_builder;
 pub mod opcode;
 pub mod runtime_info;
 pub mod env;
+pub mod object_display;
 
 mod operation;
 mod object_property;
 mod iterator;
+mod builtin;
 
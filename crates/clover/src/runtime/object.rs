@@ -1,13 +1,23 @@
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::fmt;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use crate::runtime::env::Env;
 use crate::runtime::program::RuntimeError;
 use std::ops::Deref;
 use crate::debug::Position;
+use crate::runtime::object_display::reference_address;
 
 pub type Reference<T> = Rc<RefCell<T>>;
 
+// numbers of scripts, everything which stores or converts them goes through these so the width is changed here only
+pub type Integer = i64;
+pub type Float = f64;
+
+// longest array, bytes or string a single call makes from a count given by a script, so a huge count is an
+// error instead of taking all the memory of the host
+pub const MAX_ALLOCATION_LENGTH: usize = 1 << 24;
+
 pub fn make_reference<T>(object: T) -> Reference<T> {
     Rc::new(RefCell::new(object))
 }
@@ -35,10 +45,16 @@ pub trait NativeModelInstance {
 
     fn call(&mut self, this: Reference<dyn NativeModelInstance>, env: &mut Env, key: &str, parameters: &[Object]) ->Result<Object, RuntimeError>;
 
-    fn raw_get_integer(&self, _key: &str) -> Option<i64> { None }
-    fn raw_get_float(&self, _key: &str) -> Option<f64> { None }
+    fn raw_get_integer(&self, _key: &str) -> Option<Integer> { None }
+    fn raw_get_float(&self, _key: &str) -> Option<Float> { None }
     fn raw_get_boolean(&self, _key: &str) -> Option<bool> { None }
     fn raw_get_byte_array(&self, _key: &str) -> Option<&[u8]> { None }
+
+    // key value pairs for map like instances, used by serialization
+    fn raw_entries(&self) -> Option<Vec<(String, Object)>> { None }
+
+    // a new instance of the same kind with the entries, for copy and deep_copy of map like instances
+    fn raw_copy(&self, _entries: Vec<(String, Object)>) -> Option<Reference<dyn NativeModelInstance>> { None }
 }
 
 pub fn ensure_parameters_length(parameters: &[Object], length: usize) -> Result<(), RuntimeError> {
@@ -49,9 +65,50 @@ pub fn ensure_parameters_length(parameters: &[Object], length: usize) -> Result<
     Ok(())
 }
 
+// a reference which does not keep the object alive, to break cycles such as back references
+pub enum WeakObject {
+    String(Weak<RefCell<String>>),
+    Instance(Weak<RefCell<ModelInstance>>),
+    NativeInstance(Weak<RefCell<dyn NativeModelInstance>>),
+    Array(Weak<RefCell<Vec<Object>>>)
+}
+
+impl WeakObject {
+    pub fn new(object: &Object) -> Option<WeakObject> {
+        match object {
+            Object::String(value) => Some(WeakObject::String(Rc::downgrade(value))),
+            Object::Instance(instance) => Some(WeakObject::Instance(Rc::downgrade(instance))),
+            Object::NativeInstance(instance) => Some(WeakObject::NativeInstance(Rc::downgrade(instance))),
+            Object::Array(array) => Some(WeakObject::Array(Rc::downgrade(array))),
+            _ => None
+        }
+    }
+
+    // the object, or None when it was already freed
+    pub fn upgrade(&self) -> Option<Object> {
+        match self {
+            WeakObject::String(value) => value.upgrade().map(Object::String),
+            WeakObject::Instance(instance) => instance.upgrade().map(Object::Instance),
+            WeakObject::NativeInstance(instance) => instance.upgrade().map(Object::NativeInstance),
+            WeakObject::Array(array) => array.upgrade().map(Object::Array)
+        }
+    }
+}
+
+impl Clone for WeakObject {
+    fn clone(&self) -> Self {
+        match self {
+            WeakObject::String(value) => WeakObject::String(value.clone()),
+            WeakObject::Instance(instance) => WeakObject::Instance(instance.clone()),
+            WeakObject::NativeInstance(instance) => WeakObject::NativeInstance(instance.clone()),
+            WeakObject::Array(array) => WeakObject::Array(array.clone())
+        }
+    }
+}
+
 pub enum Object {
-    Integer(i64),
-    Float(f64),
+    Integer(Integer),
+    Float(Float),
     Boolean(bool),
     Null,
 
@@ -69,6 +126,10 @@ pub enum Object {
     NativeInstance(Reference<dyn NativeModelInstance>),
 
     Array(Reference<Vec<Object>>),
+    // binary data, one byte per item instead of an object
+    Bytes(Reference<Vec<u8>>),
+
+    WeakRef(WeakObject),
 }
 
 impl fmt::Debug for Object {
@@ -85,6 +146,7 @@ impl fmt::Debug for Object {
             Object::Model(value) => struct_format.field("Model", value),
             Object::Instance(value) => struct_format.field("Instance", value),
             Object::Array(array) => struct_format.field("Array", array.deref()),
+            Object::Bytes(bytes) => struct_format.field("Bytes", bytes.deref()),
             _ => struct_format.field("Unknown", &"Unknown".to_string())
         }.finish()
     }
@@ -106,11 +168,81 @@ impl Clone for Object {
             Object::NativeModel(index) => Object::NativeModel(*index),
             Object::Instance(instance) => Object::Instance(instance.clone()),
             Object::NativeInstance(instance) => Object::NativeInstance(instance.clone()),
-            Object::Array(value) => Object::Array(value.clone())
+            Object::Array(value) => Object::Array(value.clone()),
+            Object::Bytes(value) => Object::Bytes(value.clone()),
+            Object::WeakRef(value) => Object::WeakRef(value.clone())
         }
     }
 }
 
+/// Equality for hosts, such as `assert_eq!(result, Object::Integer(3))`.
+///
+/// Numbers are only equal to numbers of the same type, `Integer(1)` is not `Float(1.0)` while `1 == 1.0` is true in
+/// scripts, and `Float(NAN)` is not equal to itself, the same as in scripts. Strings and bytes compare by content, arrays item by item and native instances
+/// which expose their entries (such as maps) entry by entry.
+/// Model instances, other native instances and native functions are equal only when they are the same object.
+impl PartialEq for Object {
+    fn eq(&self, other: &Object) -> bool {
+        objects_equal(self, other, &mut HashSet::new())
+    }
+}
+
+// visiting holds the pairs of references being compared, a pair met again is in a cycle and taken as equal
+fn objects_equal(left: &Object, right: &Object, visiting: &mut HashSet<(usize, usize)>) -> bool {
+    match (left, right) {
+        (Object::Integer(left), Object::Integer(right)) => left == right,
+        (Object::Float(left), Object::Float(right)) => left == right,
+        (Object::Boolean(left), Object::Boolean(right)) => left == right,
+        (Object::Null, Object::Null) => true,
+
+        (Object::Function(left), Object::Function(right)) => left == right,
+        (Object::InstanceFunction(left_this, left), Object::InstanceFunction(right_this, right)) => left == right && objects_equal(left_this, right_this, visiting),
+        (Object::NativeFunction(left), Object::NativeFunction(right)) => std::ptr::fn_addr_eq(*left, *right),
+        (Object::InstanceNativeFunction(left_this, left), Object::InstanceNativeFunction(right_this, right)) => Rc::ptr_eq(left_this, right_this) && left == right,
+
+        (Object::Model(left), Object::Model(right)) => left == right,
+        (Object::NativeModel(left), Object::NativeModel(right)) => left == right,
+
+        (Object::String(left), Object::String(right)) => Rc::ptr_eq(left, right) || *left.borrow() == *right.borrow(),
+        (Object::Instance(left), Object::Instance(right)) => Rc::ptr_eq(left, right),
+        (Object::NativeInstance(left), Object::NativeInstance(right)) => {
+            if Rc::ptr_eq(left, right) || !visiting.insert((reference_address(left), reference_address(right))) {
+                return true;
+            };
+
+            match (left.borrow().raw_entries(), right.borrow().raw_entries()) {
+                (Some(mut left), Some(mut right)) => {
+                    left.sort_by(|left, right| left.0.cmp(&right.0));
+                    right.sort_by(|left, right| left.0.cmp(&right.0));
+
+                    left.len() == right.len() && left.iter().zip(right.iter()).all(|(left, right)| left.0 == right.0 && objects_equal(&left.1, &right.1, visiting))
+                },
+                _ => false
+            }
+        },
+        (Object::Array(left), Object::Array(right)) => {
+            if Rc::ptr_eq(left, right) || !visiting.insert((reference_address(left), reference_address(right))) {
+                return true;
+            };
+
+            let (left, right) = (left.borrow(), right.borrow());
+            left.len() == right.len() && left.iter().zip(right.iter()).all(|(left, right)| objects_equal(left, right, visiting))
+        },
+
+        (Object::Bytes(left), Object::Bytes(right)) => Rc::ptr_eq(left, right) || *left.borrow() == *right.borrow(),
+
+        (Object::WeakRef(left), Object::WeakRef(right)) => match (left, right) {
+            (WeakObject::String(left), WeakObject::String(right)) => Weak::ptr_eq(left, right),
+            (WeakObject::Instance(left), WeakObject::Instance(right)) => Weak::ptr_eq(left, right),
+            (WeakObject::NativeInstance(left), WeakObject::NativeInstance(right)) => Weak::ptr_eq(left, right),
+            (WeakObject::Array(left), WeakObject::Array(right)) => Weak::ptr_eq(left, right),
+            _ => false
+        },
+
+        _ => false
+    }
+}
+
 impl Object {
     pub fn is_string(&self) -> bool {
         matches!(self, Object::String(_))
@@ -134,7 +266,29 @@ impl Object {
 
     pub fn is_null(&self) -> bool { matches!(self, Object::Null) }
 
-    pub fn integer_value(&self) -> Result<i64, RuntimeError> {
+    // not a From impl, it would make Object::from(Vec::new()) ambiguous
+    pub fn from_bytes(bytes: Vec<u8>) -> Object {
+        Object::Bytes(make_reference(bytes))
+    }
+
+    // short name of the kind of object, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "integer",
+            Object::Float(_) => "float",
+            Object::Boolean(_) => "boolean",
+            Object::Null => "null",
+            Object::Function(_) | Object::InstanceFunction(_, _) | Object::NativeFunction(_) | Object::InstanceNativeFunction(_, _) => "function",
+            Object::Model(_) | Object::NativeModel(_) => "model",
+            Object::String(_) => "string",
+            Object::Instance(_) | Object::NativeInstance(_) => "instance",
+            Object::Array(_) => "array",
+            Object::Bytes(_) => "bytes",
+            Object::WeakRef(_) => "weak reference"
+        }
+    }
+
+    pub fn integer_value(&self) -> Result<Integer, RuntimeError> {
         if let Object::Integer(value) = self {
             Ok(*value)
         } else {
@@ -142,10 +296,10 @@ impl Object {
         }
     }
 
-    pub fn float_value(&self) -> Result<f64, RuntimeError> {
+    pub fn float_value(&self) -> Result<Float, RuntimeError> {
         match self {
             Object::Float(value) => Ok(*value),
-            Object::Integer(value) => Ok(*value as f64),
+            Object::Integer(value) => Ok(*value as Float),
             _ => Err(RuntimeError::new("value is not a float", Position::none())),
         }
     }
@@ -168,23 +322,191 @@ impl Object {
 
 }
 
-fn objects_to_string(objects: &Vec<Object>) -> String {
-    objects.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(", ")
+// floats always keep a fraction part (1.0), very large or small ones use exponent form (1e-10)
+pub fn format_float(value: Float) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    };
+
+    let magnitude = value.abs();
+
+    if magnitude != 0.0 && !(1e-5..1e16).contains(&magnitude) {
+        format!("{:e}", value)
+    } else if value.fract() == 0.0 {
+        format!("{:.1}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn objects_to_string(objects: &[Object], visiting: &mut HashSet<usize>) -> String {
+    objects.iter().map(|value| object_to_string(value, visiting)).collect::<Vec<String>>().join(", ")
+}
+
+// visiting holds the references being converted, a reference met again is a cycle
+fn object_to_string(object: &Object, visiting: &mut HashSet<usize>) -> String {
+    match object {
+        Object::Instance(instance) => {
+            let address = reference_address(instance);
+            if !visiting.insert(address) {
+                return "{...}".to_string();
+            };
+
+            let string = "{ (".to_string() + instance.borrow().deref().model_index.to_string().as_str() + ") " + objects_to_string(&instance.borrow().deref().properties, visiting).as_str() + " }";
+            visiting.remove(&address);
+            string
+        },
+        Object::Array(array) => {
+            let address = reference_address(array);
+            if !visiting.insert(address) {
+                return "[...]".to_string();
+            };
+
+            let string = "[ ".to_string() + objects_to_string(array.borrow().deref(), visiting).as_str() + " ]";
+            visiting.remove(&address);
+            string
+        },
+        _ => object.to_string()
+    }
 }
 
 impl ToString for Object {
     fn to_string(&self) -> String {
         match self {
             Object::Integer(value) => value.to_string(),
-            Object::Float(value) => value.to_string(),
+            Object::Float(value) => format_float(*value),
             Object::String(value) => value.borrow().deref().clone(),
             Object::Boolean(value) => value.to_string(),
             Object::Null => "null".to_string(),
 
             Object::Model(index) => "{ (".to_string() + index.to_string().as_str() + ") }",
-            Object::Instance(instance) => "{ (".to_string() + instance.borrow().deref().model_index.to_string().as_str() + ") " + objects_to_string(&instance.borrow().deref().properties).as_str() + " }",
-            Object::Array(array) => "[ ".to_string() + objects_to_string(array.borrow().deref()).as_str() + " ]",
+            Object::Instance(_) | Object::Array(_) => object_to_string(self, &mut HashSet::new()),
+            Object::Bytes(bytes) => format!("<bytes {}>", bytes.borrow().len()),
+            Object::WeakRef(_) => "WeakRef".to_string(),
             _ => "Unknown".to_string()
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<Integer> for Object {
+    fn from(value: Integer) -> Self { Object::Integer(value) }
+}
+
+impl From<Float> for Object {
+    fn from(value: Float) -> Self { Object::Float(value) }
+}
+
+impl From<bool> for Object {
+    fn from(value: bool) -> Self { Object::Boolean(value) }
+}
+
+impl From<String> for Object {
+    fn from(value: String) -> Self { Object::String(make_reference(value)) }
+}
+
+impl From<&str> for Object {
+    fn from(value: &str) -> Self { Object::String(make_reference(value.to_string())) }
+}
+
+impl From<Vec<Object>> for Object {
+    fn from(value: Vec<Object>) -> Self { Object::Array(make_reference(value)) }
+}
+
+impl TryFrom<Object> for Integer {
+    type Error = RuntimeError;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> { object.integer_value() }
+}
+
+impl TryFrom<Object> for Float {
+    type Error = RuntimeError;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> { object.float_value() }
+}
+
+impl TryFrom<Object> for bool {
+    type Error = RuntimeError;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        if let Object::Boolean(value) = object {
+            Ok(value)
+        } else {
+            Err(RuntimeError::new("value is not a boolean", Position::none()))
+        }
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = RuntimeError;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> { object.string_value() }
+}
+
+impl TryFrom<Object> for Vec<u8> {
+    type Error = RuntimeError;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        if let Object::Bytes(bytes) = object {
+            Ok(bytes.borrow().deref().clone())
+        } else {
+            Err(RuntimeError::new("value is not bytes", Position::none()))
+        }
+    }
+}
+
+impl TryFrom<Object> for Vec<Object> {
+    type Error = RuntimeError;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        if let Object::Array(array) = object {
+            Ok(array.borrow().deref().clone())
+        } else {
+            Err(RuntimeError::new("value is not an array", Position::none()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::object::Object;
+
+    #[test]
+    fn convert_round_trip() {
+        assert_eq!(i64::try_from(Object::from(42)).unwrap(), 42);
+        assert_eq!(f64::try_from(Object::from(1.5)).unwrap(), 1.5);
+        assert!(bool::try_from(Object::from(true)).unwrap());
+        assert_eq!(String::try_from(Object::from("hello")).unwrap(), "hello");
+        assert_eq!(String::try_from(Object::from("world".to_string())).unwrap(), "world");
+
+        let array: Vec<Object> = Object::from(vec![ Object::from(1), Object::from(2) ]).try_into().unwrap();
+        assert_eq!(array.len(), 2);
+        assert_eq!(i64::try_from(array[1].clone()).unwrap(), 2);
+    }
+
+    #[test]
+    fn self_referential_to_string() {
+        let array = Object::from(vec![ Object::from(1) ]);
+        if let Object::Array(reference) = &array {
+            reference.borrow_mut().push(array.clone());
+        };
+
+        assert_eq!(array.to_string(), "[ 1, [...] ]");
+    }
+
+    #[test]
+    fn float_to_string() {
+        assert_eq!(Object::from(1.0).to_string(), "1.0");
+        assert_eq!(Object::from(0.5).to_string(), "0.5");
+        assert_eq!(Object::from(-2.0).to_string(), "-2.0");
+        assert_eq!(Object::from(1e-10).to_string(), "1e-10");
+        assert_eq!(Object::from(2.5e20).to_string(), "2.5e20");
+        assert_eq!(Object::from(0.1 + 0.2).to_string(), "0.30000000000000004");
+    }
+
+    #[test]
+    fn convert_wrong_type() {
+        assert!(i64::try_from(Object::from("1")).is_err());
+        assert!(bool::try_from(Object::Null).is_err());
+        assert!(Vec::<Object>::try_from(Object::from(1)).is_err());
+    }
+}
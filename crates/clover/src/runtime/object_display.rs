@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::ops::Deref;
+use std::rc::Rc;
+use crate::runtime::object::{Object, Reference};
+use crate::runtime::program::Program;
+
+impl Object {
+    /// Renders the object for people to read, arrays as `[1, 2, 3]`, maps as `{key: value}`,
+    /// instances as `Model { property: value }` and functions as `<function name>`.
+    ///
+    /// Names of models and properties come from the program. A reference which is already being
+    /// rendered is printed as `[...]` (or `{...}`), so self-referential structures are safe to print.
+    pub fn display(&self, program: &Program) -> String {
+        let mut output = String::new();
+        let mut visiting = HashSet::new();
+
+        display_object(self, program, &mut visiting, &mut output, false);
+
+        output
+    }
+}
+
+pub fn reference_address<T: ?Sized>(reference: &Reference<T>) -> usize {
+    Rc::as_ptr(reference) as *const () as usize
+}
+
+fn display_children<'a>(children: impl Iterator<Item = (Option<String>, &'a Object)>, program: &Program, visiting: &mut HashSet<usize>, output: &mut String) {
+    for (i, (name, child)) in children.enumerate() {
+        if i > 0 {
+            output.push_str(", ");
+        };
+
+        if let Some(name) = name {
+            output.push_str(&name);
+            output.push_str(": ");
+        };
+
+        display_object(child, program, visiting, output, true);
+    };
+}
+
+fn display_object(object: &Object, program: &Program, visiting: &mut HashSet<usize>, output: &mut String, nested: bool) {
+    match object {
+        Object::String(value) => {
+            if nested {
+                output.push_str(&format!("{:?}", value.borrow().deref()));
+            } else {
+                output.push_str(value.borrow().deref());
+            }
+        },
+        Object::Array(array) => {
+            let address = reference_address(array);
+            if !visiting.insert(address) {
+                output.push_str("[...]");
+                return;
+            };
+
+            output.push('[');
+            display_children(array.borrow().iter().map(|child| (None, child)), program, visiting, output);
+            output.push(']');
+
+            visiting.remove(&address);
+        },
+        Object::Instance(instance) => {
+            let address = reference_address(instance);
+            if !visiting.insert(address) {
+                output.push_str("{...}");
+                return;
+            };
+
+            let model_index = instance.borrow().model_index;
+            output.push_str(&program.model_name(model_index));
+            output.push_str(" {");
+
+            let property_names: Vec<String> = if let Some(model) = program.models.get(model_index) {
+                model.property_names.iter().map(|name| name.borrow().deref().clone()).collect()
+            } else {
+                Vec::new()
+            };
+
+            let properties = instance.borrow().properties.clone();
+            if !properties.is_empty() {
+                output.push(' ');
+                display_children(properties.iter().enumerate().map(|(i, child)| (Some(property_names.get(i).cloned().unwrap_or_else(|| i.to_string())), child)), program, visiting, output);
+                output.push(' ');
+            };
+
+            output.push('}');
+
+            visiting.remove(&address);
+        },
+        Object::NativeInstance(instance) => {
+            let address = reference_address(instance);
+            if !visiting.insert(address) {
+                output.push_str("{...}");
+                return;
+            };
+
+            let entries = instance.borrow().raw_entries();
+
+            if let Some(mut entries) = entries {
+                entries.sort_by(|left, right| left.0.cmp(&right.0));
+
+                output.push('{');
+                display_children(entries.iter().map(|(key, child)| (Some(key.clone()), child)), program, visiting, output);
+                output.push('}');
+            } else {
+                output.push_str("NativeInstance");
+            };
+
+            visiting.remove(&address);
+        },
+        Object::Model(model_index) => output.push_str(&program.model_name(*model_index)),
+        Object::Function(function_index) | Object::InstanceFunction(_, function_index) => {
+            output.push_str(&format!("<function {}>", program.function_name(*function_index)));
+        },
+        Object::NativeFunction(_) | Object::InstanceNativeFunction(_, _) => output.push_str("native function"),
+        Object::NativeModel(_) => output.push_str("NativeModel"),
+        Object::WeakRef(_) => output.push_str("weak reference"),
+        _ => output.push_str(&object.to_string())
+    }
+}
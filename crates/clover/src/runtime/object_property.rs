@@ -1,14 +1,14 @@
-use crate::runtime::object::{Object, Reference, make_reference};
+use crate::runtime::object::{Object, Integer, Float, Reference, make_reference, format_float};
 use crate::runtime::env::Env;
 use crate::runtime::program::RuntimeError;
 use std::ops::Deref;
 
-pub fn instance_get_integer(env: &mut Env, value: i64, key: &str) -> Result<(), RuntimeError> {
+pub fn instance_get_integer(env: &mut Env, value: Integer, key: &str) -> Result<(), RuntimeError> {
 
     let object = match key {
         "string" => Object::String(make_reference(value.to_string())),
         "integer" => Object::Integer(value),
-        "float" => Object::Float(value as f64),
+        "float" => Object::Float(value as Float),
 
         _ => { return Err(RuntimeError::new("unknown property", env.last_position())); }
     };
@@ -18,10 +18,10 @@ pub fn instance_get_integer(env: &mut Env, value: i64, key: &str) -> Result<(),
     Ok(())
 }
 
-pub fn instance_get_float(env: &mut Env, value: f64, key: &str) -> Result<(), RuntimeError> {
+pub fn instance_get_float(env: &mut Env, value: Float, key: &str) -> Result<(), RuntimeError> {
     let object = match key {
-        "string" => Object::String(make_reference(value.to_string())),
-        "integer" => Object::Integer(value as i64),
+        "string" => Object::String(make_reference(format_float(value))),
+        "integer" => Object::Integer(value as Integer),
         "float" => Object::Float(value),
 
         // Handle unknown property key
@@ -38,20 +38,20 @@ pub fn instance_get_string(env: &mut Env, value: Reference<String>, key: &str) -
     let object = match key {
         "string" => Object::String(value),
         "integer" => {
-            if let Ok(integer) = value.borrow().deref().parse::<i64>() {
+            if let Ok(integer) = value.borrow().deref().parse::<Integer>() {
                 Object::Integer(integer)
             } else {
                 Object::Null
             }
         },
         "float" => {
-            if let Ok(float) = value.borrow().deref().parse::<f64>() {
+            if let Ok(float) = value.borrow().deref().parse::<Float>() {
                 Object::Float(float)
             } else {
                 Object::Null
             }
         },
-        "length" => Object::Integer(value.borrow().len() as i64),
+        "length" => Object::Integer(value.borrow().len() as Integer),
         _ => { return Err(RuntimeError::new("unknown property", env.last_position())); }
     };
 
@@ -63,7 +63,17 @@ pub fn instance_get_string(env: &mut Env, value: Reference<String>, key: &str) -
 pub fn instance_get_array(env: &mut Env, array: Reference<Vec<Object>>, key: &str) -> Result<(), RuntimeError> {
     match key {
         "length" => {
-            env.push(Object::Integer(array.borrow().len() as i64));
+            env.push(Object::Integer(array.borrow().len() as Integer));
+            Ok(())
+        },
+        _ => Err(RuntimeError::new("unknown property", env.last_position()))
+    }
+}
+
+pub fn instance_get_bytes(env: &mut Env, bytes: Reference<Vec<u8>>, key: &str) -> Result<(), RuntimeError> {
+    match key {
+        "length" => {
+            env.push(Object::Integer(bytes.borrow().len() as Integer));
             Ok(())
         },
         _ => Err(RuntimeError::new("unknown property", env.last_position()))
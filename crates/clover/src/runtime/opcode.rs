@@ -16,6 +16,10 @@ pub const OPERATION_LESS_EQUAL: usize = 9;
 pub const OPERATION_AND: usize = 256 | 1;
 pub const OPERATION_OR: usize = 256 | 2;
 
+// a missing property of a model instance reads as null, a missing method to call is an error
+pub const INSTANCE_GET_PROPERTY: u64 = 0;
+pub const INSTANCE_GET_METHOD: u64 = 1;
+
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum OpCode {
@@ -23,6 +27,8 @@ pub enum OpCode {
 
     // operand -> index of constant
     PushConstant    = 0x02,
+    // operand -> integer value minus Program::SMALL_INTEGER_MIN
+    PushInteger     = 0x03,
 
     Return          = 0x05,
 
@@ -36,12 +42,18 @@ pub enum OpCode {
     ContextSet      = 0x0C,
     ContextGet      = 0x0D,
 
+    // operand -> slot, index in global dependencies of the program
+    GlobalSetSlot   = 0x0F,
+    // operand -> slot, index in global dependencies of the program
+    GlobalGetSlot   = 0x10,
+
     // operand -> index of constant (global name)
     GlobalSet       = 0x11,
     // operand -> index of constant (global name)
     GlobalGet       = 0x12,
 
     InstanceSet     = 0x13,
+    // operand -> INSTANCE_GET_PROPERTY or INSTANCE_GET_METHOD
     InstanceGet     = 0x14,
     IndexSet        = 0x15,
     IndexGet        = 0x16,
@@ -65,10 +77,14 @@ pub enum OpCode {
     Jump            = 0x51,
     // operand -> position
     JumpIf          = 0x52,
+    // operand -> position, jump when the top is null, the top is not popped
+    JumpIfNull      = 0x53,
     // operand -> enumerable index
     ForNext         = 0x56,
     // operand -> iterator index
     Iterate         = 0x57,
+    // operand -> enumerable index, same as ForNext but pushes the key before the value
+    ForNextPair     = 0x58,
 
     Unknown         = 0xFF,
 }
@@ -90,6 +106,7 @@ impl Instruction {
 
             0x01 => OpCode::Pop,
             0x02 => OpCode::PushConstant,
+            0x03 => OpCode::PushInteger,
 
             0x05 => OpCode::Return,
 
@@ -100,6 +117,8 @@ impl Instruction {
             0x0C => OpCode::ContextSet,
             0x0D => OpCode::ContextGet,
 
+            0x0F => OpCode::GlobalSetSlot,
+            0x10 => OpCode::GlobalGetSlot,
             0x11 => OpCode::GlobalSet,
             0x12 => OpCode::GlobalGet,
             0x13 => OpCode::InstanceSet,
@@ -121,9 +140,11 @@ impl Instruction {
 
             0x51 => OpCode::Jump,
             0x52 => OpCode::JumpIf,
+            0x53 => OpCode::JumpIfNull,
 
             0x56 => OpCode::ForNext,
             0x57 => OpCode::Iterate,
+            0x58 => OpCode::ForNextPair,
 
             _    => OpCode::Unknown
         }
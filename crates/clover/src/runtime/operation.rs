@@ -1,276 +1,94 @@
-use crate::runtime::object::{Object, Reference, ModelInstance, make_reference};
+use crate::runtime::object::{Object, Integer, Float, Reference, ModelInstance, make_reference};
 use crate::runtime::program::RuntimeError;
-use crate::runtime::opcode::{OPERATION_ADD, OPERATION_SUB, OPERATION_MULTIPLY, OPERATION_DIVIDE, OPERATION_MOD, OPERATION_EQUAL, OPERATION_GREATER, OPERATION_LESS, OPERATION_GREATER_EQUAL, OPERATION_LESS_EQUAL};
+use crate::runtime::opcode::{OPERATION_ADD, OPERATION_EQUAL, OPERATION_GREATER, OPERATION_LESS_EQUAL};
 use crate::runtime::env::Env;
 use std::ops::Deref;
 
-const META_METHODS: &[ &str ] = &[ "_add", "_sub", "_mul", "_div", "_mod", "_eq", "_gt", "_lt", "_gte", "_lte" ];
+// one entry for each operand of the Operation opcode, in the order of the OPERATION_ constants. The name is used
+// for the meta method of models (_add) and for error messages. None from a function means divide by zero, or an
+// integer overflow when the divisor is not zero (MIN / -1).
+struct NumericOperation {
+    name: &'static str,
+    integer: fn(Integer, Integer) -> Option<Object>,
+    float: fn(Float, Float) -> Option<Object>
+}
+
+// comparisons follow floats of Rust, every comparison with NaN is false, so NaN == NaN is false and NaN != NaN is true
+const NUMERIC_OPERATIONS: &[ NumericOperation ] = &[
+    NumericOperation { name: "add", integer: |left, right| Some(Object::Integer(left + right)), float: |left, right| Some(Object::Float(left + right)) },
+    NumericOperation { name: "sub", integer: |left, right| Some(Object::Integer(left - right)), float: |left, right| Some(Object::Float(left - right)) },
+    NumericOperation { name: "mul", integer: |left, right| Some(Object::Integer(left * right)), float: |left, right| Some(Object::Float(left * right)) },
+    NumericOperation { name: "div", integer: |left, right| left.checked_div(right).map(Object::Integer), float: |left, right| (right != 0.0).then(|| Object::Float(left / right)) },
+    NumericOperation { name: "mod", integer: |left, right| left.checked_rem(right).map(Object::Integer), float: |left, right| (right != 0.0).then(|| Object::Float(left % right)) },
+    NumericOperation { name: "eq", integer: |left, right| Some(Object::Boolean(left == right)), float: |left, right| Some(Object::Boolean(left == right)) },
+    NumericOperation { name: "gt", integer: |left, right| Some(Object::Boolean(left > right)), float: |left, right| Some(Object::Boolean(left > right)) },
+    NumericOperation { name: "lt", integer: |left, right| Some(Object::Boolean(left < right)), float: |left, right| Some(Object::Boolean(left < right)) },
+    NumericOperation { name: "gte", integer: |left, right| Some(Object::Boolean(left >= right)), float: |left, right| Some(Object::Boolean(left >= right)) },
+    NumericOperation { name: "lte", integer: |left, right| Some(Object::Boolean(left <= right)), float: |left, right| Some(Object::Boolean(left <= right)) }
+];
+
+fn is_comparison(operand: usize) -> bool {
+    (OPERATION_GREATER..=OPERATION_LESS_EQUAL).contains(&operand)
+}
+
+fn can_concatenate(object: &Object) -> bool {
+    matches!(object, Object::String(_) | Object::Integer(_) | Object::Float(_) | Object::Boolean(_) | Object::Null | Object::Instance(_) | Object::NativeInstance(_) | Object::Array(_))
+}
 
 impl Env {
-    fn handle_numeric_operation<F>(&self, left: i64, right: &Object, op: F, float_op: fn(&Self, f64, &Object) -> Result<Object, RuntimeError>) -> Result<Object, RuntimeError> 
-    where F: Fn(i64, i64) -> i64 
-    {
-        match right {
-            Object::Integer(value) => Ok(Object::Integer(op(left, *value))),
-            Object::Float(_) => float_op(self, left as f64, right),
-            _ => Err(RuntimeError::new("cannot perform numeric operation", self.last_position()))
-        }
-    }
-
-    fn integer_add(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Integer(value) => Ok(Object::Integer(left + value)),
-            Object::Float(_) => self.float_add(left as f64, right),
-            Object::String(value) => Ok(Object::String(make_reference(left.to_string() + value.borrow().deref()))),
-
-            _ => Err(RuntimeError::new("can not add integer with object", self.last_position()))
-        }
-    }
-
-    fn integer_sub(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        self.handle_numeric_operation(left, right, |a, b| a - b, Self::float_sub)
-    }
-
-    fn integer_mul(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        self.handle_numeric_operation(left, right, |a, b| a * b, Self::float_mul)
-    }
-
-    fn integer_div(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Integer(value) => {
-                if *value == 0 {
-                    Err(RuntimeError::new("divide by zero", self.last_position()))
-                } else {
-                    Ok(Object::Integer(left / value))
-                }
-            },
-            Object::Float(_) => self.float_div(left as f64, right),
-
-            _ => Err(RuntimeError::new("can not div integer with object", self.last_position()))
-        }
-    }
-
-    fn integer_mod(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Integer(value) => Ok(Object::Integer(left % value)),
-            Object::Float(_) => self.float_mod(left as f64, right),
-
-            _ => Err(RuntimeError::new("can not sub integer with object", self.last_position()))
-        }
-    }
-
-    fn integer_eq(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Integer(value) => Ok(Object::Boolean(left == *value)),
-            Object::Float(_) => self.float_eq(left as f64, right),
-
-            _ => Err(RuntimeError::new("can not sub integer with object", self.last_position()))
-        }
-    }
-
-    fn integer_gt(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Integer(value) => Ok(Object::Boolean(left > *value)),
-            Object::Float(_) => self.float_gt(left as f64, right),
-
-            _ => Err(RuntimeError::new("can not sub integer with object", self.last_position()))
-        }
-    }
-
-    fn integer_lt(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Integer(value) => Ok(Object::Boolean(left < *value)),
-            Object::Float(_) => self.float_lt(left as f64, right),
-
-            _ => Err(RuntimeError::new("can not sub integer with object", self.last_position()))
-        }
-    }
-
-    fn integer_gte(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Integer(value) => Ok(Object::Boolean(left >= *value)),
-            Object::Float(_) => self.float_gte(left as f64, right),
-
-            _ => Err(RuntimeError::new("can not sub integer with object", self.last_position()))
-        }
-    }
-
-    fn integer_lte(&self, left: i64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Integer(value) => Ok(Object::Boolean(left <= *value)),
-            Object::Float(_) => self.float_lte(left as f64, right),
-
-            _ => Err(RuntimeError::new("can not sub integer with object", self.last_position()))
-        }
-    }
-
-    fn integer_operation(&self, left: i64, right: &Object, operand: usize) -> Result<Object, RuntimeError> {
-        match operand {
-            OPERATION_ADD => self.integer_add(left, right),
-            OPERATION_SUB => self.integer_sub(left, right),
-            OPERATION_MULTIPLY => self.integer_mul(left, right),
-            OPERATION_DIVIDE => self.integer_div(left, right),
-            OPERATION_MOD => self.integer_mod(left, right),
-            OPERATION_EQUAL => self.integer_eq(left, right),
-            OPERATION_GREATER => self.integer_gt(left, right),
-            OPERATION_LESS => self.integer_lt(left, right),
-            OPERATION_GREATER_EQUAL => self.integer_gte(left, right),
-            OPERATION_LESS_EQUAL => self.integer_lte(left, right),
-
-            _ => Err(RuntimeError::new("unknown operation", self.last_position()))
-        }
-    }
-
-    fn float_add(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => Ok(Object::Float(left + value)),
-            Object::Integer(value) => Ok(Object::Float(left + *value as f64)),
-            Object::String(value) => Ok(Object::String(make_reference(left.to_string() + value.borrow().deref()))),
-
-            _ => Err(RuntimeError::new("can not add float with object", self.last_position()))
-        }
-    }
-
-    fn float_sub(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => Ok(Object::Float(left - value)),
-            Object::Integer(value) => Ok(Object::Float(left - *value as f64)),
-
-            _ => Err(RuntimeError::new("can not sub float with object", self.last_position()))
-        }
-    }
-
-    fn float_mul(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => Ok(Object::Float(left * value)),
-            Object::Integer(value) => Ok(Object::Float(left * *value as f64)),
-
-            _ => Err(RuntimeError::new("can not sub float with object", self.last_position()))
-        }
-    }
-
-    fn float_div(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => {
-                if *value == 0.0 {
-                    Err(RuntimeError::new("divide by zero", self.last_position()))
-                } else {
-                    Ok(Object::Float(left / value))
-                }
-            },
-            Object::Integer(value) => {
-                if *value == 0 {
-                    Err(RuntimeError::new("divide by zero", self.last_position()))
-                } else {
-                    Ok(Object::Float(left / *value as f64))
-                }
-            },
-
-            _ => Err(RuntimeError::new("can not sub float with object", self.last_position()))
-        }
-    }
-
-    fn float_mod(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => Ok(Object::Float(left % value)),
-            Object::Integer(value) => Ok(Object::Float(left % *value as f64)),
-
-            _ => Err(RuntimeError::new("can not sub float with object", self.last_position()))
-        }
-    }
-
-    fn float_eq(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => Ok(Object::Boolean(left == *value)),
-            Object::Integer(value) => Ok(Object::Boolean(left == *value as f64)),
-
-            _ => Err(RuntimeError::new("can not sub float with object", self.last_position()))
-        }
-    }
-
-    fn float_gt(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => Ok(Object::Boolean(left > *value)),
-            Object::Integer(value) => Ok(Object::Boolean(left > *value as f64)),
-
-            _ => Err(RuntimeError::new("can not sub float with object", self.last_position()))
-        }
-    }
-
-    fn float_lt(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => Ok(Object::Boolean(left < *value)),
-            Object::Integer(value) => Ok(Object::Boolean(left < *value as f64)),
-
-            _ => Err(RuntimeError::new("can not sub float with object", self.last_position()))
-        }
-    }
-
-    fn float_gte(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => Ok(Object::Boolean(left >= *value)),
-            Object::Integer(value) => Ok(Object::Boolean(left >= *value as f64)),
-
-            _ => Err(RuntimeError::new("can not gte float with object", self.last_position()))
-        }
-    }
+    // integers stay integers, a float on either side makes both floats
+    fn numeric_operation(&self, left: &Object, right: &Object, operand: usize, operation: &NumericOperation) -> Result<Object, RuntimeError> {
+        let result = match (left, right) {
+            (Object::Integer(left), Object::Integer(right)) => (operation.integer)(*left, *right),
+            (Object::Integer(left), Object::Float(right)) => (operation.float)(*left as Float, *right),
+            (Object::Float(left), Object::Integer(right)) => (operation.float)(*left, *right as Float),
+            (Object::Float(left), Object::Float(right)) => (operation.float)(*left, *right),
+
+            _ if is_comparison(operand) => return Err(self.compare_error(left, right)),
+            _ => return Err(RuntimeError::new(&format!("can not {} {} with object", operation.name, left.type_name()), self.last_position()))
+        };
 
-    fn float_lte(&self, left: f64, right: &Object) -> Result<Object, RuntimeError> {
-        match right {
-            Object::Float(value) => Ok(Object::Boolean(left <= *value)),
-            Object::Integer(value) => Ok(Object::Boolean(left <= *value as f64)),
+        result.ok_or_else(|| {
+            let divide_by_zero = match right {
+                Object::Integer(right) => *right == 0,
+                Object::Float(right) => *right == 0.0,
+                _ => true
+            };
 
-            _ => Err(RuntimeError::new("can not sub float with object", self.last_position()))
-        }
+            RuntimeError::new(if divide_by_zero { "divide by zero" } else { "integer overflow" }, self.last_position())
+        })
     }
 
-    fn float_operation(&self, left: f64, right: &Object, operand: usize) -> Result<Object, RuntimeError> {
-        match operand {
-            OPERATION_ADD => self.float_add(left, right),
-            OPERATION_SUB => self.float_sub(left, right),
-            OPERATION_MULTIPLY => self.float_mul(left, right),
-            OPERATION_DIVIDE => self.float_div(left, right),
-            OPERATION_MOD => self.float_mod(left, right),
-            OPERATION_EQUAL => self.float_eq(left, right),
-            OPERATION_GREATER => self.float_gt(left, right),
-            OPERATION_LESS => self.float_lt(left, right),
-            OPERATION_GREATER_EQUAL => self.float_gte(left, right),
-            OPERATION_LESS_EQUAL => self.float_lte(left, right),
-
-            _ => Err(RuntimeError::new("unknown operation", self.last_position()))
-        }
+    fn compare_error(&self, left: &Object, right: &Object) -> RuntimeError {
+        RuntimeError::new(&format!("can not compare {} with {}", left.type_name(), right.type_name()), self.last_position())
     }
 
     fn string_operation(&self, left: &Reference<String>, right: &Object, operand: usize) -> Result<Object, RuntimeError> {
         match operand {
-            OPERATION_ADD => {
-                match right {
-                    Object::String(_) | Object::Integer(_) | Object::Float(_) | Object::Boolean(_) | Object::Null => Ok(Object::String(make_reference(left.borrow().deref().to_string() + &right.to_string()))),
-                    _ => Err(RuntimeError::new("can not add string with object", self.last_position()))
-                }
-            },
+            OPERATION_ADD => Err(RuntimeError::new("can not add string with object", self.last_position())),
             OPERATION_EQUAL => {
                 match right {
                     Object::String(value) => Ok(Object::Boolean(left.borrow().deref().eq(value.borrow().deref()))),
                     _ => Err(RuntimeError::new("can not compare string with object", self.last_position()))
                 }
             }
+            _ if is_comparison(operand) => Err(self.compare_error(&Object::String(left.clone()), right)),
 
             _ => Err(RuntimeError::new("unknown operation", self.last_position()))
         }
     }
 
     fn model_instance_operation(&mut self, left: Reference<ModelInstance>, right: &Object, operand: usize) -> Result<(), RuntimeError> {
-        if operand >= META_METHODS.len() {
-            return Err(RuntimeError::new("unknown operation", self.last_position()));
+        let meta_method_name = match NUMERIC_OPERATIONS.get(operand) {
+            Some(operation) => format!("_{}", operation.name),
+            None => return Err(RuntimeError::new("unknown operation", self.last_position()))
         };
 
-        let meta_method_name = META_METHODS[operand];
-
-        let meta_method_index = if let Some(index) = self.get_program().models[left.borrow().model_index].functions.get(meta_method_name) {
+        let meta_method_index = if let Some(index) = self.get_program().models[left.borrow().model_index].functions.get(&meta_method_name) {
             *index
         } else {
-            return Err(RuntimeError::new("meta method does not exists", self.last_position()));
+            let model_name = self.get_program().model_name(left.borrow().model_index);
+            return Err(RuntimeError::new(&format!("model [{}] does not have meta method [{}]", model_name, meta_method_name), self.last_position()));
         };
 
         self.call_function_by_index(meta_method_index, &[ Object::Instance(left.clone()), right.clone() ])
@@ -294,10 +112,24 @@ impl Env {
             return self.model_instance_operation(model_instance.clone(), right, operand);
         };
 
+        // "+" with a string on either side joins the string forms of both sides, instances show as Model { property: value }
+        if operand == OPERATION_ADD && (left.is_string() || right.is_string()) && can_concatenate(left) && can_concatenate(right) {
+            let string = left.display(self.get_program()) + &right.display(self.get_program());
+            self.push(Object::String(make_reference(string)));
+            return Ok(());
+        };
+
         self.push(match left {
-            Object::Integer(value) => self.integer_operation(*value, right, operand)?,
-            Object::Float(value) => self.float_operation(*value, right, operand)?,
+            Object::Integer(_) | Object::Float(_) => match NUMERIC_OPERATIONS.get(operand) {
+                Some(operation) => self.numeric_operation(left, right, operand, operation)?,
+                None => return Err(RuntimeError::new("unknown operation", self.last_position()))
+            },
             Object::String(value) => self.string_operation(value, right, operand)?,
+            Object::Bytes(value) => match right {
+                Object::Bytes(other) if operand == OPERATION_EQUAL => Object::Boolean(*value.borrow() == *other.borrow()),
+                _ if operand == OPERATION_EQUAL || is_comparison(operand) => return Err(self.compare_error(left, right)),
+                _ => return Err(RuntimeError::new("unknown operation", self.last_position()))
+            },
 
             Object::Null => {
                 if operand == OPERATION_EQUAL {
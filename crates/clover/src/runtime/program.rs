@@ -4,8 +4,9 @@ use std::fmt::{Display, Formatter};
 
 use crate::intermediate::Position;
 use crate::runtime::runtime_info::{DebugInfo, FileInfo};
-use crate::runtime::object::{Object, Reference, make_reference};
-use crate::runtime::opcode::Instruction;
+use crate::runtime::object::{Object, Integer, Float, Reference, make_reference};
+use crate::runtime::opcode::{Instruction, OpCode};
+use crate::runtime::opcode::{OPERATION_ADD, OPERATION_SUB, OPERATION_MULTIPLY, OPERATION_DIVIDE, OPERATION_MOD, OPERATION_EQUAL, OPERATION_GREATER, OPERATION_LESS, OPERATION_GREATER_EQUAL, OPERATION_LESS_EQUAL, OPERATION_AND, OPERATION_OR};
 use crate::runtime::env::Frame;
 use std::io::{Write, Read};
 use byteorder::{ReadBytesExt, LittleEndian, WriteBytesExt};
@@ -21,7 +22,16 @@ use color_print::cprintln;
 pub struct RuntimeError {
     pub message: String,
     pub position: Position,
-    pub stack: LinkedList<Frame>
+    // file of the function which failed, None when the program has no file info or no function was running
+    pub filename: Option<String>,
+    pub stack: LinkedList<Frame>,
+    // frames left out of the stack between the outermost and the innermost ones, see Env::set_stack_trace_limit
+    pub elided_frames: usize,
+    // object raised by script, None when the error is created by runtime or native code,
+    // boxed as the error is returned by almost every function of the runtime and should stay small
+    pub value: Option<Box<Object>>,
+    // set when the script asked to exit, such as IO.exit, it can not be rescued and the host decides what to do
+    pub exit_code: Option<i32>
 }
 
 impl RuntimeError {
@@ -29,15 +39,55 @@ impl RuntimeError {
         RuntimeError {
             message: message.to_string(),
             position,
-            stack: LinkedList::new()
+            filename: None,
+            stack: LinkedList::new(),
+            elided_frames: 0,
+            value: None,
+            exit_code: None
+        }
+    }
+
+    pub fn new_with_value(value: Object, position: Position) -> RuntimeError {
+        RuntimeError {
+            message: value.to_string(),
+            position,
+            filename: None,
+            stack: LinkedList::new(),
+            elided_frames: 0,
+            value: Some(Box::new(value)),
+            exit_code: None
+        }
+    }
+
+    /// Stops the script with the exit code, the env returns it to the host instead of ending the process.
+    pub fn new_exit(exit_code: i32, position: Position) -> RuntimeError {
+        RuntimeError {
+            exit_code: Some(exit_code),
+            ..RuntimeError::new(&format!("exit with code {}", exit_code), position)
+        }
+    }
+
+    // the object received by rescue, the message is used when nothing was raised
+    pub fn to_object(&self) -> Object {
+        if let Some(value) = &self.value {
+            value.as_ref().clone()
+        } else {
+            Object::String(make_reference(self.message.clone()))
+        }
+    }
+
+    /// "filename:line:column: message", the filename is left out when it is not known.
+    pub fn location(&self) -> String {
+        match &self.filename {
+            Some(filename) => format!("{}:{}:{}: {}", filename, self.position.line, self.position.column, self.message),
+            None => format!("{}:{}: {}", self.position.line, self.position.column, self.message)
         }
     }
 }
 
 impl Display for RuntimeError {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
-        formatter.write_fmt(format_args!("at ({}, {}) - {}", self.position.line, self.position.column, self.message))
-        // TODO : add print call stack
+        formatter.write_str(&self.location())
     }
 }
 
@@ -82,8 +132,12 @@ impl Model {
             serialize_string(property_name.as_str(), writer)?;
         }
 
-        writer.write_u32::<LittleEndian>(self.functions.len() as u32)?;
-        for (function_name, &function_index) in &self.functions {
+        // sorted so the same model always serializes to the same bytes
+        let mut functions: Vec<(&String, &usize)> = self.functions.iter().collect();
+        functions.sort();
+
+        writer.write_u32::<LittleEndian>(functions.len() as u32)?;
+        for (function_name, &function_index) in functions {
             serialize_string(function_name, writer)?;
             writer.write_u32::<LittleEndian>(function_index as u32)?;
         };
@@ -185,8 +239,8 @@ pub struct Program {
     // use to init local variable, key is local index, value is constant index
     pub local_values: HashMap<usize, usize>,
 
-    // entry_point - 1 is the function index
-    pub entry_point: usize,
+    // function run by execute, None when the program has no main and no entry was picked
+    pub entry_point: Option<usize>,
 
     pub file_info: Option<FileInfo>,
     pub debug_info: Option<DebugInfo>
@@ -198,12 +252,29 @@ fn serialize_string(string: &str, writer: &mut dyn Write) -> Result<(), std::io:
     Ok(())
 }
 
+// integers and floats are written in the width of Integer and Float, the header records the widths
+fn deserialize_number<const N: usize>(reader: &mut dyn Read) -> Result<[u8; N], std::io::Error> {
+    let mut buffer = [0; N];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+// grows with what is read, a broken length does not allocate more than the reader has
+fn read_bytes(reader: &mut dyn Read, length: usize) -> Result<Vec<u8>, std::io::Error> {
+    let mut bytes = Vec::new();
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+
+    if bytes.len() != length {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "bytes are shorter than their length"));
+    };
+
+    Ok(bytes)
+}
+
 fn deserialize_string(reader: &mut dyn Read) -> Result<String, std::io::Error> {
     let string_length = reader.read_u32::<LittleEndian>()? as usize;
 
-    let mut buffer: Vec<u8> = vec![0; string_length];
-
-    reader.read(&mut buffer)?;
+    let buffer = read_bytes(reader, string_length)?;
 
     String::from_utf8(buffer).map_err(|_| {
         std::io::Error::new(std::io::ErrorKind::InvalidData, "can't convert bytes to string")
@@ -216,11 +287,147 @@ impl Program {
     pub const FALSE_CONSTANT_INDEX: usize = 2;
     pub const DEFAULT_CONSTANTS: [Object; 3] = [ Object::Null, Object::Boolean(true), Object::Boolean(false) ];
 
+    // integers in this range are pushed by PushInteger with the value in the operand, they take no constant
+    pub const SMALL_INTEGER_MIN: Integer = -128;
+    pub const SMALL_INTEGER_MAX: Integer = 256;
+
     const OBJECT_TYPE_INTEGER: u8 = 0;
     const OBJECT_TYPE_FLOAT: u8 = 1;
     const OBJECT_TYPE_STRING: u8 = 2;
     const OBJECT_TYPE_MODEL: u8 = 3;
     const OBJECT_TYPE_FUNCTION: u8 = 4;
+    const OBJECT_TYPE_BYTES: u8 = 5;
+
+    /// Name of the model in source, or `Model(index)` when the program has no file info.
+    pub fn model_name(&self, model_index: usize) -> String {
+        self.file_info.as_ref()
+            .and_then(|file_info| file_info.model_names.get(model_index).cloned())
+            .unwrap_or_else(|| format!("Model({})", model_index))
+    }
+
+    /// Name of the function in source, or `Function(index)` when the program has no file info.
+    pub fn function_name(&self, function_index: usize) -> String {
+        self.file_info.as_ref()
+            .and_then(|file_info| file_info.function_names.get(function_index).cloned())
+            .unwrap_or_else(|| format!("Function({})", function_index))
+    }
+
+    /// Index of the first function with the name, for `Env::execute_by_function_index`. None when there is no such
+    /// function or the program has no file info.
+    pub fn find_function(&self, name: &str) -> Option<usize> {
+        self.file_info.as_ref()?.function_names.iter().position(|function_name| function_name == name)
+    }
+
+    /// Source file of the function, None when the program has no file info.
+    pub fn function_filename(&self, function_index: usize) -> Option<&str> {
+        let file_info = self.file_info.as_ref()?;
+
+        file_info.function_files.get(function_index).and_then(|file_index| file_info.filenames.get(*file_index)).map(|filename| filename.as_str())
+    }
+
+    /// Position of the instruction a frame is running, or was calling from when it is not the innermost frame.
+    pub fn frame_position(&self, frame: &Frame) -> Position {
+        match &self.debug_info {
+            Some(debug_info) if frame.program_counter > 0 => debug_info.functions.get(frame.function_index)
+                .and_then(|positions| positions.get(frame.program_counter - 1))
+                .copied()
+                .unwrap_or(Position::none()),
+            _ => Position::none()
+        }
+    }
+
+    /// "filename:line:column in function" of a frame, for printing the stack of a runtime error.
+    pub fn frame_location(&self, frame: &Frame) -> String {
+        let position = self.frame_position(frame);
+        let function_name = self.function_name(frame.function_index);
+
+        match self.function_filename(frame.function_index) {
+            Some(filename) => format!("{}:{}:{} in {}", filename, position.line, position.column, function_name),
+            None => format!("{}:{} in {}", position.line, position.column, function_name)
+        }
+    }
+
+    /// Readable listing of the constants, globals and the instructions of every function, for looking at
+    /// what the compiler produced. The format is for people and may change.
+    pub fn disassemble(&self) -> String {
+        let mut output = String::new();
+
+        if let Some(entry_point) = self.entry_point {
+            output += &format!("entry point: {}\n", self.function_name(entry_point));
+        };
+
+        output += "constants:\n";
+        for constant_index in Program::DEFAULT_CONSTANTS.len()..self.constants.len() {
+            output += &format!("    {:>4}  {}\n", constant_index, self.describe_constant(constant_index));
+        };
+
+        output += "globals:\n";
+        for (slot, constant_index) in self.global_dependencies.iter().enumerate() {
+            output += &format!("    {:>4}  {}\n", slot, self.describe_constant(*constant_index));
+        };
+
+        for (function_index, function) in self.functions.iter().enumerate() {
+            output += &format!("\nfunction {}", self.function_name(function_index));
+            if let Some(filename) = self.function_filename(function_index) {
+                output += &format!(" ({})", filename);
+            };
+            output += &format!(", {} parameter(s), {} local(s):\n", function.parameter_count, function.local_variable_count);
+
+            for (position, instruction) in function.instructions.iter().enumerate() {
+                let opcode = format!("{:?}", instruction.opcode());
+                let line = format!("    {:04}  {:<14}{:>6}", position, opcode, instruction.operand());
+
+                output += &match self.describe_operand(instruction) {
+                    Some(description) => format!("{}  ; {}\n", line, description),
+                    None => format!("{}\n", line.trim_end())
+                };
+            };
+        };
+
+        output
+    }
+
+    fn describe_constant(&self, constant_index: usize) -> String {
+        match self.constants.get(constant_index) {
+            Some(Object::String(value)) => format!("{:?}", value.borrow().as_str()),
+            Some(Object::Model(model_index)) => format!("model {}", self.model_name(*model_index)),
+            Some(Object::Function(function_index)) => format!("function {}", self.function_name(*function_index)),
+            Some(constant) => constant.to_string(),
+            None => "<out of range>".to_string()
+        }
+    }
+
+    fn describe_operand(&self, instruction: &Instruction) -> Option<String> {
+        let operand = instruction.operand() as usize;
+
+        match instruction.opcode() {
+            OpCode::PushConstant | OpCode::GlobalGet | OpCode::GlobalSet => Some(self.describe_constant(operand)),
+            OpCode::PushInteger => Some((operand as Integer + Program::SMALL_INTEGER_MIN).to_string()),
+            OpCode::GlobalGetSlot | OpCode::GlobalSetSlot => Some(self.global_dependencies.get(operand).map_or("<out of range>".to_string(), |constant_index| self.describe_constant(*constant_index))),
+            OpCode::Operation => Some(match operand {
+                OPERATION_ADD => "+",
+                OPERATION_SUB => "-",
+                OPERATION_MULTIPLY => "*",
+                OPERATION_DIVIDE => "/",
+                OPERATION_MOD => "%",
+                OPERATION_EQUAL => "==",
+                OPERATION_GREATER => ">",
+                OPERATION_LESS => "<",
+                OPERATION_GREATER_EQUAL => ">=",
+                OPERATION_LESS_EQUAL => "<=",
+                OPERATION_AND => "and",
+                OPERATION_OR => "or",
+                _ => "unknown"
+            }.to_string()),
+            _ => None
+        }
+    }
+
+    const INTEGER_WIDTH: u8 = std::mem::size_of::<Integer>() as u8;
+    const FLOAT_WIDTH: u8 = std::mem::size_of::<Float>() as u8;
+
+    // entry point in a serialized program which has none
+    const NO_ENTRY_POINT: u32 = u32::MAX;
 
     // PieScript
     const HEADER: u128 = 0x747069726353656950;
@@ -230,7 +437,9 @@ impl Program {
         writer.write_u8(crate::version::MAJOR)?;
         writer.write_u8(crate::version::MINOR)?;
         writer.write_u8(crate::version::PATCH)?;
-        writer.write_u8(0)?;
+        writer.write_u8(crate::version::BYTECODE_VERSION)?;
+        writer.write_u8(Program::INTEGER_WIDTH)?;
+        writer.write_u8(Program::FLOAT_WIDTH)?;
 
         if compress {
             writer.write_u8(1)?;
@@ -274,11 +483,11 @@ impl Program {
             match object {
                 Object::Integer(value) => {
                     writer.write_u8(Program::OBJECT_TYPE_INTEGER)?;
-                    writer.write_i64::<LittleEndian>(*value)?;
+                    writer.write_all(&value.to_le_bytes())?;
                 },
                 Object::Float(value) => {
                     writer.write_u8(Program::OBJECT_TYPE_FLOAT)?;
-                    writer.write_f64::<LittleEndian>(*value)?;
+                    writer.write_all(&value.to_le_bytes())?;
                 },
                 Object::String(string) => {
                     writer.write_u8(Program::OBJECT_TYPE_STRING)?;
@@ -292,6 +501,11 @@ impl Program {
                     writer.write_u8(Program::OBJECT_TYPE_FUNCTION)?;
                     writer.write_u32::<LittleEndian>(*function_index as u32)?;
                 },
+                Object::Bytes(bytes) => {
+                    writer.write_u8(Program::OBJECT_TYPE_BYTES)?;
+                    writer.write_u32::<LittleEndian>(bytes.borrow().len() as u32)?;
+                    writer.write_all(bytes.borrow().as_slice())?;
+                },
                 _ => {
                     // can't be here
                     return Err(std::io::Error::from_raw_os_error(0));
@@ -309,37 +523,62 @@ impl Program {
         writer.write_u32::<LittleEndian>(self.local_variable_count as u32)?;
 
         // local values
-        writer.write_u32::<LittleEndian>(self.local_values.len() as u32)?;
-        for (&index, &value) in &self.local_values {
+        let mut local_values: Vec<(&usize, &usize)> = self.local_values.iter().collect();
+        local_values.sort();
+
+        writer.write_u32::<LittleEndian>(local_values.len() as u32)?;
+        for (&index, &value) in local_values {
             writer.write_u32::<LittleEndian>(index as u32)?;
             writer.write_u32::<LittleEndian>(value as u32)?;
         };
 
         // entry point
-        writer.write_u32::<LittleEndian>(self.entry_point as u32)?;
+        writer.write_u32::<LittleEndian>(self.entry_point.map_or(Program::NO_ENTRY_POINT, |entry_point| entry_point as u32))?;
+
+        // debug info is optional, files written before it was added end here
+        match &self.debug_info {
+            Some(debug_info) => {
+                writer.write_u8(1)?;
+                debug_info.serialize(writer.as_mut())?;
+            },
+            None => writer.write_u8(0)?
+        };
 
         Ok(())
     }
 
-    pub fn deserialize(reader: &mut dyn Read) -> Result<Program, std::io::Error> {
+    // reads the header up to the compression flag, returns the warnings for a header which is readable but odd
+    pub(crate) fn read_header(reader: &mut dyn Read) -> Result<Vec<String>, std::io::Error> {
+        let mut warnings = Vec::new();
+
         if Program::HEADER != reader.read_u128::<LittleEndian>()? {
-            cprintln!("<yellow>warn: header not match</>");
+            warnings.push("header not match".to_string());
         };
 
-        let version_checks = [
-            (crate::version::MAJOR, "major"),
-            (crate::version::MINOR, "minor"),
-            (crate::version::PATCH, "patch"),
-        ];
+        // crate version which wrote the program, the bytecode version below decides if it can be read
+        let mut crate_version = [0; 3];
+        reader.read_exact(&mut crate_version)?;
 
-        for (expected, label) in version_checks.iter() {
-            if expected != &reader.read_u8()? {
-                cprintln!("<yellow>warn: {} version not match</>", label);
-            }
-        }
+        // instructions of other versions mean something else (such as the locals of for loops), they can not run
+        let version = reader.read_u8()?;
+        if version != crate::version::BYTECODE_VERSION {
+            let message = format!("bytecode version {} is not supported, expected {} (written by clover {}.{}.{})", version, crate::version::BYTECODE_VERSION, crate_version[0], crate_version[1], crate_version[2]);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message));
+        };
 
-        if 0 != reader.read_u8()? {
-            cprintln!("<yellow>warn: header end not match</>");
+        let widths = (reader.read_u8()?, reader.read_u8()?);
+
+        if widths != (Program::INTEGER_WIDTH, Program::FLOAT_WIDTH) {
+            let message = format!("numbers of the program are {} and {} bytes, expected {} and {}", widths.0, widths.1, Program::INTEGER_WIDTH, Program::FLOAT_WIDTH);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message));
+        };
+
+        Ok(warnings)
+    }
+
+    pub fn deserialize(reader: &mut dyn Read) -> Result<Program, std::io::Error> {
+        for warning in Program::read_header(reader)? {
+            cprintln!("<yellow>warn: {}</>", warning);
         };
 
         // Check if compressed
@@ -377,10 +616,10 @@ impl Program {
 
             let constant = match object_type {
                 Program::OBJECT_TYPE_INTEGER => {
-                    Object::Integer(reader.read_i64::<LittleEndian>()?)
+                    Object::Integer(Integer::from_le_bytes(deserialize_number(reader.as_mut())?))
                 },
                 Program::OBJECT_TYPE_FLOAT => {
-                    Object::Float(reader.read_f64::<LittleEndian>()?)
+                    Object::Float(Float::from_le_bytes(deserialize_number(reader.as_mut())?))
                 },
                 Program::OBJECT_TYPE_STRING => {
                     Object::String(make_reference(deserialize_string(reader.as_mut())?))
@@ -391,6 +630,10 @@ impl Program {
                 Program::OBJECT_TYPE_FUNCTION => {
                     Object::Function(reader.read_u32::<LittleEndian>()? as usize)
                 },
+                Program::OBJECT_TYPE_BYTES => {
+                    let length = reader.read_u32::<LittleEndian>()? as usize;
+                    Object::from_bytes(read_bytes(reader.as_mut(), length)?)
+                },
                 _ => {
                     // can't be here
                     return Err(std::io::Error::from_raw_os_error(0));
@@ -417,7 +660,21 @@ impl Program {
             local_values.insert(index, value);
         };
 
-        let entry_point = reader.read_u32::<LittleEndian>()? as usize;
+        let entry_point = match reader.read_u32::<LittleEndian>()? {
+            Program::NO_ENTRY_POINT => None,
+            entry_point => Some(entry_point as usize)
+        };
+
+        let debug_info = match reader.read_u8() {
+            Ok(0) => None,
+            Ok(1) => {
+                let instruction_counts: Vec<usize> = functions.iter().map(|function| function.instructions.len()).collect();
+                Some(DebugInfo::deserialize(reader.as_mut(), &instruction_counts)?)
+            },
+            Ok(tag) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unknown debug info tag {}", tag))),
+            Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => None,
+            Err(error) => return Err(error)
+        };
 
         Ok(Program {
             models,
@@ -431,7 +688,7 @@ impl Program {
             entry_point,
 
             file_info: None,
-            debug_info: None
+            debug_info
         })
     }
 
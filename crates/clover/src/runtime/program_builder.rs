@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::runtime::object::{Object, make_reference};
+use crate::runtime::opcode::{Instruction, OpCode, INSTANCE_GET_METHOD};
+use crate::runtime::program::{Function, Model, Program};
+use crate::runtime::runtime_info::FileInfo;
+
+/// Why `ProgramBuilder::build` rejected the program.
+#[derive(Debug, Clone)]
+pub struct BuildError {
+    pub message: String
+}
+
+impl Display for BuildError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str(&self.message)
+    }
+}
+
+impl Error for BuildError {}
+
+/// Builds a `Program` without source, for code generators. Indices returned by the `add_` functions are
+/// used in instructions and constants, `build` checks every instruction refers to something which exists.
+pub struct ProgramBuilder {
+    models: Vec<Model>,
+    functions: Vec<Function>,
+    constants: Vec<Object>,
+    global_dependencies: Vec<usize>,
+    local_variable_count: usize,
+    entry_point: Option<usize>,
+    file_info: FileInfo
+}
+
+impl ProgramBuilder {
+    pub fn new() -> ProgramBuilder {
+        ProgramBuilder {
+            models: Vec::new(),
+            functions: Vec::new(),
+            constants: Program::DEFAULT_CONSTANTS.to_vec(),
+            global_dependencies: Vec::new(),
+            local_variable_count: 0,
+            entry_point: None,
+            file_info: FileInfo::new()
+        }
+    }
+
+    /// Index of the constant for `PushConstant`, null, true and false are always there (see `Program::NULL_CONSTANT_INDEX`).
+    pub fn add_constant(&mut self, object: Object) -> usize {
+        match object {
+            Object::Null => Program::NULL_CONSTANT_INDEX,
+            Object::Boolean(true) => Program::TRUE_CONSTANT_INDEX,
+            Object::Boolean(false) => Program::FALSE_CONSTANT_INDEX,
+            _ => {
+                self.constants.push(object);
+                self.constants.len() - 1
+            }
+        }
+    }
+
+    /// Index of the function, parameters are the first locals of it so `local_variable_count` counts them too.
+    pub fn add_function(&mut self, name: &str, parameter_count: usize, local_variable_count: usize, instructions: Vec<Instruction>) -> usize {
+        self.functions.push(Function {
+            parameter_count,
+            local_variable_count,
+            instructions,
+            ..Function::new()
+        });
+        self.file_info.function_names.push(name.to_string());
+
+        self.functions.len() - 1
+    }
+
+    /// Index of the model, push `Object::Model(index)` as a constant to create instances of it.
+    pub fn add_model(&mut self, name: &str, property_names: &[ &str ]) -> usize {
+        let mut model = Model::new();
+
+        for property_name in property_names {
+            model.add_property(property_name);
+        };
+
+        self.models.push(model);
+        self.file_info.model_names.push(name.to_string());
+
+        self.models.len() - 1
+    }
+
+    /// Slot of a global (such as a native model added to the env) for `GlobalGetSlot` and `GlobalSetSlot`.
+    pub fn add_global(&mut self, name: &str) -> usize {
+        let constant_index = self.add_constant(Object::String(make_reference(name.to_string())));
+        self.global_dependencies.push(constant_index);
+
+        self.global_dependencies.len() - 1
+    }
+
+    /// Number of locals of the program itself, shared by all functions through `ContextGet` and `ContextSet`.
+    pub fn set_local_variable_count(&mut self, local_variable_count: usize) {
+        self.local_variable_count = local_variable_count;
+    }
+
+    /// Function run by `Env::execute`.
+    pub fn set_entry_point(&mut self, function_index: usize) {
+        self.entry_point = Some(function_index);
+    }
+
+    pub fn build(self) -> Result<Program, BuildError> {
+        if let Some(entry_point) = self.entry_point {
+            if entry_point >= self.functions.len() {
+                return Err(build_error(format!("entry point [{}] is not a function", entry_point)));
+            };
+        };
+
+        for (constant_index, constant) in self.constants.iter().enumerate().skip(Program::DEFAULT_CONSTANTS.len()) {
+            match constant {
+                Object::Integer(_) | Object::Float(_) | Object::String(_) | Object::Bytes(_) => {},
+                Object::Model(model_index) if *model_index < self.models.len() => {},
+                Object::Function(function_index) if *function_index < self.functions.len() => {},
+                Object::Model(_) | Object::Function(_) => return Err(build_error(format!("constant {} refers to a {} which does not exist", constant_index, constant.type_name()))),
+                _ => return Err(build_error(format!("a constant can not be a {}", constant.type_name())))
+            };
+        };
+
+        for (function_index, function) in self.functions.iter().enumerate() {
+            self.check_function(function).map_err(|message| build_error(format!("{} in function [{}]", message, self.file_info.function_names[function_index])))?;
+        };
+
+        Ok(Program {
+            models: self.models,
+            functions: self.functions,
+            constants: self.constants,
+            global_dependencies: self.global_dependencies,
+            local_variable_count: self.local_variable_count,
+            local_values: HashMap::new(),
+            entry_point: self.entry_point,
+            file_info: Some(self.file_info),
+            debug_info: None
+        })
+    }
+
+    fn check_function(&self, function: &Function) -> Result<(), String> {
+        if function.local_variable_count < function.parameter_count {
+            return Err("fewer locals than parameters".to_string());
+        };
+
+        match function.instructions.last() {
+            Some(instruction) if instruction.opcode() == OpCode::Return => {},
+            _ => return Err("last instruction is not return".to_string())
+        };
+
+        for (position, instruction) in function.instructions.iter().enumerate() {
+            let operand = instruction.operand() as usize;
+
+            let limit = match instruction.opcode() {
+                OpCode::PushConstant => self.constants.len(),
+                OpCode::LocalGet | OpCode::LocalSet | OpCode::LocalInit | OpCode::Iterate => function.local_variable_count,
+                // the loop also uses the two locals after the enumerable, the iterator and the length
+                OpCode::ForNext | OpCode::ForNextPair => function.local_variable_count.saturating_sub(2),
+                OpCode::ContextGet | OpCode::ContextSet => self.local_variable_count,
+                OpCode::GlobalGetSlot | OpCode::GlobalSetSlot => self.global_dependencies.len(),
+                OpCode::Jump | OpCode::JumpIf | OpCode::JumpIfNull => function.instructions.len(),
+                OpCode::InstanceGet => INSTANCE_GET_METHOD as usize + 1,
+                OpCode::GlobalGet | OpCode::GlobalSet => return Err(format!("instruction {} uses a global by name, use add_global and the slot instead", position)),
+                OpCode::Unknown => return Err(format!("instruction {} is unknown", position)),
+                _ => continue
+            };
+
+            if operand >= limit {
+                return Err(format!("operand of instruction {} ({:?}) is out of range", position, instruction));
+            };
+        };
+
+        Ok(())
+    }
+}
+
+impl Default for ProgramBuilder {
+    fn default() -> Self { ProgramBuilder::new() }
+}
+
+fn build_error(message: String) -> BuildError {
+    BuildError { message }
+}
@@ -1,4 +1,7 @@
-use crate::intermediate::Positions;
+use std::io::{Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::intermediate::{Position, Positions};
 
 #[derive(Debug, Clone)]
 pub struct DebugInfo {
@@ -11,6 +14,70 @@ impl DebugInfo {
             functions: Vec::new()
         }
     }
+
+    // consecutive instructions mostly come from the same line, so positions are written as runs of one line,
+    // a run is the line, the instruction count and the column of every instruction
+    pub fn serialize(&self, writer: &mut dyn Write) -> Result<(), std::io::Error> {
+        writer.write_u32::<LittleEndian>(self.functions.len() as u32)?;
+
+        for positions in &self.functions {
+            writer.write_u32::<LittleEndian>(positions.len() as u32)?;
+
+            for run in positions.chunk_by(|left, right| left.line == right.line) {
+                writer.write_u16::<LittleEndian>(run[0].line)?;
+                writer.write_u32::<LittleEndian>(run.len() as u32)?;
+
+                for position in run {
+                    writer.write_u16::<LittleEndian>(position.column)?;
+                };
+            };
+        };
+
+        Ok(())
+    }
+
+    // instruction_counts has the count of every function of the program, the debug info must have a position
+    // for each instruction, the runtime looks positions up without checking
+    pub fn deserialize(reader: &mut dyn Read, instruction_counts: &[ usize ]) -> Result<DebugInfo, std::io::Error> {
+        let function_count = reader.read_u32::<LittleEndian>()? as usize;
+        if function_count != instruction_counts.len() {
+            return Err(invalid_debug_info(format!("debug info has {} functions, the program has {}", function_count, instruction_counts.len())));
+        };
+
+        let mut functions = Vec::new();
+
+        for (function_index, &instruction_count) in instruction_counts.iter().enumerate() {
+            let position_count = reader.read_u32::<LittleEndian>()? as usize;
+            if position_count != instruction_count {
+                return Err(invalid_debug_info(format!("debug info of function {} has {} positions, it has {} instructions", function_index, position_count, instruction_count)));
+            };
+
+            let mut positions = Vec::with_capacity(instruction_count);
+
+            while positions.len() < instruction_count {
+                let line = reader.read_u16::<LittleEndian>()?;
+                let run_length = reader.read_u32::<LittleEndian>()? as usize;
+
+                if run_length > instruction_count - positions.len() {
+                    return Err(invalid_debug_info(format!("debug info of function {} has more positions than instructions", function_index)));
+                };
+
+                for _ in 0..run_length {
+                    positions.push(Position::new(line, reader.read_u16::<LittleEndian>()?));
+                };
+            };
+
+            functions.push(positions);
+        };
+
+        Ok(DebugInfo {
+            functions
+        })
+    }
+}
+
+fn invalid_debug_info(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
 }
 
 #[derive(Debug, Clone)]
@@ -35,4 +102,15 @@ impl FileInfo {
             function_names: Vec::new()
         }
     }
-}
\ No newline at end of file
+}
+
+/// Instructions of one function run since coverage was enabled, see `Env::enable_coverage`.
+#[derive(Debug, Clone)]
+pub struct FunctionCoverage {
+    pub function_index: usize,
+    pub name: String,
+    pub covered: usize,
+    pub total: usize,
+    // source positions of the instructions never run, empty when the program has no debug info
+    pub uncovered_positions: Vec<Position>
+}
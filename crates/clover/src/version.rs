@@ -1,3 +1,24 @@
 pub const MAJOR: u8 = 0;
 pub const MINOR: u8 = 1;
-pub const PATCH: u8 = 13;
\ No newline at end of file
+pub const PATCH: u8 = 13;
+
+/// Version of the format `Program::serialize` writes, bumped only when the format changes. `Program::deserialize`
+/// checks this one, the crate version in a saved program is only for information.
+///
+/// - 0: the format before versions, this byte was always 0
+/// - 2: compared to 0
+///   - the header has the widths of integers and floats in bytes after this version
+///   - the entry point is the function index, `u32::MAX` when there is none, instead of function index + 1
+///   - a byte after the entry point tells whether the positions of instructions (debug info) follow
+///   - a constant can be bytes, type 5 with a u32 length
+///   - `PushInteger` (0x03) pushes its operand plus `Program::SMALL_INTEGER_MIN`
+///   - `GlobalSetSlot` (0x0F) and `GlobalGetSlot` (0x10) use the index of a global dependency as operand
+///   - `JumpIfNull` (0x53) jumps when the top is null and keeps it, for `?.`
+///   - `ForNextPair` (0x58) is `ForNext` pushing the key before the value
+///   - the operand of `InstanceGet` is `INSTANCE_GET_PROPERTY` (0) or `INSTANCE_GET_METHOD` (1), getting a
+///     missing method of a model instance is an error
+///   - for loops keep the length of the array in the local after the iterator (enumerable + 2)
+///
+/// 1 was only used while the format changed and never released. Programs of any version but this one are
+/// rejected, compile the source again.
+pub const BYTECODE_VERSION: u8 = 2;